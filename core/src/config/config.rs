@@ -3,6 +3,7 @@ use std::collections::{ BTreeMap };
 use serde_yaml;
 
 use crate::file::read_file;
+use crate::Point2f;
 use super::ConfigError;
 
 pub struct Config {
@@ -67,6 +68,21 @@ impl Config {
         }
     }
 
+    pub fn get_bool(&self, key: &str) -> Result<bool, ConfigError> {
+        match self.entry_map.get(key) {
+            Some(v) if v.is_bool() => Ok(v.as_bool().unwrap()),
+            Some(_other_val) => Err(ConfigError::InvalidValueType(key.to_owned(), "bool".to_owned())),
+            _ => Err(ConfigError::UnknownKey(key.to_owned()))
+        }
+    }
+
+    pub fn get_bool_or_default(&self, key: &str, default: bool) -> bool {
+        match self.get_bool(key) {
+            Ok(v) => v,
+            Err(_) => default
+        }
+    }
+
     pub fn get_float(&self, key: &str) -> Result<f32, ConfigError> {
         match self.entry_map.get(key) {
             Some(v) if v.is_f64() => Ok(v.as_f64().unwrap() as f32),
@@ -81,4 +97,36 @@ impl Config {
             Err(_) => default
         }
     }
+
+    /// Sequence of [x, y] pairs
+    pub fn get_point_list(&self, key: &str) -> Result<Vec<Point2f>, ConfigError> {
+        let invalid_type = || ConfigError::InvalidValueType(key.to_owned(), "point list".to_owned());
+        match self.entry_map.get(key) {
+            Some(v) if v.is_sequence() => {
+                let mut points = Vec::new();
+                for entry in v.as_sequence().unwrap() {
+                    match entry.as_sequence() {
+                        Some(coords) if coords.len() == 2 => {
+                            let x = coords[0].as_f64().ok_or_else(invalid_type)?;
+                            let y = coords[1].as_f64().ok_or_else(invalid_type)?;
+                            points.push(Point2f::new(x as f32, y as f32));
+                        },
+                        _ => return Err(invalid_type())
+                    }
+                }
+                Ok(points)
+            },
+            Some(_other_val) => Err(invalid_type()),
+            _ => Err(ConfigError::UnknownKey(key.to_owned()))
+        }
+    }
+}
+
+// Without any entries, every value falls back to its default
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            entry_map: BTreeMap::new()
+        }
+    }
 }
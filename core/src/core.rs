@@ -5,7 +5,7 @@ use gl;
 use gl::types::GLsizei;
 use glutin;
 use glutin::dpi::{LogicalSize, PhysicalPosition};
-use glutin::event::{DeviceEvent, ElementState, Event, KeyboardInput, WindowEvent};
+use glutin::event::{DeviceEvent, ElementState, Event, KeyboardInput, MouseButton, WindowEvent};
 use glutin::event_loop::{ControlFlow, EventLoop};
 use glutin::window::Window;
 use glutin::{ContextWrapper, PossiblyCurrent};
@@ -96,6 +96,12 @@ impl Core {
                 } => {
                     handle_keyboard_event(&input, &mut state_input);
                 }
+                Event::WindowEvent {
+                    event: WindowEvent::MouseInput { state, button, .. },
+                    ..
+                } => {
+                    handle_mouse_button_event(state, button, &mut state_input);
+                }
 
                 Event::WindowEvent { event, .. } => {
                     match handle_window_event(&event, context.window()) {
@@ -163,6 +169,10 @@ fn handle_keyboard_event(event: &KeyboardInput, input: &mut Input) {
         0x20 => {
             input.set_key_pressed("D", pressed);
         }
+        0x02..=0x06 => {
+            let number = (event.scancode - 1).to_string();
+            input.set_key_pressed(&number, pressed);
+        }
         0x39 => {
             input.set_key_pressed("SPACE", pressed);
         }
@@ -175,6 +185,22 @@ fn handle_keyboard_event(event: &KeyboardInput, input: &mut Input) {
         0x3D => {
             input.set_key_pressed("F3", pressed);
         }
+        0x3E => {
+            input.set_key_pressed("F4", pressed);
+        }
+        _ => {}
+    }
+}
+
+fn handle_mouse_button_event(state: ElementState, button: MouseButton, input: &mut Input) {
+    let pressed = match state {
+        ElementState::Pressed => true,
+        ElementState::Released => false,
+    };
+    match button {
+        MouseButton::Left => input.set_key_pressed("MOUSE_LEFT", pressed),
+        MouseButton::Right => input.set_key_pressed("MOUSE_RIGHT", pressed),
+        MouseButton::Middle => input.set_key_pressed("MOUSE_MIDDLE", pressed),
         _ => {}
     }
 }
@@ -61,6 +61,10 @@ impl Triangle {
         }
     }
 
+    pub fn get_normal(&self) -> Point3f {
+        self.triangle_normal
+    }
+
     pub fn as_vertices(&self) -> &[Vertex; 3] {
         &self.vertex
     }
@@ -54,6 +54,8 @@ impl Texture {
             0 => gl::TEXTURE0,
             1 => gl::TEXTURE1,
             2 => gl::TEXTURE2,
+            3 => gl::TEXTURE3,
+            4 => gl::TEXTURE4,
             _ => unreachable!("Texture slot too high"),
         };
         unsafe {
@@ -208,6 +210,80 @@ impl Texture {
         }
         Ok(())
     }
+
+    /// Copies the part of the image at the given origin into one layer of a 2d array texture
+    pub fn load_array_layer(
+        &mut self,
+        layer: i32,
+        img_origin: Point2i,
+        img: &RgbaImage,
+    ) -> Result<(), GraphicsError> {
+        if self.tex_type == gl::TEXTURE_2D_ARRAY && self.format == gl::RGBA8 {
+            debug_assert!(layer >= 0 && layer < self.size[2]);
+            self.activate(0);
+            let sub_img = img
+                .view(
+                    img_origin[0] as u32,
+                    img_origin[1] as u32,
+                    self.size[0] as u32,
+                    self.size[1] as u32,
+                )
+                .to_image();
+            let pixels: Vec<u8> = sub_img.into_raw();
+            unsafe {
+                gl::TexSubImage3D(
+                    self.tex_type,
+                    0,
+                    0,
+                    0,
+                    layer,
+                    self.size[0],
+                    self.size[1],
+                    1,
+                    self.channels,
+                    self.channel_type,
+                    pixels.as_ptr() as *const _,
+                );
+            }
+            check_opengl_error("gl::TexSubImage3D")?;
+            handle_new_array_image(self.mipmaps)?;
+            self.deactivate();
+        } else {
+            warn!("Wanted to load array layer for non-array texture");
+        }
+        Ok(())
+    }
+}
+
+fn handle_new_array_image(mipmaps: bool) -> Result<(), OpenglError> {
+    unsafe {
+        gl::TexParameteri(
+            gl::TEXTURE_2D_ARRAY,
+            gl::TEXTURE_WRAP_S,
+            gl::REPEAT as GLint,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D_ARRAY,
+            gl::TEXTURE_WRAP_T,
+            gl::REPEAT as GLint,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D_ARRAY,
+            gl::TEXTURE_MIN_FILTER,
+            gl::NEAREST_MIPMAP_NEAREST as GLint,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D_ARRAY,
+            gl::TEXTURE_MAG_FILTER,
+            gl::NEAREST as GLint,
+        );
+        check_opengl_error("gl::TexParameteri")?;
+        if mipmaps {
+            gl::GenerateMipmap(gl::TEXTURE_2D_ARRAY);
+            check_opengl_error("gl::GenerateMipmap")?;
+        }
+    }
+    Ok(())
 }
 
 fn handle_new_cubemap_image(mipmaps: bool) -> Result<(), OpenglError> {
@@ -32,8 +32,13 @@ pub fn create_texture_storage(
     texture_format: GLenum,
     use_mipmaps: bool,
 ) -> Result<(), GraphicsError> {
+    // layers of array textures don't shrink with the mipmap level
+    let mipmap_size = match texture_type {
+        t if t == gl::TEXTURE_2D_ARRAY => Point3i::new(size[0], size[1], 0),
+        _ => size,
+    };
     let mipmaps = match use_mipmaps {
-        true => calculate_mipmaps(size),
+        true => calculate_mipmaps(mipmap_size),
         false => 1,
     };
     match texture_type {
@@ -2,6 +2,7 @@ use std::collections::{BTreeMap, BTreeSet};
 
 use super::Light;
 use crate::graphics::{GraphicsError, ShaderProgram};
+use crate::Point3f;
 
 const MAX_SCENE_LIGHTS: u8 = 8;
 
@@ -30,6 +31,13 @@ impl SceneLights {
         self.light_map.get_mut(light_name).map(|(_, l)| l)
     }
 
+    /// Moves all lights, e.g. when the render origin moves
+    pub fn translate_all(&mut self, offset: Point3f) {
+        for (_, light) in self.light_map.values_mut() {
+            light.set_world_pos(light.get_world_pos() + offset);
+        }
+    }
+
     fn get_next_index(&self) -> Option<u8> {
         let mut used_indices = BTreeSet::new();
         for (_, (i, _)) in &self.light_map {
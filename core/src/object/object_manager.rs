@@ -5,7 +5,7 @@ use std::rc::Rc;
 use super::{FilePrototype, Object, ObjectError, ObjectPrototype};
 use crate::file::read_file;
 use crate::graphics::GraphicsError;
-use crate::traits::{RenderInfo, Renderable};
+use crate::traits::{RenderInfo, Renderable, Translatable};
 use crate::Point3f;
 
 pub struct ObjectManager {
@@ -102,6 +102,13 @@ impl ObjectManager {
         }
     }
 
+    /// Moves all active objects, e.g. when the render origin moves
+    pub fn translate_all(&mut self, offset: Point3f) {
+        for obj in &mut self.active_list {
+            obj.mod_translation(offset);
+        }
+    }
+
     pub fn unload_distant(&mut self, center: Point3f, distant: f32) -> usize {
         let begin_len = self.active_list.len();
         self.active_list
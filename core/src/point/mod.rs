@@ -1,8 +1,10 @@
 pub mod point2;
+pub mod point2d;
 pub mod point2f;
 pub mod point2i;
 
 pub mod point3;
+pub mod point3d;
 pub mod point3f;
 pub mod point3i;
 
@@ -10,10 +12,12 @@ pub mod point4;
 pub mod point4f;
 
 pub use self::point2::Point2;
+pub use self::point2d::Point2d;
 pub use self::point2f::Point2f;
 pub use self::point2i::Point2i;
 
 pub use self::point3::Point3;
+pub use self::point3d::Point3d;
 pub use self::point3f::Point3f;
 pub use self::point3i::Point3i;
 
@@ -0,0 +1,26 @@
+use super::{Point2, Point2f, Point2i};
+
+/// Double precision, for absolute positions far away from the origin
+pub type Point2d = Point2<f64>;
+
+impl Point2d {
+    pub fn length(&self) -> f64 {
+        (self[0].powf(2.) + self[1].powf(2.)).sqrt()
+    }
+
+    pub fn as_f32(&self) -> Point2f {
+        Point2f::new(self[0] as f32, self[1] as f32)
+    }
+}
+
+impl From<Point2f> for Point2d {
+    fn from(p: Point2f) -> Point2d {
+        Point2d::new(p[0] as f64, p[1] as f64)
+    }
+}
+
+impl From<Point2i> for Point2d {
+    fn from(p: Point2i) -> Point2d {
+        Point2d::new(p[0] as f64, p[1] as f64)
+    }
+}
@@ -20,6 +20,10 @@ impl Point2f {
     pub fn length(&self) -> f32 {
         glm::length(self.0)
     }
+
+    pub fn dot(&self, rhs: &Self) -> f32 {
+        glm::dot(self.0, rhs.0)
+    }
 }
 
 impl Ord for Point2f {
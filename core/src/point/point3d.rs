@@ -0,0 +1,16 @@
+use super::{Point3, Point3f};
+
+/// Double precision, for absolute positions far away from the origin
+pub type Point3d = Point3<f64>;
+
+impl Point3d {
+    pub fn as_f32(&self) -> Point3f {
+        Point3f::new(self[0] as f32, self[1] as f32, self[2] as f32)
+    }
+}
+
+impl From<Point3f> for Point3d {
+    fn from(p: Point3f) -> Point3d {
+        Point3d::new(p[0] as f64, p[1] as f64, p[2] as f64)
+    }
+}
@@ -1,7 +1,7 @@
 use glm;
 use std::cmp::Ordering;
 
-use super::Point3;
+use super::{Point3, Point3i};
 
 pub type Point3f = Point3<f32>;
 
@@ -57,6 +57,12 @@ impl PartialEq for Point3f {
 
 impl Eq for Point3f {}
 
+impl From<Point3i> for Point3f {
+    fn from(p: Point3i) -> Point3f {
+        Point3f::new(p[0] as f32, p[1] as f32, p[2] as f32)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
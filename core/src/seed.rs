@@ -45,6 +45,10 @@ impl Seed {
         Some(Self(seed))
     }
 
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
     pub fn mix_with_point(&self, point: Point2i) -> Self {
         let mut seed = self.0.clone();
         for (point_byte, seed_byte) in point[0].to_le_bytes().iter().zip(seed.iter_mut()) {
@@ -1,5 +1,5 @@
 use std::io::{Read, Error};
 
 pub trait Loadable {
-    fn load(&self, reader: &impl Read) -> Result<(), Error>;
+    fn load(&mut self, reader: &mut impl Read) -> Result<(), Error>;
 }
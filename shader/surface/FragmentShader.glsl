@@ -4,10 +4,21 @@ in vec4 gl_FragCoord;
 in VertexData {
     vec3 normal;
     vec3 frag_pos;
+    vec2 texel_pos;
 } vertex;
 
 out vec3 color;
 
+uniform sampler2D heightmap;
+uniform sampler2D analysis_map;
+uniform int analysis_available;
+uniform sampler2D splat_map;
+uniform int splat_available;
+uniform sampler2D road_mask;
+uniform int road_available;
+// volumetric chunks can't take their normals from the heightmap
+uniform int vertex_normals;
+
 uniform sampler2DArray texture_array;
 uniform vec3 view_pos;
 uniform vec3 fog_color;
@@ -24,8 +35,13 @@ uniform struct LightSource {
 }scene_lights[2];
 
 const float FOG_DEPTH = 0.0004;
+const float WETNESS_MIN = 6.;
+const float WETNESS_RANGE = 6.;
+// texture repetitions per world unit
+const float TEXTURE_SCALE = 0.25;
+const vec3 ROAD_COLOR = vec3(0.45, 0.4, 0.33);
 
-vec3 calculate_light_factor(int index) {
+vec3 calculate_light_factor(int index, vec3 normal) {
     vec3 ambient = scene_lights[index].color * scene_lights[index].ambient_intensity;
     vec3 diffuse = vec3(0., 0., 0.);
     vec3 specular = vec3(0., 0., 0.);
@@ -36,12 +52,12 @@ vec3 calculate_light_factor(int index) {
 
     light_dir = normalize(light_dir);
 
-    float lambert = max(0., dot(vertex.normal, light_dir));
+    float lambert = max(0., dot(normal, light_dir));
 
     if (lambert > 0.) {
         diffuse = scene_lights[index].color * lambert * scene_lights[index].diffuse_intensity * scene_lights[index].absolute_intensity / distance;
         vec3 view_dir = normalize(view_pos - vertex.frag_pos);
-		vec3 reflect_dir = reflect(-light_dir, vertex.normal);
+		vec3 reflect_dir = reflect(-light_dir, normal);
         float spec_angle = max(dot(reflect_dir, view_dir), 0.);
         float spec_strength = pow(spec_angle, scene_lights[index].specular_shininess);
         specular = scene_lights[index].color * spec_strength * scene_lights[index].specular_intensity * scene_lights[index].absolute_intensity / distance;
@@ -55,23 +71,28 @@ float calculate_fog_factor() {
 }
 
 void main() {
-    float slope = max(0, dot(vertex.normal, vec3(0., 0., 1.)));
-	float height = vertex.frag_pos.z;
-
-	color = vec3(0.2, 0.6, 0.2);
-	if (height > 140.){
-		color = mix(vec3(0.8, 0.8, 0.8), color, 1 - min(1., (height - 140.)/40.));
-	} else if (height < 10.) {
-		color = mix(vec3(0.8, 0.7, 0.5), color, 1 - max(0., min(1., (10. - height) /5.)));
-    }
-
-	float rock_factor = min(1., (max(0., (height - 120)) / 10.));
-
-	color = mix(vec3(0.3, 0.3, 0.3), color, pow(slope, 4.));
+	vec2 uv = vertex.frag_pos.xy * TEXTURE_SCALE;
+	vec3 normal = normalize(vertex_normals != 0 ? vertex.normal : texture(heightmap, vertex.texel_pos).gba);
+	// material weights, in the order of the texture array layers
+	vec4 splat = splat_available != 0 ? texture(splat_map, vertex.texel_pos) : vec4(1., 0., 0., 0.);
+	vec4 weights = splat / max(1e-3, dot(splat, vec4(1.)));
+	color = vec3(0.);
+	for (int i = 0; i < 4; i++) {
+		if (weights[i] > 0.) {
+			color += weights[i] * texture(texture_array, vec3(uv, i)).rgb;
+		}
+	}
+
+	// slope, curvature, topographic position, wetness
+	vec4 analysis = analysis_available != 0 ? texture(analysis_map, vertex.texel_pos) : vec4(0.);
+	float wetness = clamp((analysis.a - WETNESS_MIN) / WETNESS_RANGE, 0., 1.);
+	color = mix(color, color * vec3(0.6, 0.7, 0.6), wetness);
+	float road = road_available != 0 ? texture(road_mask, vertex.texel_pos).r : 0.;
+	color = mix(color, ROAD_COLOR, clamp(road, 0., 1.));
 
     vec3 light_factor = vec3(0., 0., 0.);
     for (int i = 0; i < active_lights; i++) {
-        light_factor += calculate_light_factor(i);
+        light_factor += calculate_light_factor(i, normal);
         if (light_factor.x >= 1. && light_factor.y >= 1. && light_factor.z >= 1.) {
             light_factor = vec3(1., 1., 1.);
             break;
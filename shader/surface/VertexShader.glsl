@@ -1,21 +1,25 @@
 #version 330 core
 
 layout(location = 0) in vec3 vertex_pos;
+layout(location = 2) in vec3 vertex_normal;
 
 out VertexData {
   vec3 normal;
   vec3 frag_pos;
+  vec2 texel_pos;
 } vertex;
 
-uniform sampler2D heightmap;
 uniform mat4 mvp;
 uniform mat4 model;
-uniform int chunk_size;
+uniform int grid_size;
+uniform float grid_spacing;
 
 void main() {
-  vec4 map_texel = texture2D(heightmap, vertex_pos.xy / chunk_size);
-  vec4 transformed_vertex = vec4(vertex_pos.xy, map_texel.r, 1.);
+  vec2 texel_pos = (vertex_pos.xy / grid_spacing + 0.5) / grid_size;
+  vec4 transformed_vertex = vec4(vertex_pos, 1.);
   gl_Position = mvp * transformed_vertex;
-  vertex.normal = map_texel.gba;
+  vertex.normal = vertex_normal;
   vertex.frag_pos = vec3(model * transformed_vertex);
+  // the maps are sampled per fragment, coarse triangles would smear them
+  vertex.texel_pos = texel_pos;
 }
@@ -18,8 +18,8 @@ out VertexData {
 
 void main() {
 	gl_Position = mvp * vec4(vertex_pos, 1.);
-	vertex.uv = (vertex_pos.xy + 1.)/2. * 1024.;
-	vertex.normal = vec3(0., 0., 1.);
 	vertex.world_pos = vec3(model * vec4(vertex_pos, 1.));
+	vertex.uv = vertex.world_pos.xy / 2.;
+	vertex.normal = vec3(0., 0., 1.);
 
 }
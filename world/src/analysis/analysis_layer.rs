@@ -0,0 +1,30 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AnalysisLayer {
+    Slope,
+    Aspect,
+    Curvature,
+    TopographicPosition,
+    Wetness,
+}
+
+impl AnalysisLayer {
+    pub fn as_slice() -> &'static [AnalysisLayer] {
+        &[
+            AnalysisLayer::Slope,
+            AnalysisLayer::Aspect,
+            AnalysisLayer::Curvature,
+            AnalysisLayer::TopographicPosition,
+            AnalysisLayer::Wetness,
+        ]
+    }
+
+    // Layers packed into the rgba channels of the analysis texture
+    pub fn texture_layers() -> &'static [AnalysisLayer; 4] {
+        &[
+            AnalysisLayer::Slope,
+            AnalysisLayer::Curvature,
+            AnalysisLayer::TopographicPosition,
+            AnalysisLayer::Wetness,
+        ]
+    }
+}
@@ -0,0 +1,100 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::convert::TryInto;
+use std::io::{Error, Read, Write};
+
+use crate::height_map::read_map_size;
+use crate::HeightSampler;
+use core::traits::{Loadable, Saveable};
+use core::{GraphicsError, Point2i, Texture, TextureBuilder};
+
+#[derive(Clone)]
+pub struct AnalysisMap {
+    size: i32,
+    scale_factor: f32,
+    value_list: Vec<f32>,
+}
+
+impl AnalysisMap {
+    pub fn new(size: i32, scale_factor: f32) -> Self {
+        debug_assert!(size > 0);
+        let mut value_list = Vec::new();
+        value_list.resize((size * size) as usize, 0.);
+        Self {
+            size: size,
+            scale_factor: scale_factor,
+            value_list: value_list,
+        }
+    }
+
+    pub fn get_size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn get_list(&self) -> &[f32] {
+        self.value_list.as_slice()
+    }
+
+    pub fn get(&self, pos: Point2i) -> f32 {
+        self.value_list[self.calculate_index(pos)]
+    }
+
+    pub fn set(&mut self, pos: Point2i, value: f32) {
+        let index = self.calculate_index(pos);
+        self.value_list[index] = value;
+    }
+
+    fn calculate_index(&self, pos: Point2i) -> usize {
+        debug_assert!(pos[0] >= 0 && pos[1] >= 0 && pos[0] < self.size && pos[1] < self.size);
+        (pos[0] + self.size * pos[1]) as usize
+    }
+}
+
+impl Saveable for AnalysisMap {
+    fn save(&self, writer: &mut impl Write) -> Result<(), Error> {
+        writer.write_i32::<LittleEndian>(self.size)?;
+        writer.write_f32::<LittleEndian>(self.scale_factor)?;
+        for value in self.value_list.iter() {
+            writer.write_f32::<LittleEndian>(*value)?;
+        }
+        Ok(())
+    }
+}
+
+impl Loadable for AnalysisMap {
+    fn load(&mut self, reader: &mut impl Read) -> Result<(), Error> {
+        let size = read_map_size(reader, "analysis map")?;
+        let scale_factor = reader.read_f32::<LittleEndian>()?;
+        let mut value_list = Vec::with_capacity((size * size) as usize);
+        for _ in 0..size * size {
+            value_list.push(reader.read_f32::<LittleEndian>()?);
+        }
+        self.size = size;
+        self.scale_factor = scale_factor;
+        self.value_list = value_list;
+        Ok(())
+    }
+}
+
+impl TryInto<Texture> for &AnalysisMap {
+    type Error = GraphicsError;
+    fn try_into(self) -> Result<Texture, Self::Error> {
+        let texture = TextureBuilder::new_2d(Point2i::from_scalar(self.size))
+            .format_r32f()
+            .finish()?;
+        texture.write_data(self.get_list())?;
+        Ok(texture)
+    }
+}
+
+impl HeightSampler for AnalysisMap {
+    fn get_grid_height(&self, grid_pos: Point2i) -> f32 {
+        self.get(Point2i::new(
+            grid_pos[0].max(0).min(self.size - 1),
+            grid_pos[1].max(0).min(self.size - 1),
+        ))
+    }
+
+    fn get_grid_spacing(&self) -> f32 {
+        self.scale_factor
+    }
+}
@@ -0,0 +1,7 @@
+mod analysis_layer;
+mod analysis_map;
+mod terrain_analysis;
+
+pub use self::analysis_layer::AnalysisLayer;
+pub use self::analysis_map::AnalysisMap;
+pub use self::terrain_analysis::TerrainAnalysis;
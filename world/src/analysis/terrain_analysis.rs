@@ -0,0 +1,202 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+
+use super::{AnalysisLayer, AnalysisMap};
+use crate::{HeightMap, HeightSampler};
+use core::{GraphicsError, Point2f, Point2i, Texture, TextureBuilder};
+
+const TPI_RADIUS: i32 = 4;
+const MIN_TAN_SLOPE: f32 = 1e-3;
+
+#[derive(Clone)]
+pub struct TerrainAnalysis {
+    size: i32,
+    layers: BTreeMap<AnalysisLayer, AnalysisMap>,
+}
+
+impl TerrainAnalysis {
+    pub fn from_heightmap(heightmap: &HeightMap) -> Self {
+        let size = heightmap.get_size();
+        let scale_factor = heightmap.get_scale_factor();
+        let mut layers = BTreeMap::new();
+        for layer in AnalysisLayer::as_slice() {
+            layers.insert(*layer, AnalysisMap::new(size, scale_factor));
+        }
+
+        for y in 0..size {
+            for x in 0..size {
+                let grid_pos = Point2i::new(x, y);
+                let pos = Point2f::from(grid_pos) * scale_factor;
+                let values = [
+                    (AnalysisLayer::Slope, heightmap.sample_slope(pos)),
+                    (AnalysisLayer::Aspect, heightmap.sample_aspect(pos)),
+                    (AnalysisLayer::Curvature, heightmap.sample_curvature(pos)),
+                    (
+                        AnalysisLayer::TopographicPosition,
+                        calculate_topographic_position(heightmap, grid_pos),
+                    ),
+                ];
+                for (layer, value) in values.iter() {
+                    if let Some(map) = layers.get_mut(layer) {
+                        map.set(grid_pos, *value);
+                    }
+                }
+            }
+        }
+
+        let wetness = match layers.get(&AnalysisLayer::Slope) {
+            Some(slope) => calculate_wetness(heightmap, slope),
+            None => unreachable!(),
+        };
+        layers.insert(AnalysisLayer::Wetness, wetness);
+
+        Self {
+            size: size,
+            layers: layers,
+        }
+    }
+
+    pub fn get_size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn get_layer(&self, layer: AnalysisLayer) -> &AnalysisMap {
+        match self.layers.get(&layer) {
+            Some(map) => map,
+            None => unreachable!(),
+        }
+    }
+
+    /// Packs slope, curvature, topographic position and wetness into an existing RGBA texture
+    pub fn write_texture(&self, texture: &Texture) -> Result<(), GraphicsError> {
+        let channels: Vec<&[f32]> = AnalysisLayer::texture_layers()
+            .iter()
+            .map(|layer| self.get_layer(*layer).get_list())
+            .collect();
+        let mut packed = Vec::with_capacity(4 * (self.size * self.size) as usize);
+        for i in 0..(self.size * self.size) as usize {
+            packed.extend(channels.iter().map(|c| c[i]));
+        }
+        texture.write_data(packed.as_slice())?;
+        Ok(())
+    }
+}
+
+impl TryInto<Texture> for &TerrainAnalysis {
+    type Error = GraphicsError;
+    fn try_into(self) -> Result<Texture, Self::Error> {
+        let texture = TextureBuilder::new_2d(Point2i::from_scalar(self.size))
+            .format_rgba32f()
+            .finish()?;
+        self.write_texture(&texture)?;
+        Ok(texture)
+    }
+}
+
+// Height relative to the mean height of the surrounding window,
+// negative in valleys and positive on ridges and peaks
+fn calculate_topographic_position(heightmap: &HeightMap, center: Point2i) -> f32 {
+    let mut sum = 0.;
+    let mut count = 0;
+    for y in center[1] - TPI_RADIUS..center[1] + TPI_RADIUS + 1 {
+        for x in center[0] - TPI_RADIUS..center[0] + TPI_RADIUS + 1 {
+            let pos = Point2i::new(x, y);
+            if pos != center && (pos - center).length() <= TPI_RADIUS as f32 {
+                sum += heightmap.get_grid_height(pos);
+                count += 1;
+            }
+        }
+    }
+    heightmap.get(center) - sum / count as f32
+}
+
+// Topographic wetness index ln(a / tan(b)), using single direction (D8) flow accumulation
+// within the heightmap for the specific catchment area a
+fn calculate_wetness(heightmap: &HeightMap, slope: &AnalysisMap) -> AnalysisMap {
+    let size = heightmap.get_size();
+    let spacing = heightmap.get_scale_factor();
+    let heights = heightmap.get_list();
+
+    let mut order: Vec<usize> = (0..heights.len()).collect();
+    order.sort_by(|a, b| {
+        heights[*b]
+            .partial_cmp(&heights[*a])
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let mut accumulation = Vec::new();
+    accumulation.resize(heights.len(), 1f32);
+    for index in order {
+        let pos = Point2i::new(index as i32 % size, index as i32 / size);
+        let mut target: Option<(usize, f32)> = None;
+        for dy in -1..2 {
+            for dx in -1..2 {
+                let nb = pos + Point2i::new(dx, dy);
+                if (dx == 0 && dy == 0) || nb[0] < 0 || nb[1] < 0 || nb[0] >= size || nb[1] >= size
+                {
+                    continue;
+                }
+                let nb_index = (nb[0] + nb[1] * size) as usize;
+                let drop = (heights[index] - heights[nb_index]) / Point2i::new(dx, dy).length();
+                match target {
+                    Some((_, max_drop)) if max_drop >= drop => {}
+                    _ if drop > 0. => target = Some((nb_index, drop)),
+                    _ => {}
+                }
+            }
+        }
+        if let Some((nb_index, _)) = target {
+            accumulation[nb_index] += accumulation[index];
+        }
+    }
+
+    let mut wetness = AnalysisMap::new(size, spacing);
+    for y in 0..size {
+        for x in 0..size {
+            let pos = Point2i::new(x, y);
+            let catchment = accumulation[(x + y * size) as usize] * spacing;
+            let tan_slope = f32::max(slope.get(pos).tan(), MIN_TAN_SLOPE);
+            wetness.set(pos, (catchment / tan_slope).ln());
+        }
+    }
+    wetness
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn create_valley_heightmap() -> HeightMap {
+        let mut hm = HeightMap::new(17, 1.);
+        for y in 0..17 {
+            for x in 0..17 {
+                hm.set(Point2i::new(x, y), (x - 8).abs() as f32 + 0.1 * y as f32);
+            }
+        }
+        hm
+    }
+
+    #[test]
+    fn test_valley_topographic_position_negative() {
+        let analysis = TerrainAnalysis::from_heightmap(&create_valley_heightmap());
+        let tpi = analysis.get_layer(AnalysisLayer::TopographicPosition);
+        assert!(tpi.get(Point2i::new(8, 8)) < 0.);
+        assert!(tpi.get(Point2i::new(8, 8)) < tpi.get(Point2i::new(4, 8)));
+    }
+
+    #[test]
+    fn test_valley_wetter_than_slope() {
+        let analysis = TerrainAnalysis::from_heightmap(&create_valley_heightmap());
+        let wetness = analysis.get_layer(AnalysisLayer::Wetness);
+        assert!(wetness.get(Point2i::new(8, 2)) > wetness.get(Point2i::new(3, 2)));
+    }
+
+    #[test]
+    fn test_valley_curvature_positive() {
+        let analysis = TerrainAnalysis::from_heightmap(&create_valley_heightmap());
+        let curvature = analysis.get_layer(AnalysisLayer::Curvature);
+        assert!(curvature.get(Point2i::new(8, 8)) > 0.);
+    }
+}
@@ -1,55 +1,463 @@
 use rand::rngs::StdRng;
 use rand::Rng;
 use std::collections::BTreeSet;
+use std::sync::Arc;
 
-use crate::chunk::{get_world_pos, CHUNK_SIZE};
+use crate::analysis::AnalysisMap;
+use crate::chunk::WorldLayout;
+use crate::climate::ClimateMap;
 use crate::height_map::HeightMap;
-use crate::noise::presets::{get_default_noise, get_default_tree_noise};
-use crate::noise::Noise;
-use core::{Point2f, Point2i, Point3f, Seed};
+use crate::hydrology::WaterMap;
+use crate::noise::presets::{get_default_cave_noise, get_default_noise, get_default_tree_noise};
+use crate::noise::{Noise, Noise3D};
+use crate::region_cache::RegionCache;
+use crate::road::{Road, RoadPlanner};
+use crate::settlement::{score_site, select_sites, SettlementSite};
+use crate::tectonics::{PlateMap, PlateNoise};
+use crate::volume::DensityField;
+use core::{Point2d, Point2f, Point2i, Point3d, Point3f, Point3i, Seed};
+
+// Has to be increased whenever the generated terrain changes, outdated cached chunks are discarded
+pub const GENERATOR_VERSION: u32 = 3;
+
+// Vertical extent of the density field below the lowest and above the highest surface point
+const CAVE_DEPTH: i32 = 24;
+const OVERHANG_AMPLITUDE: f32 = 6.;
+// Caves are carved where the absolute cave noise is below this value
+const CAVE_WIDTH: f32 = 0.08;
+const CAVE_STRENGTH: f32 = 20.;
+
+// Spacing of the coarse height grid roads are planned on
+const ROAD_GRID_SPACING: f32 = 8.;
+const ROAD_WIDTH: f32 = 4.;
+
+// Extent of settlement regions along each axis, in world units
+const SETTLEMENT_REGION_SIZE: f32 = 512.;
+// Candidates per axis, jittered within their cell of the region
+const SETTLEMENT_CANDIDATES: i32 = 8;
+const MIN_SETTLEMENT_DISTANCE: f32 = 128.;
+const MAX_SETTLEMENTS_PER_REGION: usize = 4;
+
+// Distance in world units between the grid points of the climate simulation
+const CLIMATE_GRID_SPACING: f32 = 128.;
+// Grid cells of a climate region along each axis
+const CLIMATE_REGION_CELLS: i32 = 64;
+// Climate maps and water regions kept in memory, the least recently used are dropped
+const CACHED_REGIONS: usize = 64;
+
+// Distance in world units between the grid points depressions are flooded on
+const WATER_GRID_SPACING: f32 = 8.;
+// Grid cells of a water region along each axis
+const WATER_REGION_CELLS: i32 = 128;
+// Cells flooded around a water region, lakes reaching further than this beyond the
+// region may spill at a different level than in the neighbouring region
+const WATER_REGION_MARGIN: i32 = 32;
+
+// Distance in world units between the sample points of the tree noise
+const FOREST_SCALE: f32 = 64.;
+// Trees per square of the forest scale at full forest density
+const MAX_TREE_COUNT: f32 = 40.;
 
 pub struct Architect {
+    seed: Seed,
     height_noise: Box<dyn Noise>,
     tree_noise: Box<dyn Noise>,
+    cave_noise: Box<dyn Noise3D>,
+    overhang_noise: Box<dyn Noise3D>,
+    plates: PlateMap,
+    sea_level: f32,
+    layout: WorldLayout,
+    roads: Vec<Road>,
+    climate_maps: RegionCache<ClimateMap>,
+    water_regions: RegionCache<WaterMap>,
 }
 
 impl Architect {
     pub fn from_seed(seed: Seed) -> Self {
+        let mut rng: StdRng = seed.into();
+        let cave_noise = get_default_cave_noise(Seed::from_rng(&mut rng));
+        let overhang_noise = get_default_cave_noise(Seed::from_rng(&mut rng));
+        let plates = PlateMap::from_seed(Seed::from_rng(&mut rng));
         Self {
-            height_noise: get_default_noise(seed),
+            seed: seed,
+            height_noise: Box::new(PlateNoise::new(plates.clone(), get_default_noise(seed))),
             tree_noise: get_default_tree_noise(seed),
+            cave_noise: cave_noise,
+            overhang_noise: overhang_noise,
+            plates: plates,
+            sea_level: 0.,
+            layout: WorldLayout::default(),
+            roads: Vec::new(),
+            climate_maps: RegionCache::new(CACHED_REGIONS),
+            water_regions: RegionCache::new(CACHED_REGIONS),
         }
     }
 
+    pub fn with_sea_level(mut self, sea_level: f32) -> Self {
+        self.sea_level = sea_level;
+        self
+    }
+
+    pub fn with_layout(mut self, layout: WorldLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn get_layout(&self) -> WorldLayout {
+        self.layout
+    }
+
+    pub fn get_seed(&self) -> Seed {
+        self.seed
+    }
+
+    pub fn get_sea_level(&self) -> f32 {
+        self.sea_level
+    }
+
+    /// Tectonic plates giving the base elevation of the terrain
+    pub fn get_plates(&self) -> &PlateMap {
+        &self.plates
+    }
+
     pub fn get_height(&self, absolute_pos: Point2f) -> f32 {
         self.height_noise.get_noise(absolute_pos)
     }
 
+    /// Height of the terrain after flattening the roads into it
+    pub fn get_surface_height(&self, absolute_pos: Point2f) -> f32 {
+        self.get_surface_height_precise(Point2d::from(absolute_pos))
+    }
+
+    // Roads are only placed near the origin, so they don't need double precision
+    fn get_surface_height_precise(&self, absolute_pos: Point2d) -> f32 {
+        let road_pos = absolute_pos.as_f32();
+        self.roads.iter().fold(
+            self.height_noise.get_noise_precise(absolute_pos),
+            |height, road| match road.get_cross_section(road_pos) {
+                Some((road_height, weight, _)) => height + (road_height - height) * weight,
+                None => height,
+            },
+        )
+    }
+
+    /// Road between the given positions, planned on a coarse grid of procedural heights.
+    /// The road isn't part of the terrain until it's added.
+    pub fn plan_road(&self, from: Point2f, to: Point2f) -> Option<Road> {
+        let planner = RoadPlanner::new(ROAD_GRID_SPACING, self.sea_level);
+        let path = planner.find_path(|p| self.get_height(p), from, to)?;
+        Some(Road::from_path(
+            &path,
+            ROAD_WIDTH,
+            |p| self.get_height(p),
+            self.sea_level,
+        ))
+    }
+
+    /// Roads have to be added before any chunks are built
+    pub fn add_road(&mut self, road: Road) {
+        self.roads.push(road);
+        // the lakes were flooded without the road
+        self.water_regions.clear();
+    }
+
+    pub fn get_roads(&self) -> &[Road] {
+        self.roads.as_slice()
+    }
+
+    /// Hash of the settings the generated terrain depends on besides the seed:
+    /// sea level, layout and roads. Cached chunks built with other settings are discarded.
+    pub fn get_terrain_hash(&self) -> u64 {
+        let mut values: Vec<u32> = vec![
+            self.sea_level.to_bits(),
+            self.layout.get_chunk_size().to_bits(),
+            self.layout.get_resolution() as u32,
+            self.roads.len() as u32,
+        ];
+        for road in self.roads.iter() {
+            values.push(road.get_width().to_bits());
+            values.push(road.get_points().len() as u32);
+            for point in road.get_points() {
+                values.extend_from_slice(&[
+                    point[0].to_bits(),
+                    point[1].to_bits(),
+                    point[2].to_bits(),
+                ]);
+            }
+        }
+        // FNV-1a, stable across builds unlike the std hasher
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+
+    /// Flattens all roads touching the heightmap into it, returns the road surface mask.
+    /// None if no road touches the heightmap.
+    pub fn apply_roads(&self, heightmap: &mut HeightMap) -> Option<AnalysisMap> {
+        let size = heightmap.get_size();
+        let scale_factor = heightmap.get_scale_factor();
+        let origin = heightmap.get_origin();
+        let extent = origin + Point2f::from_scalar((size - 1) as f32 * scale_factor);
+        let mut mask: Option<AnalysisMap> = None;
+        for road in self.roads.iter().filter(|r| r.intersects(origin, extent)) {
+            road.apply_to(
+                heightmap,
+                mask.get_or_insert_with(|| AnalysisMap::new(size, scale_factor)),
+            );
+        }
+        mask
+    }
+
     pub fn create_heightmap(&self, chunk_pos: Point2i) -> HeightMap {
         HeightMap::from_noise(
-            get_world_pos(chunk_pos, None),
-            CHUNK_SIZE,
-            1.,
+            self.layout.get_world_pos(chunk_pos, None),
+            self.layout.get_grid_size(),
+            self.layout.get_grid_spacing(),
             self.height_noise.as_ref(),
         )
     }
 
+    pub fn get_settlement_region(&self, absolute_pos: Point2f) -> Point2i {
+        Point2i::new(
+            (absolute_pos[0] / SETTLEMENT_REGION_SIZE).floor() as i32,
+            (absolute_pos[1] / SETTLEMENT_REGION_SIZE).floor() as i32,
+        )
+    }
+
+    /// Best scoring sites of the region, only depending on the seed and the region.
+    /// Candidates keep half the minimum distance to the region border,
+    /// so sites of neighbouring regions are spaced as well.
+    pub fn get_settlement_sites(&self, region_pos: Point2i) -> Vec<SettlementSite> {
+        let region_extent = SETTLEMENT_REGION_SIZE;
+        let inset = MIN_SETTLEMENT_DISTANCE / 2.;
+        let cell_size = (region_extent - 2. * inset) / SETTLEMENT_CANDIDATES as f32;
+        let origin = Point2f::from(region_pos) * region_extent + Point2f::from_scalar(inset);
+        let mut rng: StdRng = self.seed.mix_with_point(region_pos).into();
+
+        let mut candidates = Vec::new();
+        for y in 0..SETTLEMENT_CANDIDATES {
+            for x in 0..SETTLEMENT_CANDIDATES {
+                let jitter = Point2f::new(rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0));
+                let pos = origin + (Point2f::new(x as f32, y as f32) + jitter) * cell_size;
+                let score = score_site(
+                    |p| self.get_height(p),
+                    |p| self.get_forest_density(p),
+                    |p| {
+                        let absolute_pos = Point2d::from(p);
+                        [
+                            self.get_temperature(absolute_pos),
+                            self.get_precipitation(absolute_pos),
+                        ]
+                    },
+                    pos,
+                    self.sea_level,
+                );
+                if let Some(score) = score {
+                    candidates.push(SettlementSite::new(pos.extend(self.get_height(pos)), score));
+                }
+            }
+        }
+        select_sites(
+            candidates,
+            MIN_SETTLEMENT_DISTANCE,
+            MAX_SETTLEMENTS_PER_REGION,
+        )
+    }
+
+    pub fn get_climate_region(&self, absolute_pos: Point2d) -> Point2i {
+        let region_extent = (CLIMATE_REGION_CELLS as f32 * CLIMATE_GRID_SPACING) as f64;
+        Point2i::new(
+            (absolute_pos[0] / region_extent).floor() as i32,
+            (absolute_pos[1] / region_extent).floor() as i32,
+        )
+    }
+
+    /// Climate of the region, simulated on a coarse grid of procedural heights.
+    /// Roads are too small to matter for the climate.
+    pub fn create_climate_map(&self, region_pos: Point2i) -> ClimateMap {
+        let region_extent = (CLIMATE_REGION_CELLS as f32 * CLIMATE_GRID_SPACING) as f64;
+        ClimateMap::simulate(
+            |p| self.height_noise.get_noise_precise(p),
+            Point2d::from(region_pos) * region_extent,
+            CLIMATE_REGION_CELLS + 1,
+            CLIMATE_GRID_SPACING,
+            self.sea_level,
+        )
+    }
+
+    /// Temperature in degrees celsius, from the latitude and the elevation
+    pub fn get_temperature(&self, absolute_pos: Point2d) -> f32 {
+        self.get_climate_map(self.get_climate_region(absolute_pos))
+            .get_temperature(absolute_pos)
+    }
+
+    /// Share of the air moisture rained out per climate grid cell, between 0 and 1
+    pub fn get_precipitation(&self, absolute_pos: Point2d) -> f32 {
+        self.get_climate_map(self.get_climate_region(absolute_pos))
+            .get_precipitation(absolute_pos)
+    }
+
+    /// Temperature and precipitation at each grid point of the chunk
+    pub fn sample_climate(&self, chunk_pos: Point2i) -> Vec<[f32; 2]> {
+        let size = self.layout.get_grid_size();
+        let spacing = self.layout.get_grid_spacing() as f64;
+        let origin = Point2d::from(self.layout.get_world_pos(chunk_pos, None));
+        let mut climate = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let absolute_pos = origin + Point2d::new(x as f64, y as f64) * spacing;
+                climate.push([
+                    self.get_temperature(absolute_pos),
+                    self.get_precipitation(absolute_pos),
+                ]);
+            }
+        }
+        climate
+    }
+
+    // Climate maps are simulated on first use
+    fn get_climate_map(&self, region_pos: Point2i) -> Arc<ClimateMap> {
+        self.climate_maps
+            .get_or_create(region_pos, || self.create_climate_map(region_pos))
+    }
+
+    // Same noise as used for the tree count of a chunk
+    fn get_forest_density(&self, absolute_pos: Point2f) -> f32 {
+        self.tree_noise
+            .get_noise(absolute_pos / FOREST_SCALE)
+            .max(0.)
+            .min(1.)
+    }
+
+    pub fn update_normals(&self, heightmap: &mut HeightMap) {
+        if self.roads.is_empty() {
+            heightmap.update_normals(self.height_noise.as_ref());
+        } else {
+            heightmap.update_normals(&SurfaceNoise { architect: self });
+        }
+    }
+
+    /// Water of the chunk, sampled from the lakes flooded on the coarse grid of its water
+    /// region, so lakes spanning several chunks have the same level in all of them.
+    /// The heightmap only decides which grid points lie below the water surface.
+    pub fn create_water_map(&self, chunk_pos: Point2i, heightmap: &HeightMap) -> WaterMap {
+        let spacing = self.layout.get_grid_spacing() as f64;
+        let size = heightmap.get_size();
+        let origin = Point2d::from(self.layout.get_world_pos(chunk_pos, None));
+        let mut region: Option<(Point2i, Arc<WaterMap>)> = None;
+        let mut heights = Vec::with_capacity((size * size) as usize);
+        let mut levels = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let absolute_pos = origin + Point2d::new(x as f64, y as f64) * spacing;
+                let cell = Point2i::new(
+                    (absolute_pos[0] / WATER_GRID_SPACING as f64).floor() as i32,
+                    (absolute_pos[1] / WATER_GRID_SPACING as f64).floor() as i32,
+                );
+                let region_pos = Point2i::new(
+                    cell[0].div_euclid(WATER_REGION_CELLS),
+                    cell[1].div_euclid(WATER_REGION_CELLS),
+                );
+                let water_region = match &region {
+                    Some((pos, water_region)) if *pos == region_pos => water_region.clone(),
+                    _ => {
+                        let water_region = self.get_water_region(region_pos);
+                        region = Some((region_pos, water_region.clone()));
+                        water_region
+                    }
+                };
+                let local_cell = cell - region_pos * WATER_REGION_CELLS;
+                heights.push(heightmap.get(Point2i::new(x, y)));
+                levels.push(get_highest_water_level(&water_region, local_cell));
+            }
+        }
+        WaterMap::from_levels(
+            &heights,
+            &levels,
+            size,
+            self.layout.get_grid_spacing(),
+            self.sea_level,
+        )
+    }
+
+    /// Lakes of the procedural terrain, flooded on a coarse grid including the shared edges
+    /// with the neighbouring regions
+    pub fn create_water_region(&self, region_pos: Point2i) -> WaterMap {
+        let region_extent = (WATER_REGION_CELLS as f32 * WATER_GRID_SPACING) as f64;
+        let size = WATER_REGION_CELLS + 1 + 2 * WATER_REGION_MARGIN;
+        let origin = Point2d::from(region_pos) * region_extent
+            - Point2d::from_scalar((WATER_REGION_MARGIN as f32 * WATER_GRID_SPACING) as f64);
+        let mut heights = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                heights.push(self.get_surface_height_precise(
+                    origin + Point2d::new(x as f64, y as f64) * WATER_GRID_SPACING as f64,
+                ));
+            }
+        }
+        WaterMap::from_heights(&heights, size, WATER_GRID_SPACING, self.sea_level).crop(
+            Point2i::from_scalar(WATER_REGION_MARGIN),
+            WATER_REGION_CELLS + 1,
+        )
+    }
+
+    // Water regions are flooded on first use and dropped when the roads change
+    fn get_water_region(&self, region_pos: Point2i) -> Arc<WaterMap> {
+        self.water_regions
+            .get_or_create(region_pos, || self.create_water_region(region_pos))
+    }
+
+    /// Density of the heightmap surface, displaced by 3D noise for overhangs and carved by caves
+    pub fn create_density_field(&self, chunk_pos: Point2i, heightmap: &HeightMap) -> DensityField {
+        let size = heightmap.get_size();
+        let z_origin = heightmap.get_min().floor() as i32 - CAVE_DEPTH;
+        let z_top = (heightmap.get_max() + OVERHANG_AMPLITUDE).ceil() as i32 + 2;
+        let spacing = self.layout.get_grid_spacing();
+        let mut field =
+            DensityField::new(size, z_origin, z_top - z_origin + 1).with_spacing(spacing);
+        let origin = self.layout.get_world_pos(chunk_pos, None);
+        for y in 0..size {
+            for x in 0..size {
+                let height = heightmap.get(Point2i::new(x, y));
+                for z in 0..field.get_layers() {
+                    let world_pos = Point3d::new(
+                        origin[0] as f64 + x as f64 * spacing as f64,
+                        origin[1] as f64 + y as f64 * spacing as f64,
+                        (z_origin + z) as f64,
+                    );
+                    let overhang =
+                        self.overhang_noise.get_noise_precise(world_pos) * OVERHANG_AMPLITUDE;
+                    let surface = height - (z_origin + z) as f32 + overhang;
+                    let cave = (self.cave_noise.get_noise_precise(world_pos).abs() - CAVE_WIDTH)
+                        * CAVE_STRENGTH;
+                    field.set(Point3i::new(x, y, z), f32::min(surface, cave));
+                }
+            }
+        }
+        field
+    }
+
     pub fn get_trees(&self, chunk_pos: Point2i) -> Vec<Point3f> {
-        let n = self.tree_noise.get_noise(chunk_pos.into());
+        let origin = self.layout.get_world_pos(chunk_pos, None);
+        let n = self.get_forest_density(origin);
         if n > 0. {
-            let tree_count = (40. * n).round() as usize;
+            let area = (self.layout.get_chunk_size() / FOREST_SCALE).powi(2);
+            let tree_count = (MAX_TREE_COUNT * n * area).round() as usize;
+            let grid_size = self.layout.get_grid_size();
             let mut rng: StdRng = Seed::from_entropy().into();
             let mut trees = BTreeSet::new();
             for _i in 0..tree_count {
-                let offset =
-                    Point2i::new(rng.gen_range(0..CHUNK_SIZE), rng.gen_range(0..CHUNK_SIZE));
+                let offset = Point2i::new(rng.gen_range(0..grid_size), rng.gen_range(0..grid_size));
                 trees.insert(offset);
             }
             trees
                 .into_iter()
                 .map(|offset| {
-                    let abs_pos = get_world_pos(chunk_pos, Some(offset.into()));
-                    let height = self.get_height(abs_pos);
+                    let abs_pos = origin + Point2f::from(offset) * self.layout.get_grid_spacing();
+                    let height = self.get_surface_height(abs_pos);
                     abs_pos.extend(height)
                 })
                 .collect()
@@ -58,3 +466,125 @@ impl Architect {
         }
     }
 }
+
+// Highest water surface at the corners of the grid cell
+fn get_highest_water_level(water_region: &WaterMap, cell: Point2i) -> Option<f32> {
+    [(0, 0), (1, 0), (0, 1), (1, 1)]
+        .iter()
+        .filter_map(|(dx, dy)| water_region.get_level(cell + Point2i::new(*dx, *dy)))
+        .fold(None, |acc, level| match acc {
+            Some(max) => Some(f32::max(max, level)),
+            None => Some(level),
+        })
+}
+
+// Surface heights beyond the border of a heightmap, for normals consistent with neighbouring chunks
+struct SurfaceNoise<'a> {
+    architect: &'a Architect,
+}
+
+impl<'a> Noise for SurfaceNoise<'a> {
+    fn get_noise(&self, point: Point2f) -> f32 {
+        self.architect.get_surface_height(point)
+    }
+
+    fn get_noise_precise(&self, point: Point2d) -> f32 {
+        self.architect.get_surface_height_precise(point)
+    }
+
+    fn get_range(&self) -> [f32; 2] {
+        self.architect.height_noise.get_range()
+    }
+
+    fn get_cycle(&self) -> Point2f {
+        self.architect.height_noise.get_cycle()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_terrain_hash() {
+        let seed = Seed::from_string("HASH");
+        let base = Architect::from_seed(seed).get_terrain_hash();
+        assert_eq!(base, Architect::from_seed(seed).get_terrain_hash());
+        let sea_level = Architect::from_seed(seed).with_sea_level(5.);
+        assert_ne!(base, sea_level.get_terrain_hash());
+        let layout = Architect::from_seed(seed).with_layout(WorldLayout::new(128., 64));
+        assert_ne!(base, layout.get_terrain_hash());
+        let mut roads = Architect::from_seed(seed);
+        let road = roads
+            .plan_road(Point2f::new(0., 0.), Point2f::new(64., 0.))
+            .unwrap();
+        roads.add_road(road);
+        assert_ne!(base, roads.get_terrain_hash());
+    }
+
+    #[test]
+    fn test_water_agrees_on_shared_edges() {
+        let architect = Architect::from_seed(Seed::from_string("WATER")).with_sea_level(-20.);
+        let size = architect.get_layout().get_grid_size();
+        let positions = [Point2i::new(0, 0), Point2i::new(1, 0)];
+        let water_maps: Vec<WaterMap> = positions
+            .iter()
+            .map(|pos| {
+                let heightmap = architect.create_heightmap(*pos);
+                architect.create_water_map(*pos, &heightmap)
+            })
+            .collect();
+        for y in 0..size {
+            let left = Point2i::new(size - 1, y);
+            let right = Point2i::new(0, y);
+            assert_eq!(water_maps[0].get_kind(left), water_maps[1].get_kind(right));
+            assert_eq!(
+                water_maps[0].get_level(left),
+                water_maps[1].get_level(right)
+            );
+        }
+    }
+
+    #[test]
+    fn test_water_region_levels_match_heights() {
+        let architect = Architect::from_seed(Seed::from_string("WATER"));
+        let region = architect.create_water_region(Point2i::new(0, 0));
+        assert_eq!(WATER_REGION_CELLS + 1, region.get_size());
+        let level = region.get_level(Point2i::new(0, 0));
+        let height = architect.get_surface_height(Point2f::new(0., 0.));
+        assert!(level.map_or(true, |l| l >= height));
+    }
+
+    #[test]
+    fn test_settlement_sites_deterministic() {
+        let a = Architect::from_seed(Seed::from_string("SITES"));
+        let b = Architect::from_seed(Seed::from_string("SITES"));
+        let region = Point2i::new(-1, 2);
+        let sites_a: Vec<Point3f> = a
+            .get_settlement_sites(region)
+            .iter()
+            .map(|s| s.get_pos())
+            .collect();
+        let sites_b: Vec<Point3f> = b
+            .get_settlement_sites(region)
+            .iter()
+            .map(|s| s.get_pos())
+            .collect();
+        assert_eq!(sites_a, sites_b);
+    }
+
+    #[test]
+    fn test_settlement_sites_spaced_across_regions() {
+        let architect = Architect::from_seed(Seed::from_string("SITES"));
+        let mut sites = architect.get_settlement_sites(Point2i::new(0, 0));
+        sites.extend(architect.get_settlement_sites(Point2i::new(1, 0)));
+        for (i, a) in sites.iter().enumerate() {
+            for b in sites.iter().skip(i + 1) {
+                assert!(
+                    (a.get_pos().as_xy() - b.get_pos().as_xy()).length() >= MIN_SETTLEMENT_DISTANCE
+                );
+            }
+        }
+    }
+}
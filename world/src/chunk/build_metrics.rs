@@ -0,0 +1,379 @@
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::build_stats::{BuildStage, HISTOGRAM_BOUNDS};
+
+/// Timings of a build stage in ms. Percentiles cover the recent builds,
+/// count, mean, max and histogram all builds.
+#[derive(Clone, Debug)]
+pub struct StageMetrics {
+    name: &'static str,
+    count: u64,
+    mean: f64,
+    percentiles: [f64; 3],
+    max: f64,
+    histogram: Vec<u32>,
+}
+
+impl StageMetrics {
+    pub fn new(
+        name: &'static str,
+        count: u64,
+        mean: f64,
+        percentiles: [f64; 3],
+        max: f64,
+        histogram: Vec<u32>,
+    ) -> Self {
+        Self {
+            name: name,
+            count: count,
+            mean: mean,
+            percentiles: percentiles,
+            max: max,
+            histogram: histogram,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        self.name
+    }
+
+    pub fn get_count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn get_mean(&self) -> f64 {
+        self.mean
+    }
+
+    pub fn get_p50(&self) -> f64 {
+        self.percentiles[0]
+    }
+
+    pub fn get_p90(&self) -> f64 {
+        self.percentiles[1]
+    }
+
+    pub fn get_p99(&self) -> f64 {
+        self.percentiles[2]
+    }
+
+    pub fn get_max(&self) -> f64 {
+        self.max
+    }
+
+    /// Counts per bucket of `get_histogram_bounds`, plus one for longer timings
+    pub fn get_histogram(&self) -> &[u32] {
+        &self.histogram
+    }
+}
+
+/// Estimated bytes of the loaded and the retained chunks
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MemoryUsage {
+    cpu_size: usize,
+    gpu_size: usize,
+    retained_cpu_size: usize,
+    retained_gpu_size: usize,
+    retained_count: usize,
+    budget: usize,
+}
+
+impl MemoryUsage {
+    pub fn new(cpu_size: usize, gpu_size: usize, budget: usize) -> Self {
+        Self {
+            cpu_size: cpu_size,
+            gpu_size: gpu_size,
+            retained_cpu_size: 0,
+            retained_gpu_size: 0,
+            retained_count: 0,
+            budget: budget,
+        }
+    }
+
+    pub fn with_retained(mut self, cpu_size: usize, gpu_size: usize, count: usize) -> Self {
+        self.retained_cpu_size = cpu_size;
+        self.retained_gpu_size = gpu_size;
+        self.retained_count = count;
+        self
+    }
+
+    /// Terrain data of the loaded chunks
+    pub fn get_cpu_size(&self) -> usize {
+        self.cpu_size
+    }
+
+    /// Meshes and textures of the loaded chunks
+    pub fn get_gpu_size(&self) -> usize {
+        self.gpu_size
+    }
+
+    pub fn get_retained_cpu_size(&self) -> usize {
+        self.retained_cpu_size
+    }
+
+    pub fn get_retained_gpu_size(&self) -> usize {
+        self.retained_gpu_size
+    }
+
+    pub fn get_retained_count(&self) -> usize {
+        self.retained_count
+    }
+
+    pub fn get_budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Loaded and retained chunks, CPU and GPU
+    pub fn get_total(&self) -> usize {
+        self.cpu_size + self.gpu_size + self.retained_cpu_size + self.retained_gpu_size
+    }
+}
+
+/// Snapshot of the chunk build statistics
+#[derive(Clone, Debug)]
+pub struct BuildMetrics {
+    total: StageMetrics,
+    stages: Vec<StageMetrics>,
+    cache_hits: u64,
+    cache_misses: u64,
+    chunks_per_second: f64,
+    queue_depth: usize,
+    memory: MemoryUsage,
+}
+
+impl BuildMetrics {
+    pub fn new(
+        total: StageMetrics,
+        stages: Vec<StageMetrics>,
+        cache_hits: u64,
+        cache_misses: u64,
+        chunks_per_second: f64,
+        queue_depth: usize,
+        memory: MemoryUsage,
+    ) -> Self {
+        Self {
+            total: total,
+            stages: stages,
+            cache_hits: cache_hits,
+            cache_misses: cache_misses,
+            chunks_per_second: chunks_per_second,
+            queue_depth: queue_depth,
+            memory: memory,
+        }
+    }
+
+    pub fn get_histogram_bounds() -> &'static [f64] {
+        &HISTOGRAM_BOUNDS
+    }
+
+    /// Whole build on a worker, without the upload
+    pub fn get_total(&self) -> &StageMetrics {
+        &self.total
+    }
+
+    /// Stages which ran at least once
+    pub fn get_stages(&self) -> &[StageMetrics] {
+        &self.stages
+    }
+
+    pub fn get_stage(&self, stage: BuildStage) -> Option<&StageMetrics> {
+        self.stages.iter().find(|s| s.name == stage.get_name())
+    }
+
+    pub fn get_chunk_count(&self) -> u64 {
+        self.total.count
+    }
+
+    /// None if no chunk cache is in use
+    pub fn get_cache_hit_rate(&self) -> Option<f64> {
+        let lookups = self.cache_hits + self.cache_misses;
+        if lookups > 0 {
+            Some(self.cache_hits as f64 / lookups as f64)
+        } else {
+            None
+        }
+    }
+
+    /// Over the last ten seconds
+    pub fn get_chunks_per_second(&self) -> f64 {
+        self.chunks_per_second
+    }
+
+    pub fn get_queue_depth(&self) -> usize {
+        self.queue_depth
+    }
+
+    pub fn get_memory_usage(&self) -> &MemoryUsage {
+        &self.memory
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\n");
+        let _ = writeln!(json, "  \"chunk_count\": {},", self.get_chunk_count());
+        let _ = writeln!(json, "  \"cache_hits\": {},", self.cache_hits);
+        let _ = writeln!(json, "  \"cache_misses\": {},", self.cache_misses);
+        let _ = writeln!(
+            json,
+            "  \"cache_hit_rate\": {},",
+            match self.get_cache_hit_rate() {
+                Some(rate) => format!("{:.4}", rate),
+                None => String::from("null"),
+            }
+        );
+        let _ = writeln!(
+            json,
+            "  \"chunks_per_second\": {:.3},",
+            self.chunks_per_second
+        );
+        let _ = writeln!(json, "  \"queue_depth\": {},", self.queue_depth);
+        let memory = &self.memory;
+        let _ = writeln!(
+            json,
+            "  \"memory\": {{\"cpu_bytes\": {}, \"gpu_bytes\": {}, \"retained_cpu_bytes\": {}, \
+             \"retained_gpu_bytes\": {}, \"retained_count\": {}, \"budget_bytes\": {}}},",
+            memory.cpu_size,
+            memory.gpu_size,
+            memory.retained_cpu_size,
+            memory.retained_gpu_size,
+            memory.retained_count,
+            memory.budget
+        );
+        let _ = writeln!(
+            json,
+            "  \"histogram_bounds_ms\": [{}],",
+            join(HISTOGRAM_BOUNDS.iter(), ", ")
+        );
+        json.push_str("  \"stages\": [\n");
+        let rows: Vec<String> = self
+            .iter_rows()
+            .map(|s| {
+                format!(
+                    "    {{\"stage\": \"{}\", \"count\": {}, \"mean_ms\": {:.3}, \
+                     \"p50_ms\": {:.3}, \"p90_ms\": {:.3}, \"p99_ms\": {:.3}, \
+                     \"max_ms\": {:.3}, \"histogram\": [{}]}}",
+                    s.name,
+                    s.count,
+                    s.mean,
+                    s.get_p50(),
+                    s.get_p90(),
+                    s.get_p99(),
+                    s.max,
+                    join(s.histogram.iter(), ", ")
+                )
+            })
+            .collect();
+        json.push_str(&rows.join(",\n"));
+        json.push_str("\n  ]\n}\n");
+        json
+    }
+
+    /// One row per stage, followed by the overall values
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("stage,count,mean_ms,p50_ms,p90_ms,p99_ms,max_ms");
+        for bound in HISTOGRAM_BOUNDS.iter() {
+            let _ = write!(csv, ",le_{}ms", bound);
+        }
+        let _ = writeln!(
+            csv,
+            ",gt_{}ms",
+            HISTOGRAM_BOUNDS[HISTOGRAM_BOUNDS.len() - 1]
+        );
+        for s in self.iter_rows() {
+            let _ = writeln!(
+                csv,
+                "{},{},{:.3},{:.3},{:.3},{:.3},{:.3},{}",
+                s.name,
+                s.count,
+                s.mean,
+                s.get_p50(),
+                s.get_p90(),
+                s.get_p99(),
+                s.max,
+                join(s.histogram.iter(), ",")
+            );
+        }
+        csv.push_str("\nmetric,value\n");
+        let _ = writeln!(csv, "chunk_count,{}", self.get_chunk_count());
+        let _ = writeln!(csv, "cache_hits,{}", self.cache_hits);
+        let _ = writeln!(csv, "cache_misses,{}", self.cache_misses);
+        if let Some(rate) = self.get_cache_hit_rate() {
+            let _ = writeln!(csv, "cache_hit_rate,{:.4}", rate);
+        }
+        let _ = writeln!(csv, "chunks_per_second,{:.3}", self.chunks_per_second);
+        let _ = writeln!(csv, "queue_depth,{}", self.queue_depth);
+        let _ = writeln!(csv, "cpu_bytes,{}", self.memory.cpu_size);
+        let _ = writeln!(csv, "gpu_bytes,{}", self.memory.gpu_size);
+        let _ = writeln!(csv, "retained_cpu_bytes,{}", self.memory.retained_cpu_size);
+        let _ = writeln!(csv, "retained_gpu_bytes,{}", self.memory.retained_gpu_size);
+        let _ = writeln!(csv, "retained_count,{}", self.memory.retained_count);
+        let _ = writeln!(csv, "budget_bytes,{}", self.memory.budget);
+        csv
+    }
+
+    /// Writes CSV for a `.csv` extension, JSON otherwise
+    pub fn save_file(&self, path: &Path) -> Result<(), io::Error> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let content = match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => self.to_csv(),
+            _ => self.to_json(),
+        };
+        fs::write(path, content)
+    }
+
+    fn iter_rows(&self) -> impl Iterator<Item = &StageMetrics> {
+        std::iter::once(&self.total).chain(self.stages.iter())
+    }
+}
+
+fn join<T: ToString>(values: impl Iterator<Item = T>, separator: &str) -> String {
+    values
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_metrics() -> BuildMetrics {
+        let mut histogram = vec![0; HISTOGRAM_BOUNDS.len() + 1];
+        histogram[2] = 4;
+        let total = StageMetrics::new("total", 4, 4.5, [4., 5., 5.], 5., histogram.clone());
+        let noise = StageMetrics::new("noise", 4, 3., [3., 3.5, 3.5], 3.5, histogram);
+        let memory = MemoryUsage::new(100, 200, 1000).with_retained(30, 40, 2);
+        BuildMetrics::new(total, vec![noise], 1, 3, 2., 7, memory)
+    }
+
+    #[test]
+    fn test_export_json() {
+        let json = create_metrics().to_json();
+        assert!(json.starts_with("{\n"));
+        assert!(json.contains("\"cache_hit_rate\": 0.2500,"));
+        assert!(json.contains("\"queue_depth\": 7,"));
+        assert!(json.contains("\"retained_count\": 2, \"budget_bytes\": 1000},"));
+        assert!(json.contains("{\"stage\": \"total\", \"count\": 4, \"mean_ms\": 4.500,"));
+        assert!(json.contains("\"histogram\": [0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0]}\n  ]"));
+        assert_eq!(json.matches('{').count(), json.matches('}').count());
+        assert_eq!(json.matches('[').count(), json.matches(']').count());
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let csv = create_metrics().to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        let columns = lines[0].split(',').count();
+        assert_eq!(columns, 7 + HISTOGRAM_BOUNDS.len() + 1);
+        assert!(lines[1].starts_with("total,4,4.500,4.000,5.000,5.000,5.000,0,0,4,"));
+        assert!(lines[2].starts_with("noise,"));
+        assert_eq!(lines[2].split(',').count(), columns);
+        assert!(csv.contains("\nmetric,value\n"));
+        assert!(csv.contains("cache_hit_rate,0.2500\n"));
+        assert!(csv.contains("retained_gpu_bytes,40\n"));
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use crate::surface::MaterialRule;
+use core::Config;
+
+#[derive(Clone)]
+pub struct BuildOptions {
+    mesh_max_error: f32,
+    terrain_analysis: bool,
+    volumetric_terrain: bool,
+    surface_rules: Arc<Vec<MaterialRule>>,
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self {
+            mesh_max_error: 0.5,
+            terrain_analysis: false,
+            volumetric_terrain: false,
+            surface_rules: Arc::new(Vec::new()),
+        }
+    }
+}
+
+impl BuildOptions {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            mesh_max_error: config.get_float_or_default("surface_max_error", 0.5),
+            terrain_analysis: config.get_bool_or_default("terrain_analysis", false),
+            volumetric_terrain: config.get_bool_or_default("volumetric_terrain", false),
+            surface_rules: Arc::new(Vec::new()),
+        }
+    }
+
+    pub fn with_surface_rules(mut self, rules: Vec<MaterialRule>) -> Self {
+        self.surface_rules = Arc::new(rules);
+        self
+    }
+
+    pub fn get_mesh_max_error(&self) -> f32 {
+        self.mesh_max_error
+    }
+
+    // Must cover the error of both sides of a chunk border, neighbours may use a different error
+    pub fn get_skirt_depth(&self) -> f32 {
+        f32::max(1., 2. * self.mesh_max_error)
+    }
+
+    pub fn use_terrain_analysis(&self) -> bool {
+        self.terrain_analysis
+    }
+
+    pub fn use_volumetric_terrain(&self) -> bool {
+        self.volumetric_terrain
+    }
+
+    /// No splat maps are built without rules
+    pub fn get_surface_rules(&self) -> &[MaterialRule] {
+        self.surface_rules.as_slice()
+    }
+}
@@ -1,31 +1,312 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use super::{BuildMetrics, MemoryUsage, StageMetrics};
+
+// Percentiles are taken from the most recent samples only
+const SAMPLE_WINDOW: usize = 1000;
+// Chunks per second are measured over the recent builds
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Upper bounds of the histogram buckets in ms, the last bucket takes everything above
+pub const HISTOGRAM_BOUNDS: [f64; 10] = [1., 2., 5., 10., 20., 50., 100., 200., 500., 1000.];
+
+/// Steps of building a chunk. Decoration is the splat map of the surface materials,
+/// the upload to the GPU happens on the main thread once the chunk is retrieved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildStage {
+    CacheLoad,
+    Noise,
+    Roads,
+    Normals,
+    Water,
+    Edits,
+    Volume,
+    Analysis,
+    Decoration,
+    Geometry,
+    Upload,
+}
+
+impl BuildStage {
+    pub const ALL: [BuildStage; 11] = [
+        BuildStage::CacheLoad,
+        BuildStage::Noise,
+        BuildStage::Roads,
+        BuildStage::Normals,
+        BuildStage::Water,
+        BuildStage::Edits,
+        BuildStage::Volume,
+        BuildStage::Analysis,
+        BuildStage::Decoration,
+        BuildStage::Geometry,
+        BuildStage::Upload,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            BuildStage::CacheLoad => "cache_load",
+            BuildStage::Noise => "noise",
+            BuildStage::Roads => "roads",
+            BuildStage::Normals => "normals",
+            BuildStage::Water => "water",
+            BuildStage::Edits => "edits",
+            BuildStage::Volume => "volume",
+            BuildStage::Analysis => "analysis",
+            BuildStage::Decoration => "decoration",
+            BuildStage::Geometry => "geometry",
+            BuildStage::Upload => "upload",
+        }
+    }
+
+    fn get_index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Stage timings of a single chunk build. Stages that were skipped have no timing.
+pub struct BuildTimings {
+    durations: [Option<Duration>; BuildStage::ALL.len()],
+    cache_hit: Option<bool>,
+}
+
+impl BuildTimings {
+    pub fn new() -> Self {
+        Self {
+            durations: [None; BuildStage::ALL.len()],
+            cache_hit: None,
+        }
+    }
+
+    /// Runs the stage, a stage running repeatedly accumulates its time
+    pub fn measure<T>(&mut self, stage: BuildStage, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.add(stage, start.elapsed());
+        result
+    }
+
+    pub fn add(&mut self, stage: BuildStage, duration: Duration) {
+        let slot = &mut self.durations[stage.get_index()];
+        *slot = Some(slot.unwrap_or_default() + duration);
+    }
+
+    pub fn get(&self, stage: BuildStage) -> Option<Duration> {
+        self.durations[stage.get_index()]
+    }
+
+    /// None if no chunk cache is in use
+    pub fn set_cache_hit(&mut self, cache_hit: bool) {
+        self.cache_hit = Some(cache_hit);
+    }
+}
+
+struct Samples {
+    recent: VecDeque<f64>,
+    histogram: [u32; HISTOGRAM_BOUNDS.len() + 1],
+    count: u64,
+    accumulated: f64,
+    max: f64,
+}
+
+impl Samples {
+    fn new() -> Self {
+        Self {
+            recent: VecDeque::with_capacity(SAMPLE_WINDOW),
+            histogram: [0; HISTOGRAM_BOUNDS.len() + 1],
+            count: 0,
+            accumulated: 0.,
+            max: 0.,
+        }
+    }
+
+    fn add(&mut self, duration: Duration) {
+        let ms = duration.as_secs_f64() * 1000.;
+        if self.recent.len() == SAMPLE_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(ms);
+        let bucket = HISTOGRAM_BOUNDS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(HISTOGRAM_BOUNDS.len());
+        self.histogram[bucket] += 1;
+        self.count += 1;
+        self.accumulated += ms;
+        self.max = self.max.max(ms);
+    }
+
+    fn get_metrics(&self, name: &'static str) -> StageMetrics {
+        let mut sorted: Vec<f64> = self.recent.iter().cloned().collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mean = if self.count > 0 {
+            self.accumulated / self.count as f64
+        } else {
+            0.
+        };
+        StageMetrics::new(
+            name,
+            self.count,
+            mean,
+            [
+                get_percentile(&sorted, 50.),
+                get_percentile(&sorted, 90.),
+                get_percentile(&sorted, 99.),
+            ],
+            self.max,
+            self.histogram.to_vec(),
+        )
+    }
+}
+
+/// Build timings of all workers, shared with the chunk loader
 pub struct BuildStats {
-    build_time_accumulated: u32,
-    build_count: u32,
+    total: Samples,
+    stages: Vec<Samples>,
+    cache_hits: u64,
+    cache_misses: u64,
+    start: Instant,
+    recent_builds: VecDeque<Instant>,
+    memory: MemoryUsage,
 }
 
 impl Default for BuildStats {
     fn default() -> Self {
         Self {
-            build_time_accumulated: 0,
-            build_count: 0,
+            total: Samples::new(),
+            stages: BuildStage::ALL.iter().map(|_| Samples::new()).collect(),
+            cache_hits: 0,
+            cache_misses: 0,
+            start: Instant::now(),
+            recent_builds: VecDeque::new(),
+            memory: MemoryUsage::default(),
         }
     }
 }
 
 impl BuildStats {
-    pub fn add_time(&mut self, build_time: u32) {
-        if self.build_count > 40000 {
-            self.build_time_accumulated = 0;
-            self.build_count = 0;
+    pub fn add_build(&mut self, timings: &BuildTimings, build_time: Duration) {
+        for stage in BuildStage::ALL.iter() {
+            if let Some(duration) = timings.get(*stage) {
+                self.stages[stage.get_index()].add(duration);
+            }
+        }
+        match timings.cache_hit {
+            Some(true) => self.cache_hits += 1,
+            Some(false) => self.cache_misses += 1,
+            None => {}
+        }
+        self.total.add(build_time);
+
+        let now = Instant::now();
+        self.recent_builds.push_back(now);
+        while let Some(oldest) = self.recent_builds.front() {
+            if now.duration_since(*oldest) > RATE_WINDOW {
+                self.recent_builds.pop_front();
+            } else {
+                break;
+            }
         }
-        self.build_time_accumulated += build_time;
-        self.build_count += 1;
     }
-    pub fn get_avg_time(&mut self) -> f64 {
-        if self.build_count > 0 {
-            self.build_time_accumulated as f64 / self.build_count as f64
+
+    /// For stages outside of the workers, like the upload
+    pub fn add_stage_time(&mut self, stage: BuildStage, duration: Duration) {
+        self.stages[stage.get_index()].add(duration);
+    }
+
+    /// Reported by the chunk manager, which owns the chunks
+    pub fn set_memory_usage(&mut self, memory: MemoryUsage) {
+        self.memory = memory;
+    }
+
+    pub fn get_metrics(&self, queue_depth: usize) -> BuildMetrics {
+        let stages = BuildStage::ALL
+            .iter()
+            .zip(self.stages.iter())
+            .filter(|(_, samples)| samples.count > 0)
+            .map(|(stage, samples)| samples.get_metrics(stage.get_name()))
+            .collect();
+        BuildMetrics::new(
+            self.total.get_metrics("total"),
+            stages,
+            self.cache_hits,
+            self.cache_misses,
+            self.get_chunks_per_second(),
+            queue_depth,
+            self.memory,
+        )
+    }
+
+    fn get_chunks_per_second(&self) -> f64 {
+        let window = RATE_WINDOW.min(self.start.elapsed()).as_secs_f64();
+        let now = Instant::now();
+        let count = self
+            .recent_builds
+            .iter()
+            .filter(|time| now.duration_since(**time) <= RATE_WINDOW)
+            .count();
+        if window > 0. {
+            count as f64 / window
         } else {
             0.
         }
     }
 }
+
+// Nearest rank of sorted samples
+fn get_percentile(sorted: &[f64], percentile: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.;
+    }
+    let rank = (percentile / 100. * sorted.len() as f64).ceil() as usize;
+    sorted[rank.max(1).min(sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_percentiles() {
+        let mut stats = BuildStats::default();
+        for i in 1..=100 {
+            let mut timings = BuildTimings::new();
+            timings.add(BuildStage::Noise, Duration::from_millis(i));
+            timings.set_cache_hit(i % 4 == 0);
+            stats.add_build(&timings, Duration::from_millis(i + 1));
+        }
+        let metrics = stats.get_metrics(3);
+        let noise = metrics.get_stage(BuildStage::Noise).unwrap();
+        assert_eq!(noise.get_count(), 100);
+        assert!((noise.get_p50() - 50.).abs() < 1e-6);
+        assert!((noise.get_p90() - 90.).abs() < 1e-6);
+        assert!((noise.get_p99() - 99.).abs() < 1e-6);
+        assert!((noise.get_max() - 100.).abs() < 1e-6);
+        assert!((noise.get_mean() - 50.5).abs() < 1e-6);
+        assert!(metrics.get_stage(BuildStage::Volume).is_none());
+        assert_eq!(metrics.get_total().get_count(), 100);
+        assert_eq!(metrics.get_cache_hit_rate(), Some(0.25));
+        assert_eq!(metrics.get_queue_depth(), 3);
+    }
+
+    #[test]
+    fn test_histogram_buckets() {
+        let mut timings = BuildTimings::new();
+        timings.measure(BuildStage::Water, || ());
+        timings.add(BuildStage::Normals, Duration::from_millis(3));
+        timings.add(BuildStage::Normals, Duration::from_millis(4));
+        let mut stats = BuildStats::default();
+        stats.add_build(&timings, Duration::from_millis(5000));
+        let metrics = stats.get_metrics(0);
+
+        let normals = metrics.get_stage(BuildStage::Normals).unwrap();
+        assert!((normals.get_max() - 7.).abs() < 1e-6);
+        assert_eq!(normals.get_histogram()[3], 1);
+        assert_eq!(
+            metrics.get_total().get_histogram()[HISTOGRAM_BOUNDS.len()],
+            1
+        );
+        assert_eq!(metrics.get_stage(BuildStage::Water).unwrap().get_count(), 1);
+        assert_eq!(metrics.get_cache_hit_rate(), None);
+    }
+}
@@ -1,62 +1,145 @@
-use std::convert::TryInto;
+use std::mem;
 
-use super::{get_world_pos, ChunkError};
-use crate::HeightMap;
-use core::graphics::GraphicsError;
-use core::traits::{RenderInfo, Translatable};
-use core::{BoundingBox, Model, Point2f, Point2i, Point3f, Texture};
+use super::BuildOptions;
+use crate::analysis::{AnalysisLayer, AnalysisMap, TerrainAnalysis};
+use crate::surface::SPLAT_CHANNELS;
+use crate::{DensityField, HeightMap, SplatMap, WaterKind, WaterMap};
+use core::{Point2i, Point3f};
 
+/// Terrain data of a chunk, independent of any graphics resources
 pub struct Chunk {
     pos: Point2i,
-    model: Model,
     heightmap: HeightMap,
-    height_normal_texture: Texture,
-    bounding_box: BoundingBox,
+    analysis: Option<TerrainAnalysis>,
+    road_mask: Option<AnalysisMap>,
+    splat: Option<SplatMap>,
+    density: Option<DensityField>,
+    water: WaterMap,
+    edit_revision: u32,
 }
 
 impl Chunk {
-    pub fn new(pos: Point2i, heightmap: HeightMap) -> Result<Self, ChunkError> {
-        let mut model = Model::default();
-        let abs_pos = get_world_pos(pos, None);
-        model.set_translation(abs_pos.extend(0.));
-
-        let texture: Texture = heightmap.clone().try_into()?;
-        let bounding_box = build_bounding_box(&heightmap);
-
-        Ok(Self {
+    pub fn new(
+        pos: Point2i,
+        heightmap: HeightMap,
+        analysis: Option<TerrainAnalysis>,
+        density: Option<DensityField>,
+        water: WaterMap,
+    ) -> Self {
+        Self {
             pos: pos,
-            model: model,
             heightmap: heightmap,
-            height_normal_texture: texture,
-            bounding_box: bounding_box,
-        })
+            analysis: analysis,
+            road_mask: None,
+            splat: None,
+            density: density,
+            water: water,
+            edit_revision: 0,
+        }
+    }
+
+    /// Road surface coverage of the heightmap grid, shaded on top of the terrain
+    pub fn with_road_mask(mut self, road_mask: Option<AnalysisMap>) -> Self {
+        self.road_mask = road_mask;
+        self
+    }
+
+    /// Material weights of the heightmap grid, blended by the surface shader
+    pub fn with_splat_map(mut self, splat: Option<SplatMap>) -> Self {
+        self.splat = splat;
+        self
+    }
+
+    /// Revision of the edit delta applied to the heightmap
+    pub fn with_edit_revision(mut self, edit_revision: u32) -> Self {
+        self.edit_revision = edit_revision;
+        self
+    }
+
+    pub fn get_edit_revision(&self) -> u32 {
+        self.edit_revision
+    }
+
+    pub fn set_edit_revision(&mut self, edit_revision: u32) {
+        self.edit_revision = edit_revision;
     }
 
     pub fn get_pos(&self) -> Point2i {
         self.pos
     }
 
-    pub fn get_height(&self, relative_pos: Point2f) -> f32 {
-        self.heightmap.get_interpolated_height(relative_pos)
+    pub fn get_heightmap(&self) -> &HeightMap {
+        &self.heightmap
     }
 
-    pub fn prepare_rendering(&self, info: &RenderInfo) -> Result<bool, GraphicsError> {
-        let mvp = info.get_camera().create_mvp_matrix(&self.model);
-        if self.bounding_box.is_visible(mvp) {
-            let shader = info.get_active_shader()?;
-            shader.set_resource_mat4("mvp", &mvp)?;
-            shader.set_resource_mat4("model", self.model.get_matrix_ref())?;
-            self.height_normal_texture.activate(0);
-            Ok(true)
-        } else {
-            Ok(false)
+    /// Changes only take effect on the next `update_surface`
+    pub fn get_heightmap_mut(&mut self) -> &mut HeightMap {
+        &mut self.heightmap
+    }
+
+    /// Rebuilds analysis, splat map and height pyramid after the heightmap was modified
+    pub fn update_surface(&mut self, options: &BuildOptions) {
+        self.heightmap.build_pyramid();
+        if self.analysis.is_some() {
+            self.analysis = Some(TerrainAnalysis::from_heightmap(&self.heightmap));
+        }
+        if let Some(splat) = &self.splat {
+            // the climate doesn't change with edits
+            let climate = splat.get_climate().map(|c| c.to_vec());
+            self.splat = Some(SplatMap::from_heightmap(
+                &self.heightmap,
+                self.analysis.as_ref(),
+                climate.as_deref(),
+                options.get_surface_rules(),
+            ));
         }
     }
-}
 
-fn build_bounding_box(height_map: &HeightMap) -> BoundingBox {
-    let max_xy = (height_map.get_size() - 1) as f32;
-    let min = Point3f::new(0., 0., height_map.get_min() as f32);
-    let max = Point3f::new(max_xy, max_xy, height_map.get_max() as f32);
-    BoundingBox::from_min_max(min, max)
+    pub fn get_analysis(&self) -> Option<&TerrainAnalysis> {
+        self.analysis.as_ref()
+    }
+
+    pub fn get_splat_map(&self) -> Option<&SplatMap> {
+        self.splat.as_ref()
+    }
+
+    pub fn get_road_mask(&self) -> Option<&AnalysisMap> {
+        self.road_mask.as_ref()
+    }
+
+    /// Only present for volumetric chunks
+    pub fn get_density(&self) -> Option<&DensityField> {
+        self.density.as_ref()
+    }
+
+    pub fn get_water_map(&self) -> &WaterMap {
+        &self.water
+    }
+
+    /// Estimated bytes of the terrain data, derived from the grid sizes
+    pub fn get_memory_size(&self) -> usize {
+        let cells = |size: i32| (size * size) as usize;
+        let heightmap_size = self.heightmap.get_size();
+        let mut bytes = cells(heightmap_size) * (mem::size_of::<f32>() + mem::size_of::<Point3f>());
+        if self.heightmap.get_pyramid().is_some() {
+            // min, max and average of all levels add up to about a third of the grid each
+            bytes += cells(heightmap_size) * mem::size_of::<f32>();
+        }
+        if let Some(analysis) = &self.analysis {
+            bytes += cells(analysis.get_size())
+                * AnalysisLayer::as_slice().len()
+                * mem::size_of::<f32>();
+        }
+        if let Some(road_mask) = &self.road_mask {
+            bytes += cells(road_mask.get_size()) * mem::size_of::<f32>();
+        }
+        if let Some(splat) = &self.splat {
+            bytes += cells(splat.get_size()) * mem::size_of::<[f32; SPLAT_CHANNELS]>();
+        }
+        if let Some(density) = &self.density {
+            bytes +=
+                cells(density.get_size()) * density.get_layers() as usize * mem::size_of::<f32>();
+        }
+        bytes + cells(self.water.get_size()) * (mem::size_of::<WaterKind>() + mem::size_of::<f32>())
+    }
 }
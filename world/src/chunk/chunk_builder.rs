@@ -1,24 +1,120 @@
-use super::{Chunk, ChunkError};
+use super::{
+    BuildOptions, BuildStage, BuildTimings, Chunk, ChunkCache, ChunkError, ChunkGeometry,
+    ChunkRecord,
+};
+use crate::analysis::TerrainAnalysis;
 use crate::architect::Architect;
-use crate::HeightMap;
+use crate::{EditDelta, SplatMap};
 use core::Point2i;
 
+/// Chunk built on a worker thread, along with its geometry
 pub struct ChunkBuilder {
-    pos: Point2i,
-    heightmap: HeightMap,
+    chunk: Chunk,
+    geometry: ChunkGeometry,
+    timings: BuildTimings,
 }
 
 impl ChunkBuilder {
-    pub fn new(pos: Point2i, architect: &Architect) -> Result<Self, ChunkError> {
-        let heightmap = architect.create_heightmap(pos);
+    pub fn new(
+        pos: Point2i,
+        architect: &Architect,
+        options: &BuildOptions,
+        edit_delta: Option<&EditDelta>,
+        cache: Option<&ChunkCache>,
+    ) -> Result<Self, ChunkError> {
+        let mut timings = BuildTimings::new();
+        let chunk = Self::build_chunk(pos, architect, options, edit_delta, cache, &mut timings)?;
+        let geometry = timings.measure(BuildStage::Geometry, || {
+            ChunkGeometry::from_chunk(&chunk, options)
+        })?;
         let builder = Self {
-            pos: pos,
-            heightmap: heightmap,
+            chunk: chunk,
+            geometry: geometry,
+            timings: timings,
         };
         Ok(builder)
     }
 
-    pub fn finish(self) -> Result<Chunk, ChunkError> {
-        Chunk::new(self.pos, self.heightmap)
+    /// Chunk data without the geometry, which is only needed for rendering
+    pub fn build_chunk(
+        pos: Point2i,
+        architect: &Architect,
+        options: &BuildOptions,
+        edit_delta: Option<&EditDelta>,
+        cache: Option<&ChunkCache>,
+        timings: &mut BuildTimings,
+    ) -> Result<Chunk, ChunkError> {
+        let cached = timings.measure(BuildStage::CacheLoad, || {
+            cache.map(|c| c.load(pos)).transpose()
+        });
+        let record = match cached {
+            Ok(Some(Some(record))) => {
+                timings.set_cache_hit(true);
+                record
+            }
+            result => {
+                if let Err(e) = result {
+                    warn!("Could not load chunk {} from cache: {}", pos, e);
+                }
+                let record = ChunkRecord::generate(pos, architect, timings);
+                if let Some(cache) = cache {
+                    timings.set_cache_hit(false);
+                    cache.store(pos, &record)?;
+                }
+                record
+            }
+        };
+        let (mut heightmap, road_mask, mut water) = record.into_parts();
+        if let Some(delta) = edit_delta {
+            timings.measure(BuildStage::Edits, || delta.apply_to(&mut heightmap));
+            timings.measure(BuildStage::Normals, || {
+                architect.update_normals(&mut heightmap)
+            });
+            water = timings.measure(BuildStage::Water, || {
+                architect.create_water_map(pos, &heightmap)
+            });
+        }
+        let density = if options.use_volumetric_terrain() {
+            Some(timings.measure(BuildStage::Volume, || {
+                architect.create_density_field(pos, &heightmap)
+            }))
+        } else {
+            None
+        };
+        // the pyramid is only used for the coarser levels of detail
+        timings.measure(BuildStage::Geometry, || heightmap.build_pyramid());
+        let analysis = if options.use_terrain_analysis() {
+            Some(timings.measure(BuildStage::Analysis, || {
+                TerrainAnalysis::from_heightmap(&heightmap)
+            }))
+        } else {
+            None
+        };
+        let splat = if options.get_surface_rules().is_empty() {
+            None
+        } else {
+            Some(timings.measure(BuildStage::Decoration, || {
+                let rules = options.get_surface_rules();
+                let climate = if rules.iter().any(|rule| rule.uses_climate()) {
+                    Some(architect.sample_climate(pos))
+                } else {
+                    None
+                };
+                SplatMap::from_heightmap(&heightmap, analysis.as_ref(), climate.as_deref(), rules)
+            }))
+        };
+        let chunk = Chunk::new(pos, heightmap, analysis, density, water)
+            .with_road_mask(road_mask)
+            .with_splat_map(splat)
+            .with_edit_revision(edit_delta.map_or(0, |delta| delta.get_revision()));
+        Ok(chunk)
+    }
+
+    pub fn get_timings(&self) -> &BuildTimings {
+        &self.timings
+    }
+
+    pub fn finish(self) -> (Chunk, ChunkGeometry) {
+        (self.chunk, self.geometry)
     }
 }
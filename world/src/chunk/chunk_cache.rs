@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Cursor, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::region_file::{RegionFile, RegionHeader};
+use super::{ChunkError, ChunkRecord};
+use core::traits::{Loadable, Saveable};
+use core::{Point2i, Seed};
+
+// Chunks per region file along each axis
+const REGION_SIZE: i32 = 16;
+// The least recently used region files are closed beyond this count
+const MAX_OPEN_REGIONS: usize = 16;
+
+struct OpenRegion {
+    file: RegionFile,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct OpenRegions {
+    region_map: BTreeMap<Point2i, OpenRegion>,
+    use_count: u64,
+}
+
+type RegionMap = Arc<Mutex<OpenRegions>>;
+
+/// Persistent store of generated chunks, grouped into region files per world seed.
+/// Records are written back by a background thread.
+pub struct ChunkCache {
+    directory: PathBuf,
+    header: RegionHeader,
+    region_map: RegionMap,
+    sender: Mutex<Option<Sender<(Point2i, Vec<u8>)>>>,
+    writer_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl ChunkCache {
+    /// Cached chunks of other generator versions, grid sizes or terrain settings
+    /// are discarded on first access
+    pub fn new(
+        directory: &Path,
+        seed: Seed,
+        generator_version: u32,
+        grid_size: i32,
+        terrain_hash: u64,
+    ) -> Result<Self, Error> {
+        let directory = directory.join(format!("{}", seed));
+        fs::create_dir_all(&directory)?;
+        let header = RegionHeader {
+            seed: seed,
+            generator_version: generator_version,
+            grid_size: grid_size,
+            terrain_hash: terrain_hash,
+        };
+        let region_map: RegionMap = Arc::new(Mutex::new(OpenRegions::default()));
+
+        let (sender, receiver) = mpsc::channel::<(Point2i, Vec<u8>)>();
+        let writer_directory = directory.clone();
+        let writer_region_map = region_map.clone();
+        let writer_handle = thread::spawn(move || {
+            // ends once the cache is dropped and all pending records are written
+            for (chunk_pos, data) in receiver {
+                let result = with_region_file(
+                    &writer_region_map,
+                    &writer_directory,
+                    &header,
+                    chunk_pos,
+                    |region_file| region_file.append(chunk_pos, &data),
+                );
+                if let Err(e) = result {
+                    error!("Could not write chunk {} to cache: {}", chunk_pos, e);
+                }
+            }
+        });
+
+        Ok(Self {
+            directory: directory,
+            header: header,
+            region_map: region_map,
+            sender: Mutex::new(Some(sender)),
+            writer_handle: Some(writer_handle),
+        })
+    }
+
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// None if the chunk isn't cached yet
+    pub fn load(&self, chunk_pos: Point2i) -> Result<Option<ChunkRecord>, ChunkError> {
+        let data = with_region_file(
+            &self.region_map,
+            &self.directory,
+            &self.header,
+            chunk_pos,
+            |region_file| region_file.read(chunk_pos),
+        )?;
+        match data {
+            Some(data) => {
+                let mut record = ChunkRecord::default();
+                record.load(&mut Cursor::new(data))?;
+                if record.get_grid_size() != self.header.grid_size {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("invalid grid size of cached chunk: {}", record.get_grid_size()),
+                    )
+                    .into());
+                }
+                Ok(Some(record))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Queues the record for writing, returns before it is written
+    pub fn store(&self, chunk_pos: Point2i, record: &ChunkRecord) -> Result<(), ChunkError> {
+        let mut data = Vec::new();
+        record.save(&mut data)?;
+        let sender = self.sender.lock().or(Err(ChunkError::MutexPoison))?;
+        if let Some(sender) = sender.as_ref() {
+            if sender.send((chunk_pos, data)).is_err() {
+                warn!(
+                    "Chunk cache writer stopped, chunk {} is not cached",
+                    chunk_pos
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ChunkCache {
+    fn drop(&mut self) {
+        // closing the channel lets the writer finish the pending records
+        if let Ok(mut sender) = self.sender.lock() {
+            sender.take();
+        }
+        if let Some(handle) = self.writer_handle.take() {
+            if handle.join().is_err() {
+                warn!("Chunk cache writer panicked");
+            }
+        }
+    }
+}
+
+fn get_region_pos(chunk_pos: Point2i) -> Point2i {
+    Point2i::new(
+        chunk_pos[0].div_euclid(REGION_SIZE),
+        chunk_pos[1].div_euclid(REGION_SIZE),
+    )
+}
+
+// Opens the region file of the chunk on first access, closing the least recently used one
+// once too many are open
+fn with_region_file<T, F: FnOnce(&mut RegionFile) -> Result<T, Error>>(
+    region_map: &RegionMap,
+    directory: &Path,
+    header: &RegionHeader,
+    chunk_pos: Point2i,
+    f: F,
+) -> Result<T, ChunkError> {
+    let region_pos = get_region_pos(chunk_pos);
+    let mut regions = region_map.lock().or(Err(ChunkError::MutexPoison))?;
+    regions.use_count += 1;
+    let use_count = regions.use_count;
+    if !regions.region_map.contains_key(&region_pos) {
+        let path = directory.join(format!("r.{}.{}.region", region_pos[0], region_pos[1]));
+        let region_file = RegionFile::open(&path, header)?;
+        trace!(
+            "Opened chunk cache region {} with {} chunks",
+            region_pos,
+            region_file.get_chunk_count()
+        );
+        while regions.region_map.len() >= MAX_OPEN_REGIONS {
+            let oldest = regions
+                .region_map
+                .iter()
+                .min_by_key(|(_, region)| region.last_used)
+                .map(|(pos, _)| *pos);
+            if let Some(pos) = oldest {
+                regions.region_map.remove(&pos);
+            }
+        }
+        regions.region_map.insert(
+            region_pos,
+            OpenRegion {
+                file: region_file,
+                last_used: use_count,
+            },
+        );
+    }
+    match regions.region_map.get_mut(&region_pos) {
+        Some(region) => {
+            region.last_used = use_count;
+            Ok(f(&mut region.file)?)
+        }
+        None => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HeightMap, WaterKind, WaterMap};
+
+    fn create_record(height: f32) -> ChunkRecord {
+        let mut heightmap = HeightMap::new(5, 2.);
+        heightmap.set(Point2i::new(1, 3), height);
+        let heights: Vec<f32> = heightmap.get_list().to_vec();
+        let water = WaterMap::from_heights(&heights, 5, 2., 0.5);
+        ChunkRecord::new(heightmap, None, water)
+    }
+
+    fn get_test_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("chunk_cache_{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let directory = get_test_directory("roundtrip");
+        let seed = Seed::from_string("CACHE");
+        {
+            let cache = ChunkCache::new(&directory, seed, 1, 5, 0).unwrap();
+            cache
+                .store(Point2i::new(-1, 20), &create_record(3.))
+                .unwrap();
+            cache.store(Point2i::new(0, 0), &create_record(1.)).unwrap();
+            cache
+                .store(Point2i::new(-1, 20), &create_record(4.))
+                .unwrap();
+        }
+        let cache = ChunkCache::new(&directory, seed, 1, 5, 0).unwrap();
+        let (heightmap, _, _) = cache
+            .load(Point2i::new(-1, 20))
+            .unwrap()
+            .unwrap()
+            .into_parts();
+        assert_eq!(4., heightmap.get(Point2i::new(1, 3)));
+        let (heightmap, _, water) = cache
+            .load(Point2i::new(0, 0))
+            .unwrap()
+            .unwrap()
+            .into_parts();
+        assert_eq!(1., heightmap.get(Point2i::new(1, 3)));
+        assert_eq!(WaterKind::Ocean, water.get_kind(Point2i::new(0, 0)));
+        assert!(cache.load(Point2i::new(0, 1)).unwrap().is_none());
+        drop(cache);
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_open_regions_limited() {
+        let directory = get_test_directory("open_regions");
+        let seed = Seed::from_string("CACHE");
+        let region_count = MAX_OPEN_REGIONS as i32 + 4;
+        {
+            let cache = ChunkCache::new(&directory, seed, 1, 5, 0).unwrap();
+            for i in 0..region_count {
+                let chunk_pos = Point2i::new(i * REGION_SIZE, 0);
+                cache.store(chunk_pos, &create_record(i as f32)).unwrap();
+            }
+        }
+        let cache = ChunkCache::new(&directory, seed, 1, 5, 0).unwrap();
+        for i in 0..region_count {
+            let (heightmap, _, _) = cache
+                .load(Point2i::new(i * REGION_SIZE, 0))
+                .unwrap()
+                .unwrap()
+                .into_parts();
+            assert_eq!(i as f32, heightmap.get(Point2i::new(1, 3)));
+        }
+        assert_eq!(
+            MAX_OPEN_REGIONS,
+            cache.region_map.lock().unwrap().region_map.len()
+        );
+        drop(cache);
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_outdated_generator() {
+        let directory = get_test_directory("outdated");
+        let seed = Seed::from_string("CACHE");
+        {
+            let cache = ChunkCache::new(&directory, seed, 1, 5, 0).unwrap();
+            cache.store(Point2i::new(2, 2), &create_record(3.)).unwrap();
+        }
+        let cache = ChunkCache::new(&directory, seed, 2, 5, 0).unwrap();
+        assert!(cache.load(Point2i::new(2, 2)).unwrap().is_none());
+        drop(cache);
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_changed_terrain_settings() {
+        let directory = get_test_directory("settings");
+        let seed = Seed::from_string("CACHE");
+        {
+            let cache = ChunkCache::new(&directory, seed, 1, 5, 7).unwrap();
+            cache.store(Point2i::new(2, 2), &create_record(3.)).unwrap();
+        }
+        let cache = ChunkCache::new(&directory, seed, 1, 5, 8).unwrap();
+        assert!(cache.load(Point2i::new(2, 2)).unwrap().is_none());
+        drop(cache);
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
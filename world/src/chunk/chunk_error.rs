@@ -1,8 +1,11 @@
+use std::io;
 use thiserror::Error;
 
 use core::config::ConfigError;
+use core::file::FileError;
 use core::graphics::{mesh::MeshError, GraphicsError};
 use core::object::ObjectError;
+use core::Point2i;
 
 #[derive(Error, Debug)]
 pub enum ChunkError {
@@ -26,6 +29,21 @@ pub enum ChunkError {
         #[from]
         source: ConfigError,
     },
+    #[error("file: {source}")]
+    File {
+        #[from]
+        source: FileError,
+    },
+    #[error("yaml: {source}")]
+    Yaml {
+        #[from]
+        source: serde_yaml::Error,
+    },
+    #[error("io: {source}")]
+    Io {
+        #[from]
+        source: io::Error,
+    },
 
     #[error("no buffer built: chunk pos = {0}/{1}")]
     NoBufferBuilt(i32, i32),
@@ -33,4 +51,6 @@ pub enum ChunkError {
     HeightmapTriangulation,
     #[error("mutex poisoned")]
     MutexPoison,
+    #[error("chunk builder panicked: chunk pos = {0}, {1}")]
+    BuilderPanic(Point2i, String),
 }
@@ -0,0 +1,113 @@
+use super::Chunk;
+use core::Point2i;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChunkEvent {
+    /// Built by the chunk loader and retrieved on the main thread, fired right before the chunk
+    /// is added to the active chunks. Chunks restored from the retained ones are only loaded.
+    Generated,
+    /// Added to the active chunks
+    Loaded,
+    /// About to be removed from the active chunks
+    Unloading,
+    /// Surface changed by an edit
+    Modified,
+}
+
+/// Called on the main thread with the event, the absolute chunk position and the chunk
+pub type ChunkListener = Box<dyn FnMut(ChunkEvent, Point2i, &Chunk)>;
+
+/// Listeners subscribed to chunk events, identified by the id returned on subscription
+pub struct ChunkEvents {
+    next_id: u32,
+    listener_list: Vec<(u32, ChunkListener)>,
+}
+
+impl ChunkEvents {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            listener_list: Vec::new(),
+        }
+    }
+
+    pub fn subscribe(&mut self, listener: ChunkListener) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.listener_list.push((id, listener));
+        id
+    }
+
+    /// False if no listener had the id
+    pub fn unsubscribe(&mut self, id: u32) -> bool {
+        let count = self.listener_list.len();
+        self.listener_list
+            .retain(|(listener_id, _)| *listener_id != id);
+        self.listener_list.len() < count
+    }
+
+    pub fn notify(&mut self, event: ChunkEvent, pos: Point2i, chunk: &Chunk) {
+        for (_, listener) in self.listener_list.iter_mut() {
+            listener(event, pos, chunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HeightMap, WaterMap};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn create_chunk() -> Chunk {
+        Chunk::new(
+            Point2i::new(0, 0),
+            HeightMap::new(9, 1.),
+            None,
+            None,
+            WaterMap::new(9, 1.),
+        )
+    }
+
+    #[test]
+    fn test_notify_in_subscription_order() {
+        let mut events = ChunkEvents::new();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let ids: Vec<u32> = (0..3)
+            .map(|i| {
+                let calls = calls.clone();
+                events.subscribe(Box::new(move |event, pos, _| {
+                    calls.borrow_mut().push((i, event, pos))
+                }))
+            })
+            .collect();
+        assert_eq!(vec![0, 1, 2], ids);
+
+        let pos = Point2i::new(3, -1);
+        events.notify(ChunkEvent::Loaded, pos, &create_chunk());
+        assert_eq!(
+            vec![
+                (0, ChunkEvent::Loaded, pos),
+                (1, ChunkEvent::Loaded, pos),
+                (2, ChunkEvent::Loaded, pos)
+            ],
+            *calls.borrow()
+        );
+    }
+
+    #[test]
+    fn test_unsubscribe() {
+        let mut events = ChunkEvents::new();
+        let calls = Rc::new(RefCell::new(0));
+        let counter = calls.clone();
+        let id = events.subscribe(Box::new(move |_, _, _| *counter.borrow_mut() += 1));
+        assert!(!events.unsubscribe(id + 1));
+        assert!(events.unsubscribe(id));
+        assert!(!events.unsubscribe(id));
+        events.notify(ChunkEvent::Modified, Point2i::new(0, 0), &create_chunk());
+        assert_eq!(0, *calls.borrow());
+        // ids aren't reused
+        assert_ne!(id, events.subscribe(Box::new(|_, _, _| {})));
+    }
+}
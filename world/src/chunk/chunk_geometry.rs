@@ -0,0 +1,84 @@
+use std::iter;
+
+use super::{BuildOptions, Chunk, ChunkError};
+use crate::{HeightMap, HeightSampler};
+use core::graphics::mesh::Triangle;
+use core::{Point2f, Point2i};
+
+/// Surface meshes per chunk, from the full resolution to the coarsest one
+pub const LOD_COUNT: usize = 3;
+
+/// Triangles of the terrain surface per level of detail and of the water surface.
+/// Built along with the chunk, so the render cache only has to upload them.
+pub struct ChunkGeometry {
+    lod_triangles: Vec<Vec<Triangle>>,
+    water_triangles: Vec<Triangle>,
+}
+
+impl ChunkGeometry {
+    /// Volumetric chunks have the full resolution only. The height pyramid has to be built.
+    pub fn from_chunk(chunk: &Chunk, options: &BuildOptions) -> Result<Self, ChunkError> {
+        let lod_triangles = match chunk.get_density() {
+            Some(density) => vec![density.triangulate()],
+            None => triangulate_lods(chunk.get_heightmap(), options)?,
+        };
+        Ok(Self {
+            lod_triangles: lod_triangles,
+            water_triangles: chunk.get_water_map().triangulate(),
+        })
+    }
+
+    pub fn get_lod_triangles(&self) -> &[Vec<Triangle>] {
+        &self.lod_triangles
+    }
+
+    pub fn get_water_triangles(&self) -> &[Triangle] {
+        &self.water_triangles
+    }
+}
+
+/// Surface triangles for every level of detail, coarser levels are triangulated from the
+/// averaged heights of the pyramid, which has to be built. Neighbouring chunks may use another
+/// level, so the skirts reach deep enough to cover the deviation of the averages from the
+/// full resolution on the border on either side.
+pub fn triangulate_lods(
+    heightmap: &HeightMap,
+    options: &BuildOptions,
+) -> Result<Vec<Vec<Triangle>>, ChunkError> {
+    let reduced_list: Vec<HeightMap> = (1..LOD_COUNT)
+        .filter_map(|lod| heightmap.get_reduced(lod))
+        .collect();
+    let deviation = reduced_list
+        .iter()
+        .map(|reduced| get_border_deviation(heightmap, reduced))
+        .fold(0., f32::max);
+    let skirt_depth = options.get_skirt_depth() + 2. * deviation;
+    iter::once(heightmap)
+        .chain(reduced_list.iter())
+        .map(|hm| {
+            hm.triangulate_adaptive(options.get_mesh_max_error(), skirt_depth)
+                .ok_or(ChunkError::HeightmapTriangulation)
+        })
+        .collect()
+}
+
+// Largest height difference on the border between the full resolution and the reduced map
+fn get_border_deviation(heightmap: &HeightMap, reduced: &HeightMap) -> f32 {
+    let last = heightmap.get_size() - 1;
+    let mut deviation: f32 = 0.;
+    for i in 0..last + 1 {
+        for grid_pos in [
+            Point2i::new(i, 0),
+            Point2i::new(i, last),
+            Point2i::new(0, i),
+            Point2i::new(last, i),
+        ]
+        .iter()
+        {
+            let pos = Point2f::from(*grid_pos) * heightmap.get_scale_factor();
+            let difference = reduced.sample_bilinear(pos) - heightmap.get(*grid_pos);
+            deviation = deviation.max(difference.abs());
+        }
+    }
+    deviation
+}
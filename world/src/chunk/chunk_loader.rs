@@ -1,53 +1,86 @@
-use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::collections::{BTreeMap, VecDeque};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
+use std::time::Duration;
 
-use super::{BuildStats, Chunk, ChunkBuilder, ChunkError, Worker};
+use super::{
+    BuildMetrics, BuildOptions, BuildStage, BuildStats, Chunk, ChunkBuilder, ChunkCache,
+    ChunkError, ChunkGeometry, ChunkQueue, MemoryUsage, Worker,
+};
 use crate::architect::Architect;
-use core::Point2i;
+use crate::EditStore;
+use core::{Point2f, Point2i};
 
 const INPUT_QUEUE_MAX: usize = 500;
 
 pub struct ChunkLoader {
     stop: Arc<AtomicBool>,
     architect: Arc<Architect>,
-    input_queue: Arc<Mutex<VecDeque<Point2i>>>,
+    options: BuildOptions,
+    edit_store: Arc<Mutex<EditStore>>,
+    chunk_cache: Option<Arc<ChunkCache>>,
+    input_queue: Arc<Mutex<ChunkQueue>>,
+    work_signal: Arc<Condvar>,
     output_queue: Arc<Mutex<VecDeque<ChunkBuilder>>>,
     build_stats: Arc<Mutex<BuildStats>>,
-    handeled_positions: BTreeSet<Point2i>,
     thread_handles: Vec<thread::JoinHandle<()>>,
 }
 
 impl ChunkLoader {
-    pub fn new(architect: Arc<Architect>) -> Self {
+    pub fn new(
+        architect: Arc<Architect>,
+        options: BuildOptions,
+        edit_store: Arc<Mutex<EditStore>>,
+        chunk_cache: Option<ChunkCache>,
+    ) -> Self {
         Self {
             stop: Arc::new(AtomicBool::new(false)),
             architect: architect,
-            input_queue: Arc::new(Mutex::new(VecDeque::new())),
+            options: options,
+            edit_store: edit_store,
+            chunk_cache: chunk_cache.map(Arc::new),
+            input_queue: Arc::new(Mutex::new(ChunkQueue::new())),
+            work_signal: Arc::new(Condvar::new()),
             output_queue: Arc::new(Mutex::new(VecDeque::new())),
             build_stats: Arc::new(Mutex::new(BuildStats::default())),
-            handeled_positions: BTreeSet::new(),
             thread_handles: Vec::new(),
         }
     }
+    /// Workers wait for requests without polling, a worker whose builder panicked
+    /// is restarted
     pub fn start(&mut self, thread_count: usize) {
         if !self.thread_handles.is_empty() {
             warn!("Starting chunk loader threads, but threads already running");
         }
-        self.stop.load(Ordering::Relaxed);
+        self.stop.store(false, Ordering::Relaxed);
         let worker = Worker::new(
             self.architect.clone(),
+            self.options.clone(),
+            self.edit_store.clone(),
+            self.chunk_cache.clone(),
             self.stop.clone(),
             self.input_queue.clone(),
+            self.work_signal.clone(),
             self.output_queue.clone(),
             self.build_stats.clone(),
         );
         for _i in 0..thread_count {
             let next_worker = worker.clone();
-            let handle = thread::spawn(move || match next_worker.work() {
-                Ok(_) => trace!("Worker finished successfully"),
-                Err(e) => error!("Worker error: {}", e),
+            let handle = thread::spawn(move || loop {
+                match next_worker.work() {
+                    Ok(_) => {
+                        trace!("Worker finished successfully");
+                        break;
+                    }
+                    Err(e @ ChunkError::BuilderPanic(..)) => {
+                        error!("Worker error: {}, restarting worker", e)
+                    }
+                    Err(e) => {
+                        error!("Worker error: {}", e);
+                        break;
+                    }
+                }
             });
             self.thread_handles.push(handle);
         }
@@ -57,6 +90,7 @@ impl ChunkLoader {
     pub fn stop(&mut self) {
         info!("Stopping chunk loader threads");
         self.stop.store(true, Ordering::Relaxed);
+        self.notify_workers();
         let mut stop_count = 0;
         while let Some(handle) = self.thread_handles.pop() {
             match handle.join() {
@@ -69,50 +103,79 @@ impl ChunkLoader {
         info!("Stopped {} chunk loader threads", stop_count);
     }
 
-    pub fn get(&mut self, max_taken: usize) -> Result<BTreeMap<Point2i, Chunk>, ChunkError> {
+    /// Built chunks with their geometry, at most the given count
+    pub fn get(
+        &mut self,
+        max_taken: usize,
+    ) -> Result<BTreeMap<Point2i, (Chunk, ChunkGeometry)>, ChunkError> {
         let mut chunks = BTreeMap::new();
-        match self.output_queue.lock() {
-            Ok(mut guard) => {
-                while chunks.len() < max_taken {
-                    if let Some(cb) = (*guard).pop_front() {
-                        let chunk = cb.finish()?;
-                        let pos = chunk.get_pos();
-                        self.handeled_positions.remove(&pos);
-                        chunks.insert(pos, chunk);
-                    } else {
-                        break;
-                    }
-                }
-            }
-            Err(_poisoned) => {
-                return Err(ChunkError::MutexPoison);
+        let mut output_queue = self.output_queue.lock().or(Err(ChunkError::MutexPoison))?;
+        let mut input_queue = self.input_queue.lock().or(Err(ChunkError::MutexPoison))?;
+        while chunks.len() < max_taken {
+            if let Some(cb) = output_queue.pop_front() {
+                let (chunk, geometry) = cb.finish();
+                let pos = chunk.get_pos();
+                input_queue.finish(pos);
+                chunks.insert(pos, (chunk, geometry));
+            } else {
+                break;
             }
         }
         Ok(chunks)
     }
 
-    pub fn request(&mut self, chunk_pos: &[Point2i]) -> Result<(), ChunkError> {
+    /// Replaces the pending requests, nearest positions in view direction are built first.
+    /// Center and view direction are given in chunks.
+    pub fn request(
+        &mut self,
+        center: Point2f,
+        view_direction: Point2f,
+        chunk_pos: &[Point2i],
+    ) -> Result<(), ChunkError> {
+        self.input_queue
+            .lock()
+            .map(|mut q| q.request(center, view_direction, chunk_pos, INPUT_QUEUE_MAX))
+            .or(Err(ChunkError::MutexPoison))?;
+        self.work_signal.notify_all();
+        Ok(())
+    }
+
+    /// Requested positions not yet taken by a worker
+    pub fn get_queue_depth(&self) -> usize {
         match self.input_queue.lock() {
-            Ok(mut guard) => {
-                for pos in chunk_pos {
-                    if (*guard).len() >= INPUT_QUEUE_MAX {
-                        break;
-                    }
-                    if self.handeled_positions.insert(*pos) {
-                        (*guard).push_back(*pos);
-                    }
-                }
-                Ok(())
-            }
-            Err(_) => Err(ChunkError::MutexPoison),
+            Ok(guard) => (*guard).get_depth(),
+            Err(_poisoned) => 0,
         }
     }
 
-    pub fn get_avg_build_time(&self) -> f64 {
-        match self.build_stats.lock() {
-            Ok(mut guard) => (*guard).get_avg_time(),
-            Err(_poisoned) => 0.,
-        }
+    /// Snapshot of the build timings along with the current queue depth
+    pub fn get_build_metrics(&self) -> Result<BuildMetrics, ChunkError> {
+        let queue_depth = self.get_queue_depth();
+        self.build_stats
+            .lock()
+            .map(|bs| bs.get_metrics(queue_depth))
+            .or(Err(ChunkError::MutexPoison))
+    }
+
+    pub fn set_memory_usage(&self, memory: MemoryUsage) -> Result<(), ChunkError> {
+        self.build_stats
+            .lock()
+            .map(|mut bs| bs.set_memory_usage(memory))
+            .or(Err(ChunkError::MutexPoison))
+    }
+
+    /// Records a stage which runs outside of the workers
+    pub fn add_stage_time(&self, stage: BuildStage, duration: Duration) -> Result<(), ChunkError> {
+        self.build_stats
+            .lock()
+            .map(|mut bs| bs.add_stage_time(stage, duration))
+            .or(Err(ChunkError::MutexPoison))
+    }
+
+    // Taking the lock first ensures no worker is between checking the stop flag and waiting
+    fn notify_workers(&self) {
+        let _guard = self.input_queue.lock();
+        self.work_signal.notify_all();
     }
 }
 
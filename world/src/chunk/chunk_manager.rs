@@ -1,54 +1,143 @@
-use std::collections::BTreeMap;
-use std::convert::TryInto;
 use std::iter;
-use std::rc::Rc;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
 
-use super::{get_chunk_pos, get_relative_pos, Chunk, ChunkError, ChunkLoader, CHUNK_SIZE};
-use crate::{Architect, HeightMap};
+use super::{
+    BuildMetrics, BuildOptions, BuildStage, Chunk, ChunkCache, ChunkError, ChunkEvent, ChunkEvents,
+    ChunkGeometry, ChunkListener, ChunkLoader, ChunkRetention, LayerSampler, MemoryUsage,
+    RenderCache, TerrainStore, WorldLayout,
+};
+use crate::analysis::AnalysisLayer;
+use crate::architect::GENERATOR_VERSION;
+use crate::{Architect, Brush, EditStore, HeightSampler, SurfaceInfo};
 use core::light::SceneLights;
 use core::{
-    Config, GraphicsError, Mesh, Point2i, Point3f, RenderInfo, Renderable, ShaderProgram,
-    ShaderProgramBuilder, Timer, Updatable, UpdateError,
+    Config, GraphicsError, Point2f, Point2i, Point3f, RenderInfo, Renderable, Timer, Updatable,
+    UpdateError,
 };
 
+/// Loads chunks around the center into the terrain store and mirrors them into the render
+/// cache. Positions passed to and returned from the chunk manager are relative to the render
+/// origin, which is moved along with the player to keep them small.
 pub struct ChunkManager {
-    shader: Rc<ShaderProgram>,
     chunk_loader: ChunkLoader,
-    chunk_map: BTreeMap<Point2i, Chunk>,
+    build_options: BuildOptions,
+    store: TerrainStore,
+    render_cache: Option<RenderCache>,
+    events: ChunkEvents,
+    edit_store: Arc<Mutex<EditStore>>,
+    edit_file: PathBuf,
+    edit_save_timer: Timer,
     build_stats_timer: Timer,
     chunk_retrieval_timer: Timer,
-    mesh: [Mesh; 2],
     lod_distances: [i32; 3],
+    unload_radius: i32,
+    retention: ChunkRetention,
+    memory_budget: usize,
+    time: u64,
 }
 
 impl ChunkManager {
     pub fn new(architect: Arc<Architect>, config: &Config) -> Result<Self, ChunkError> {
-        let mesh_lod0: Mesh = HeightMap::new(CHUNK_SIZE, 1.).try_into()?;
-        let mesh_lod1: Mesh = HeightMap::new(CHUNK_SIZE / 8, 9.).try_into()?;
+        Self::create(architect, config, true)
+    }
+
+    /// Without a render cache, so no graphics context is needed.
+    /// Rendering does nothing, all queries work as usual.
+    pub fn headless(architect: Arc<Architect>, config: &Config) -> Result<Self, ChunkError> {
+        Self::create(architect, config, false)
+    }
+
+    fn create(
+        architect: Arc<Architect>,
+        config: &Config,
+        rendering: bool,
+    ) -> Result<Self, ChunkError> {
+        let layout = architect.get_layout();
+        let surface_info_path =
+            config.get_str_or_default("surface_info_path", "resources/surface_texture.yaml");
+        let surface_info = SurfaceInfo::from_yaml(&surface_info_path)?;
+        let build_options =
+            BuildOptions::from_config(config).with_surface_rules(surface_info.get_rules());
+        info!(
+            "Surface mesh max error is {}, terrain analysis is {}",
+            build_options.get_mesh_max_error(),
+            if build_options.use_terrain_analysis() {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+        let lod_distances = get_lod_distances(config);
+        let unload_radius = get_unload_radius(config, lod_distances[2]);
+        let memory_budget =
+            config.get_uint_or_default("chunk_memory_budget", 1024) as usize * 1024 * 1024;
+        info!(
+            "Chunks load within {} and unload beyond {} chunks, memory budget is {} MB",
+            lod_distances[2],
+            unload_radius,
+            memory_budget / (1024 * 1024)
+        );
+        let render_cache = if rendering {
+            Some(RenderCache::new(
+                config,
+                layout,
+                &surface_info,
+                lod_distances,
+            )?)
+        } else {
+            None
+        };
+        let material_names: Vec<String> = (0..surface_info.get_material_count())
+            .filter_map(|index| surface_info.get_material_name(index))
+            .map(String::from)
+            .collect();
 
-        let surface_shader_dir = config.get_str("surface_shader_dir")?;
-        let surface_shader_program = load_surface_shader(surface_shader_dir)?;
-        surface_shader_program
-            .set_resource_integer("chunk_size", CHUNK_SIZE)
-            .map_err(GraphicsError::from)?;
+        let edit_directory = config.get_str_or_default("edit_directory", "edits");
+        let edit_file = EditStore::get_file_path(Path::new(&edit_directory), architect.get_seed());
+        let edit_store =
+            EditStore::load_file(&edit_file, architect.get_seed(), layout.get_grid_size())?;
+        info!(
+            "Loaded terrain edits of {} chunks from '{}'",
+            edit_store.get_chunk_count(),
+            edit_file.display()
+        );
+        let edit_store = Arc::new(Mutex::new(edit_store));
+        let chunk_cache = create_chunk_cache(config, &architect);
 
         let mut cm = Self {
-            shader: Rc::new(surface_shader_program),
-            chunk_loader: ChunkLoader::new(architect),
-            chunk_map: BTreeMap::default(),
+            chunk_loader: ChunkLoader::new(
+                architect,
+                build_options.clone(),
+                edit_store.clone(),
+                chunk_cache,
+            ),
+            build_options: build_options,
+            store: TerrainStore::new(layout).with_material_names(material_names),
+            render_cache: render_cache,
+            events: ChunkEvents::new(),
+            edit_store: edit_store,
+            edit_file: edit_file,
+            edit_save_timer: Timer::new(10000),
             build_stats_timer: Timer::new(5000),
             chunk_retrieval_timer: Timer::new(500),
-            mesh: [mesh_lod0, mesh_lod1],
-            lod_distances: get_lod_distances(config),
+            lod_distances: lod_distances,
+            unload_radius: unload_radius,
+            retention: ChunkRetention::new(),
+            memory_budget: memory_budget,
+            time: 0,
         };
-        cm.chunk_loader.start(8);
+        cm.chunk_loader.start(get_thread_count(config));
         Ok(cm)
     }
 
-    pub fn request(&mut self, center: Point3f) -> Result<(), ChunkError> {
+    /// Requests the missing chunks around the center, cancelling requests out of range.
+    /// Chunks in view direction are built first, recently unloaded chunks are restored.
+    pub fn request(&mut self, center: Point3f, view_direction: Point3f) -> Result<(), ChunkError> {
         let mut request_list: Vec<Point2i> = Vec::new();
-        let center_chunk = get_chunk_pos(center);
+        let center_chunk = self.store.get_chunk_pos(center);
 
         let max_distance = self.lod_distances[2] as f32;
         for r in 0..self.lod_distances[2] {
@@ -68,77 +157,336 @@ impl ChunkManager {
             });
             for offset in offset_iter {
                 let abs_pos = center_chunk + offset;
-                if self.chunk_map.get(&abs_pos).is_none() && !request_list.contains(&abs_pos) {
+                if self.store.get_chunk(abs_pos).is_none()
+                    && !request_list.contains(&abs_pos)
+                    && !self.restore_chunk(abs_pos)?
+                {
                     request_list.push(abs_pos);
                 }
             }
         }
-        self.chunk_loader.request(request_list.as_slice())?;
+        let center_pos = center.as_xy() / self.store.get_layout().get_chunk_size()
+            + Point2f::from(self.store.get_origin());
+        self.chunk_loader
+            .request(center_pos, view_direction.as_xy(), request_list.as_slice())?;
         self.unload_distant_chunks(center_chunk)?;
+        if let Some(render_cache) = &mut self.render_cache {
+            render_cache.update_lods(center_pos);
+        }
         Ok(())
     }
 
+    /// The listener is called on chunk events until unsubscribed with the returned id
+    pub fn subscribe(&mut self, listener: ChunkListener) -> u32 {
+        self.events.subscribe(listener)
+    }
+
+    pub fn unsubscribe(&mut self, id: u32) -> bool {
+        self.events.unsubscribe(id)
+    }
+
+    /// Loaded chunks and all queries on them
+    pub fn get_store(&self) -> &TerrainStore {
+        &self.store
+    }
+
     pub fn update_shader_resources(
         &mut self,
         world_center: Point3f,
         fog_color: Point3f,
         scene_lights: &SceneLights,
     ) -> Result<(), GraphicsError> {
-        self.shader.use_program();
-        self.shader
-            .set_resource_vec3("view_pos", &world_center.as_glm())?;
-        self.shader
-            .set_resource_vec3("fog_color", &fog_color.as_glm())?;
+        match &mut self.render_cache {
+            Some(render_cache) => {
+                render_cache.update_shader_resources(world_center, fog_color, scene_lights)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Height of the terrain surface below the given position
+    pub fn get_height(&self, world_pos: Point3f) -> f32 {
+        self.store.get_height(world_pos)
+    }
+
+    pub fn is_solid(&self, world_pos: Point3f) -> bool {
+        self.store.is_solid(world_pos)
+    }
+
+    /// First intersection of the ray with the terrain surface within the given distance
+    pub fn raycast(
+        &self,
+        origin: Point3f,
+        direction: Point3f,
+        max_distance: f32,
+    ) -> Option<Point3f> {
+        self.store.raycast(origin, direction, max_distance)
+    }
+
+    /// Applies the brush to the heightmaps of all loaded chunks within its radius.
+    /// Volumetric chunks and water bodies are left unchanged.
+    pub fn apply_brush(
+        &mut self,
+        brush: &Brush,
+        center: Point2f,
+        intensity: f32,
+    ) -> Result<(), ChunkError> {
+        let mut edit_store = self.edit_store.lock().or(Err(ChunkError::MutexPoison))?;
+        let modified = self.store.apply_brush(
+            brush,
+            center,
+            intensity,
+            &mut edit_store,
+            &self.build_options,
+        );
+        drop(edit_store);
+
+        for chunk_pos in modified {
+            if let Some(chunk) = self.store.get_chunk(chunk_pos) {
+                if let Some(render_cache) = &mut self.render_cache {
+                    let geometry = ChunkGeometry::from_chunk(chunk, &self.build_options)?;
+                    render_cache.update(chunk, Some(&geometry))?;
+                }
+                self.events.notify(ChunkEvent::Modified, chunk_pos, chunk);
+            }
+        }
+        Ok(())
+    }
+
+    /// Stage timings, cache hit rate, throughput and queue depth of the chunk builds
+    pub fn get_build_metrics(&self) -> Result<BuildMetrics, ChunkError> {
+        self.chunk_loader.get_build_metrics()
+    }
 
-        scene_lights.update_lights_for_shader(&self.shader)?;
+    /// Writes the build metrics as CSV for a `.csv` extension, as JSON otherwise
+    pub fn export_build_metrics(&self, path: &Path) -> Result<(), ChunkError> {
+        self.get_build_metrics()?.save_file(path)?;
+        info!("Exported chunk build metrics to '{}'", path.display());
         Ok(())
     }
 
-    pub fn get_height(&self, world_pos: Point3f) -> f32 {
-        let chunk_pos = get_chunk_pos(world_pos);
-        match self.chunk_map.get(&chunk_pos) {
-            Some(chunk) => chunk.get_height(get_relative_pos(world_pos)),
-            None => 0.,
+    /// Writes the terrain edits to disk, if there are unsaved changes
+    pub fn save_edits(&self) -> Result<(), ChunkError> {
+        let mut edit_store = self.edit_store.lock().or(Err(ChunkError::MutexPoison))?;
+        if edit_store.is_modified() {
+            edit_store.save_file(&self.edit_file)?;
+            info!(
+                "Saved terrain edits of {} chunks to '{}'",
+                edit_store.get_chunk_count(),
+                self.edit_file.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Water surface level at the nearest grid point, none if dry or not loaded
+    pub fn get_water_level(&self, world_pos: Point2f) -> Option<f32> {
+        self.store.get_water_level(world_pos)
+    }
+
+    /// Road surface coverage at the nearest grid point, zero if there is no road or not loaded
+    pub fn get_road_coverage(&self, world_pos: Point2f) -> f32 {
+        self.store.get_road_coverage(world_pos)
+    }
+
+    /// Name of the dominant surface material at the nearest grid point, none if not loaded
+    pub fn get_surface_material(&self, world_pos: Point2f) -> Option<&str> {
+        self.store.get_surface_material(world_pos)
+    }
+
+    // Expects the water shader to be active
+    pub fn render_water(&self, info: &mut RenderInfo) -> Result<(), GraphicsError> {
+        match &self.render_cache {
+            Some(render_cache) => render_cache.render_water(info),
+            None => Ok(()),
+        }
+    }
+
+    pub fn get_layer_sampler(&self, layer: AnalysisLayer) -> LayerSampler<'_> {
+        self.store.get_layer_sampler(layer)
+    }
+
+    pub fn get_layout(&self) -> WorldLayout {
+        self.store.get_layout()
+    }
+
+    /// Chunk whose origin is the render origin
+    pub fn get_origin(&self) -> Point2i {
+        self.store.get_origin()
+    }
+
+    /// Moves the render origin, all positions relative to the old origin become invalid
+    pub fn set_origin(&mut self, origin: Point2i) {
+        self.store.set_origin(origin);
+        let store = &self.store;
+        if let Some(render_cache) = &mut self.render_cache {
+            render_cache.set_render_positions(|pos| store.get_render_pos(pos));
         }
     }
 
     fn retrieve_loaded_chunks(&mut self) -> Result<(), ChunkError> {
         let new_chunks = self.chunk_loader.get(500)?;
-        for (pos, chunk) in new_chunks.into_iter() {
-            self.chunk_map.insert(pos, chunk);
+        // chunks edited while being built are discarded and requested again
+        let new_chunks: Vec<(Point2i, (Chunk, ChunkGeometry))> = {
+            let edit_store = self.edit_store.lock().or(Err(ChunkError::MutexPoison))?;
+            new_chunks
+                .into_iter()
+                .filter(|(pos, (chunk, _))| {
+                    let revision = edit_store.get_delta(*pos).map_or(0, |d| d.get_revision());
+                    if chunk.get_edit_revision() != revision {
+                        trace!("Discarding chunk {} built from outdated edits", pos);
+                    }
+                    chunk.get_edit_revision() == revision
+                })
+                .collect()
+        };
+        for (pos, (chunk, geometry)) in new_chunks.into_iter() {
+            // a freshly built chunk supersedes a retained copy
+            self.retention.discard(pos);
+            self.events.notify(ChunkEvent::Generated, pos, &chunk);
+            if let Some(render_cache) = &mut self.render_cache {
+                let upload_start = Instant::now();
+                render_cache.insert(&chunk, &geometry, self.store.get_render_pos(pos))?;
+                self.chunk_loader
+                    .add_stage_time(BuildStage::Upload, upload_start.elapsed())?;
+            }
+            self.store.insert(chunk);
+            if let Some(chunk) = self.store.get_chunk(pos) {
+                self.events.notify(ChunkEvent::Loaded, pos, chunk);
+            }
         }
         Ok(())
     }
 
+    // Moves a retained chunk back into the store and the render cache
+    fn restore_chunk(&mut self, pos: Point2i) -> Result<bool, ChunkError> {
+        let edit_revision = self
+            .edit_store
+            .lock()
+            .map(|store| store.get_delta(pos).map_or(0, |d| d.get_revision()))
+            .or(Err(ChunkError::MutexPoison))?;
+        let (chunk, render_chunk) = match self.retention.take(pos, edit_revision) {
+            Some(retained) => retained,
+            None => return Ok(false),
+        };
+        if let (Some(render_cache), Some(render_chunk)) = (&mut self.render_cache, render_chunk) {
+            render_cache.restore(pos, render_chunk, self.store.get_render_pos(pos));
+        }
+        self.store.insert(chunk);
+        if let Some(chunk) = self.store.get_chunk(pos) {
+            self.events.notify(ChunkEvent::Loaded, pos, chunk);
+        }
+        Ok(true)
+    }
+
+    /// Chunks beyond the unload radius are retained until the memory budget runs out
     fn unload_distant_chunks(&mut self, center: Point2i) -> Result<(), ChunkError> {
         let remove_list: Vec<Point2i> = self
-            .chunk_map
+            .store
+            .get_chunk_map()
             .keys()
-            .filter_map(|k| {
-                if (*k - center).length() > self.lod_distances[2] as f32 {
-                    Some(k.clone())
-                } else {
-                    None
-                }
-            })
+            .filter(|k| (**k - center).length() > self.unload_radius as f32)
+            .cloned()
             .collect();
-        if remove_list.len() > 0 {
+        if !remove_list.is_empty() {
             trace!("Unloading {} chunks", remove_list.len());
         }
-        remove_list.into_iter().for_each(|k| {
-            self.chunk_map.remove(&k);
-        });
-        Ok(())
+        for pos in remove_list {
+            if let Some(chunk) = self.unload_chunk(pos) {
+                let render_chunk = self
+                    .render_cache
+                    .as_mut()
+                    .and_then(|render_cache| render_cache.remove(pos));
+                self.retention.insert(chunk, render_chunk, self.time);
+            }
+        }
+        self.enforce_memory_budget(center)
+    }
+
+    fn unload_chunk(&mut self, pos: Point2i) -> Option<Chunk> {
+        if let Some(chunk) = self.store.get_chunk(pos) {
+            self.events.notify(ChunkEvent::Unloading, pos, chunk);
+        }
+        self.store.remove(pos)
+    }
+
+    /// Evicts retained chunks, by distance and time since unloading, to fit the loaded ones
+    /// into the budget. If the loaded chunks alone exceed it, those beyond the load radius
+    /// are dropped, farthest first.
+    fn enforce_memory_budget(&mut self, center: Point2i) -> Result<(), ChunkError> {
+        let mut cpu_size = self.store.get_memory_size();
+        let mut gpu_size = self.render_cache.as_ref().map_or(0, |rc| rc.get_gpu_size());
+        let evicted = self.retention.evict(
+            Point2f::from(center),
+            self.time,
+            self.memory_budget.saturating_sub(cpu_size + gpu_size),
+        );
+        if !evicted.is_empty() {
+            trace!("Evicted {} retained chunks", evicted.len());
+        }
+
+        if cpu_size + gpu_size > self.memory_budget {
+            let mut drop_list: Vec<(f32, Point2i)> = self
+                .store
+                .get_chunk_map()
+                .keys()
+                .map(|k| ((*k - center).length(), *k))
+                .filter(|(distance, _)| *distance >= self.lod_distances[2] as f32)
+                .collect();
+            drop_list.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+            for (_, pos) in drop_list {
+                if cpu_size + gpu_size <= self.memory_budget {
+                    break;
+                }
+                if let Some(chunk) = self.unload_chunk(pos) {
+                    cpu_size -= chunk.get_memory_size();
+                }
+                if let Some(render_cache) = &mut self.render_cache {
+                    gpu_size -= render_cache.remove(pos).map_or(0, |rc| rc.get_gpu_size());
+                }
+            }
+        }
+
+        self.chunk_loader.set_memory_usage(
+            MemoryUsage::new(cpu_size, gpu_size, self.memory_budget).with_retained(
+                self.retention.get_cpu_size(),
+                self.retention.get_gpu_size(),
+                self.retention.get_count(),
+            ),
+        )
+    }
+}
+
+impl HeightSampler for ChunkManager {
+    fn get_grid_height(&self, grid_pos: Point2i) -> f32 {
+        self.store.get_grid_height(grid_pos)
+    }
+
+    fn get_grid_spacing(&self) -> f32 {
+        self.store.get_grid_spacing()
+    }
+
+    fn has_grid_height(&self, grid_pos: Point2i) -> bool {
+        self.store.has_grid_height(grid_pos)
     }
 }
 
 impl Updatable for ChunkManager {
     fn tick(&mut self, time_passed: u32) -> Result<(), UpdateError> {
         if self.build_stats_timer.fires() {
+            let metrics = self
+                .get_build_metrics()
+                .map_err(|e| UpdateError::Internal(e.to_string()))?;
             info!(
-                "Active chunks: {}, avg chunk build time: {:.2}ms",
-                self.chunk_map.len(),
-                self.chunk_loader.get_avg_build_time()
+                "Active chunks: {}, retained chunks: {}, queued chunks: {}, chunk build time: {:.2}ms avg, {:.2}ms p90, {:.1} chunks/s, memory: {} of {} MB",
+                self.store.get_chunk_count(),
+                metrics.get_memory_usage().get_retained_count(),
+                metrics.get_queue_depth(),
+                metrics.get_total().get_mean(),
+                metrics.get_total().get_p90(),
+                metrics.get_chunks_per_second(),
+                metrics.get_memory_usage().get_total() / (1024 * 1024),
+                self.memory_budget / (1024 * 1024)
             );
         }
 
@@ -147,59 +495,86 @@ impl Updatable for ChunkManager {
                 .map_err(|e| UpdateError::Internal(e.to_string()))?;
         }
 
+        if self.edit_save_timer.fires() {
+            if let Err(e) = self.save_edits() {
+                error!("Could not save terrain edits: {}", e);
+            }
+        }
+
+        self.time += time_passed as u64;
         self.build_stats_timer.tick(time_passed)?;
         self.chunk_retrieval_timer.tick(time_passed)?;
+        self.edit_save_timer.tick(time_passed)?;
         Ok(())
     }
 }
 
+impl Drop for ChunkManager {
+    fn drop(&mut self) {
+        if let Err(e) = self.save_edits() {
+            error!("Could not save terrain edits: {}", e);
+        }
+    }
+}
+
 impl Renderable for ChunkManager {
     fn render<'a>(&self, info: &'a mut RenderInfo) -> Result<(), GraphicsError> {
-        info.push_shader(self.shader.clone());
+        match &self.render_cache {
+            Some(render_cache) => render_cache.render(info),
+            None => Ok(()),
+        }
+    }
+}
 
-        for chunk in self.chunk_map.values() {
-            if chunk.prepare_rendering(info)? {
-                self.mesh[0].render(info)?;
-            }
+// Chunks are regenerated every time without a cache
+fn create_chunk_cache(config: &Config, architect: &Architect) -> Option<ChunkCache> {
+    if !config.get_bool_or_default("chunk_cache", true) {
+        info!("Chunk cache is disabled");
+        return None;
+    }
+    let directory = config.get_str_or_default("chunk_cache_directory", "cache");
+    match ChunkCache::new(
+        Path::new(&directory),
+        architect.get_seed(),
+        GENERATOR_VERSION,
+        architect.get_layout().get_grid_size(),
+        architect.get_terrain_hash(),
+    ) {
+        Ok(cache) => {
+            info!("Caching chunks in '{}'", cache.get_directory().display());
+            Some(cache)
+        }
+        Err(e) => {
+            warn!("Could not create chunk cache in '{}': {}", directory, e);
+            None
         }
+    }
+}
 
-        info.pop_shader();
-        Ok(())
+// Defaults to the available parallelism
+fn get_thread_count(config: &Config) -> usize {
+    match config.get_uint_or_default("chunk_threads", 0) {
+        0 => thread::available_parallelism()
+            .map(|count| count.get())
+            .unwrap_or(4),
+        count => count as usize,
     }
 }
 
-fn load_surface_shader(directory: &str) -> Result<ShaderProgram, GraphicsError> {
-    let surface_shader_program = ShaderProgramBuilder::new()
-        .add_vertex_shader((directory.to_owned() + "/VertexShader.glsl").as_str())
-        .add_fragment_shader((directory.to_owned() + "/FragmentShader.glsl").as_str())
-        .add_resource("mvp")
-        .add_resource("model")
-        .add_resource("chunk_size")
-        .add_resource("view_pos")
-        .add_resource("fog_color")
-        .add_resource("active_lights")
-        .add_resource("scene_lights[0].color")
-        .add_resource("scene_lights[0].world_pos")
-        .add_resource("scene_lights[0].absolute_intensity")
-        .add_resource("scene_lights[0].ambient_intensity")
-        .add_resource("scene_lights[0].diffuse_intensity")
-        .add_resource("scene_lights[0].specular_intensity")
-        .add_resource("scene_lights[0].specular_shininess")
-        .add_resource("scene_lights[1].color")
-        .add_resource("scene_lights[1].world_pos")
-        .add_resource("scene_lights[1].absolute_intensity")
-        .add_resource("scene_lights[1].ambient_intensity")
-        .add_resource("scene_lights[1].diffuse_intensity")
-        .add_resource("scene_lights[1].specular_intensity")
-        .add_resource("scene_lights[1].specular_shininess")
-        .finish()?;
-    Ok(surface_shader_program)
+// Beyond the load radius, so chunks at the boundary aren't reloaded when moving back and forth
+fn get_unload_radius(config: &Config, load_radius: i32) -> i32 {
+    let default = load_radius + i32::max(2, load_radius / 8);
+    i32::max(
+        config.get_int_or_default("unload_radius", default),
+        load_radius,
+    )
 }
 
+// The far radius has to stay below the active radius, otherwise the coarsest level is never shown
 fn get_lod_distances(config: &Config) -> [i32; 3] {
     let active_radius = config.get_int_or_default("active_radius", 40);
     let far_radius = i32::min(
-        config.get_int_or_default("far_radius", 3 * active_radius / 2),
+        config.get_int_or_default("far_radius", 2 * active_radius / 3),
         active_radius,
     );
     let near_radius = i32::min(
@@ -208,3 +583,20 @@ fn get_lod_distances(config: &Config) -> [i32; 3] {
     );
     [near_radius, far_radius, active_radius]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::render_cache::select_lod;
+    use super::*;
+
+    #[test]
+    fn test_default_lod_distances() {
+        let lod_distances = get_lod_distances(&Config::default());
+        assert!(lod_distances[0] < lod_distances[1]);
+        assert!(lod_distances[1] < lod_distances[2]);
+        // chunks at the edge of the active radius are rendered at the coarsest level
+        let distance = lod_distances[2] as f32 - 0.5;
+        assert_eq!(2, select_lod(0, distance, &lod_distances));
+        assert_eq!(2, select_lod(2, distance, &lod_distances));
+    }
+}
@@ -0,0 +1,194 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, BinaryHeap};
+
+use core::{Point2f, Point2i};
+
+// Chunks straight ahead are treated as if they were closer by this share of their distance
+const VIEW_WEIGHT: f32 = 0.5;
+
+#[derive(PartialEq)]
+struct Request {
+    priority: f32,
+    pos: Point2i,
+}
+
+impl Eq for Request {}
+
+// Lowest priority value first, the heap pops the greatest element
+impl Ord for Request {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .priority
+            .partial_cmp(&self.priority)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.pos.cmp(&other.pos))
+    }
+}
+
+impl PartialOrd for Request {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Chunk positions waiting to be built, nearest to the center and in view direction first.
+/// Positions taken for building stay known until they're finished, so they aren't requested twice.
+pub struct ChunkQueue {
+    center: Point2f,
+    view_direction: Point2f,
+    heap: BinaryHeap<Request>,
+    pending: BTreeSet<Point2i>,
+    in_progress: BTreeSet<Point2i>,
+}
+
+impl ChunkQueue {
+    pub fn new() -> Self {
+        Self {
+            center: Point2f::from_scalar(0.),
+            view_direction: Point2f::from_scalar(0.),
+            heap: BinaryHeap::new(),
+            pending: BTreeSet::new(),
+            in_progress: BTreeSet::new(),
+        }
+    }
+
+    /// Replaces all pending positions, positions missing from the list are cancelled.
+    /// Center and view direction are given in chunks, only the nearest positions up to the
+    /// maximum count are kept.
+    pub fn request(
+        &mut self,
+        center: Point2f,
+        view_direction: Point2f,
+        positions: &[Point2i],
+        max_count: usize,
+    ) {
+        self.center = center;
+        self.view_direction = if view_direction.length() > 0. {
+            view_direction.as_normalized()
+        } else {
+            view_direction
+        };
+        let mut requests: Vec<Request> = positions
+            .iter()
+            .filter(|pos| !self.in_progress.contains(pos))
+            .map(|pos| Request {
+                priority: self.get_priority(*pos),
+                pos: *pos,
+            })
+            .collect();
+        requests.sort_by(|a, b| b.cmp(a));
+        requests.dedup_by(|a, b| a.pos == b.pos);
+        requests.truncate(max_count);
+
+        let cancelled = self
+            .pending
+            .iter()
+            .filter(|pos| !requests.iter().any(|r| r.pos == **pos))
+            .count();
+        if cancelled > 0 {
+            trace!("Cancelled {} chunk requests", cancelled);
+        }
+        self.pending = requests.iter().map(|r| r.pos).collect();
+        self.heap = requests.into_iter().collect();
+    }
+
+    /// Highest priority position, marked as in progress until it's finished
+    pub fn pop(&mut self) -> Option<Point2i> {
+        let request = self.heap.pop()?;
+        self.pending.remove(&request.pos);
+        self.in_progress.insert(request.pos);
+        Some(request.pos)
+    }
+
+    pub fn finish(&mut self, pos: Point2i) {
+        self.in_progress.remove(&pos);
+    }
+
+    /// Positions waiting to be built
+    pub fn get_depth(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn get_priority(&self, pos: Point2i) -> f32 {
+        let offset = Point2f::from(pos) + Point2f::from_scalar(0.5) - self.center;
+        let distance = offset.length();
+        if distance > 0. {
+            let alignment = offset.dot(&self.view_direction) / distance;
+            distance * (1. - VIEW_WEIGHT * alignment.max(0.))
+        } else {
+            distance
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_in_view_first() {
+        let mut queue = ChunkQueue::new();
+        let positions = [
+            Point2i::new(5, 0),
+            Point2i::new(-3, 0),
+            Point2i::new(2, 0),
+            Point2i::new(0, 0),
+        ];
+        queue.request(
+            Point2f::from_scalar(0.5),
+            Point2f::new(1., 0.),
+            &positions,
+            10,
+        );
+        assert_eq!(4, queue.get_depth());
+        assert_eq!(Some(Point2i::new(0, 0)), queue.pop());
+        assert_eq!(Some(Point2i::new(2, 0)), queue.pop());
+        // 5 ahead counts as 2.5, behind counts in full
+        assert_eq!(Some(Point2i::new(5, 0)), queue.pop());
+        assert_eq!(Some(Point2i::new(-3, 0)), queue.pop());
+        assert_eq!(None, queue.pop());
+        // all are in progress
+        queue.request(
+            Point2f::from_scalar(0.5),
+            Point2f::new(1., 0.),
+            &positions,
+            10,
+        );
+        assert_eq!(0, queue.get_depth());
+    }
+
+    #[test]
+    fn test_request_replaces_pending() {
+        let mut queue = ChunkQueue::new();
+        let positions = [Point2i::new(1, 0), Point2i::new(8, 0), Point2i::new(9, 0)];
+        queue.request(
+            Point2f::from_scalar(0.),
+            Point2f::from_scalar(0.),
+            &positions,
+            10,
+        );
+        assert_eq!(Some(Point2i::new(1, 0)), queue.pop());
+
+        // moved towards the far chunks, the near one is in progress and not requested again
+        let positions = [Point2i::new(1, 0), Point2i::new(9, 0), Point2i::new(10, 0)];
+        queue.request(
+            Point2f::new(10., 0.),
+            Point2f::from_scalar(0.),
+            &positions,
+            2,
+        );
+        assert_eq!(2, queue.get_depth());
+        assert_eq!(Some(Point2i::new(10, 0)), queue.pop());
+        assert_eq!(Some(Point2i::new(9, 0)), queue.pop());
+        assert_eq!(None, queue.pop());
+
+        queue.finish(Point2i::new(1, 0));
+        queue.request(
+            Point2f::new(10., 0.),
+            Point2f::from_scalar(0.),
+            &positions,
+            10,
+        );
+        assert_eq!(1, queue.get_depth());
+    }
+}
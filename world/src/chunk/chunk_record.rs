@@ -0,0 +1,100 @@
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use std::io::{Error, ErrorKind, Read, Write};
+
+use super::{BuildStage, BuildTimings};
+use crate::analysis::AnalysisMap;
+use crate::architect::Architect;
+use crate::{HeightMap, WaterMap};
+use core::traits::{Loadable, Saveable};
+use core::Point2i;
+
+/// Procedural part of a chunk, before edits are applied.
+/// Everything else is derived from it when the chunk is built.
+pub struct ChunkRecord {
+    heightmap: HeightMap,
+    road_mask: Option<AnalysisMap>,
+    water: WaterMap,
+}
+
+impl ChunkRecord {
+    pub fn new(heightmap: HeightMap, road_mask: Option<AnalysisMap>, water: WaterMap) -> Self {
+        Self {
+            heightmap: heightmap,
+            road_mask: road_mask,
+            water: water,
+        }
+    }
+
+    pub fn generate(chunk_pos: Point2i, architect: &Architect, timings: &mut BuildTimings) -> Self {
+        let mut heightmap =
+            timings.measure(BuildStage::Noise, || architect.create_heightmap(chunk_pos));
+        let road_mask =
+            timings.measure(BuildStage::Roads, || architect.apply_roads(&mut heightmap));
+        if road_mask.is_some() {
+            timings.measure(BuildStage::Normals, || {
+                architect.update_normals(&mut heightmap)
+            });
+        }
+        let water = timings.measure(BuildStage::Water, || {
+            architect.create_water_map(chunk_pos, &heightmap)
+        });
+        Self::new(heightmap, road_mask, water)
+    }
+
+    /// Grid points of the maps along each axis
+    pub fn get_grid_size(&self) -> i32 {
+        self.heightmap.get_size()
+    }
+
+    pub fn into_parts(self) -> (HeightMap, Option<AnalysisMap>, WaterMap) {
+        (self.heightmap, self.road_mask, self.water)
+    }
+}
+
+impl Default for ChunkRecord {
+    fn default() -> Self {
+        Self::new(HeightMap::new(1, 1.), None, WaterMap::new(1, 1.))
+    }
+}
+
+impl Saveable for ChunkRecord {
+    fn save(&self, writer: &mut impl Write) -> Result<(), Error> {
+        self.heightmap.save(writer)?;
+        match &self.road_mask {
+            Some(road_mask) => {
+                writer.write_u8(1)?;
+                road_mask.save(writer)?;
+            }
+            None => writer.write_u8(0)?,
+        }
+        self.water.save(writer)
+    }
+}
+
+impl Loadable for ChunkRecord {
+    fn load(&mut self, reader: &mut impl Read) -> Result<(), Error> {
+        let mut heightmap = HeightMap::new(1, 1.);
+        heightmap.load(reader)?;
+        let road_mask = if reader.read_u8()? != 0 {
+            let mut road_mask = AnalysisMap::new(1, 1.);
+            road_mask.load(reader)?;
+            Some(road_mask)
+        } else {
+            None
+        };
+        let mut water = WaterMap::new(1, 1.);
+        water.load(reader)?;
+        let size = heightmap.get_size();
+        let road_size = road_mask.as_ref().map_or(size, |road_mask| road_mask.get_size());
+        if road_size != size || water.get_size() != size {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("map sizes don't match the heightmap size {}", size),
+            ));
+        }
+        self.heightmap = heightmap;
+        self.road_mask = road_mask;
+        self.water = water;
+        Ok(())
+    }
+}
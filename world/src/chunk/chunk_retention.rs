@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use super::{Chunk, RenderChunk};
+use core::{Point2f, Point2i};
+
+// Unloaded this long ago, a chunk is as likely to be evicted as one a chunk further away
+const RECENCY_MS_PER_CHUNK: f32 = 10000.;
+
+struct RetainedChunk {
+    chunk: Chunk,
+    render_chunk: Option<RenderChunk>,
+    unloaded_at: u64,
+    cpu_size: usize,
+    gpu_size: usize,
+}
+
+/// Recently unloaded chunks along with their GPU resources, so chunks coming back
+/// into range are restored instead of rebuilt
+pub struct ChunkRetention {
+    chunk_map: BTreeMap<Point2i, RetainedChunk>,
+    cpu_size: usize,
+    gpu_size: usize,
+}
+
+impl ChunkRetention {
+    pub fn new() -> Self {
+        Self {
+            chunk_map: BTreeMap::new(),
+            cpu_size: 0,
+            gpu_size: 0,
+        }
+    }
+
+    /// Time in ms of the unloading, used for the eviction order
+    pub fn insert(&mut self, chunk: Chunk, render_chunk: Option<RenderChunk>, time: u64) {
+        let cpu_size = chunk.get_memory_size();
+        let gpu_size = render_chunk.as_ref().map_or(0, |c| c.get_gpu_size());
+        self.cpu_size += cpu_size;
+        self.gpu_size += gpu_size;
+        let pos = chunk.get_pos();
+        let retained = RetainedChunk {
+            chunk: chunk,
+            render_chunk: render_chunk,
+            unloaded_at: time,
+            cpu_size: cpu_size,
+            gpu_size: gpu_size,
+        };
+        if let Some(replaced) = self.chunk_map.insert(pos, retained) {
+            self.release(&replaced);
+        }
+    }
+
+    /// A chunk retained with another edit revision is dropped, as its edges may have been
+    /// edited while it was unloaded
+    pub fn take(
+        &mut self,
+        chunk_pos: Point2i,
+        edit_revision: u32,
+    ) -> Option<(Chunk, Option<RenderChunk>)> {
+        let retained = self.remove(chunk_pos)?;
+        if retained.chunk.get_edit_revision() != edit_revision {
+            trace!("Dropping retained chunk {} with outdated edits", chunk_pos);
+            return None;
+        }
+        Some((retained.chunk, retained.render_chunk))
+    }
+
+    /// Drops the chunk, if retained
+    pub fn discard(&mut self, chunk_pos: Point2i) {
+        self.remove(chunk_pos);
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.chunk_map.len()
+    }
+
+    pub fn get_cpu_size(&self) -> usize {
+        self.cpu_size
+    }
+
+    pub fn get_gpu_size(&self) -> usize {
+        self.gpu_size
+    }
+
+    /// Evicts chunks until the retained bytes fit into the budget. Chunks far from the
+    /// center and unloaded long ago go first. Returns the evicted positions.
+    pub fn evict(&mut self, center: Point2f, time: u64, budget: usize) -> Vec<Point2i> {
+        let mut evicted = Vec::new();
+        if self.cpu_size + self.gpu_size <= budget {
+            return evicted;
+        }
+        let mut candidates: Vec<(f32, Point2i)> = self
+            .chunk_map
+            .iter()
+            .map(|(pos, retained)| {
+                let distance = (Point2f::from(*pos) - center).length();
+                let age = time.saturating_sub(retained.unloaded_at) as f32;
+                (distance + age / RECENCY_MS_PER_CHUNK, *pos)
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        for (_, pos) in candidates {
+            if self.cpu_size + self.gpu_size <= budget {
+                break;
+            }
+            self.remove(pos);
+            evicted.push(pos);
+        }
+        evicted
+    }
+
+    fn remove(&mut self, chunk_pos: Point2i) -> Option<RetainedChunk> {
+        let retained = self.chunk_map.remove(&chunk_pos)?;
+        self.release(&retained);
+        Some(retained)
+    }
+
+    fn release(&mut self, retained: &RetainedChunk) {
+        self.cpu_size -= retained.cpu_size;
+        self.gpu_size -= retained.gpu_size;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HeightMap, WaterMap};
+
+    fn create_chunk(x: i32, y: i32) -> Chunk {
+        Chunk::new(
+            Point2i::new(x, y),
+            HeightMap::new(9, 1.),
+            None,
+            None,
+            WaterMap::new(9, 1.),
+        )
+    }
+
+    #[test]
+    fn test_evict_by_distance_and_recency() {
+        let mut retention = ChunkRetention::new();
+        retention.insert(create_chunk(10, 0), None, 0);
+        retention.insert(create_chunk(3, 0), None, 0);
+        retention.insert(create_chunk(4, 0), None, 60000);
+        let chunk_size = retention.get_cpu_size() / 3;
+
+        let evicted = retention.evict(Point2f::from_scalar(0.), 60000, 2 * chunk_size);
+        assert_eq!(evicted, vec![Point2i::new(10, 0)]);
+        // unloaded a minute earlier, the closer chunk is evicted first
+        let evicted = retention.evict(Point2f::from_scalar(0.), 60000, chunk_size);
+        assert_eq!(evicted, vec![Point2i::new(3, 0)]);
+        assert_eq!(retention.get_cpu_size(), chunk_size);
+        assert!(retention.take(Point2i::new(4, 0), 0).is_some());
+    }
+
+    #[test]
+    fn test_take_releases_memory() {
+        let mut retention = ChunkRetention::new();
+        retention.insert(create_chunk(1, 2), None, 0);
+        retention.insert(create_chunk(1, 2), None, 10);
+        assert_eq!(retention.get_count(), 1);
+        assert!(retention.take(Point2i::new(1, 2), 0).is_some());
+        assert!(retention.take(Point2i::new(1, 2), 0).is_none());
+        assert_eq!(retention.get_cpu_size(), 0);
+        assert_eq!(retention.get_gpu_size(), 0);
+    }
+
+    #[test]
+    fn test_drop_outdated_edits() {
+        let mut retention = ChunkRetention::new();
+        retention.insert(create_chunk(0, 0).with_edit_revision(2), None, 0);
+        assert!(retention.take(Point2i::new(0, 0), 3).is_none());
+        assert_eq!(retention.get_count(), 0);
+        assert_eq!(retention.get_cpu_size(), 0);
+    }
+}
@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+
+use super::{Chunk, WorldLayout};
+use crate::analysis::AnalysisLayer;
+use crate::HeightSampler;
+use core::Point2i;
+
+// Samples an analysis layer across chunk borders, positions are relative to the render origin.
+// Unloaded chunks and chunks without analysis yield zero.
+pub struct LayerSampler<'a> {
+    chunk_map: &'a BTreeMap<Point2i, Chunk>,
+    layout: WorldLayout,
+    origin: Point2i,
+    layer: AnalysisLayer,
+}
+
+impl<'a> LayerSampler<'a> {
+    pub fn new(
+        chunk_map: &'a BTreeMap<Point2i, Chunk>,
+        layout: WorldLayout,
+        origin: Point2i,
+        layer: AnalysisLayer,
+    ) -> Self {
+        Self {
+            chunk_map: chunk_map,
+            layout: layout,
+            origin: origin,
+            layer: layer,
+        }
+    }
+}
+
+impl<'a> HeightSampler for LayerSampler<'a> {
+    fn get_grid_height(&self, grid_pos: Point2i) -> f32 {
+        let (chunk_pos, local_pos) = self.layout.split_grid_pos(grid_pos);
+        match self
+            .chunk_map
+            .get(&(chunk_pos + self.origin))
+            .and_then(|c| c.get_analysis())
+        {
+            Some(analysis) => analysis.get_layer(self.layer).get(local_pos),
+            None => 0.,
+        }
+    }
+
+    fn get_grid_spacing(&self) -> f32 {
+        self.layout.get_grid_spacing()
+    }
+}
@@ -1,18 +1,46 @@
+mod build_options;
+pub mod build_metrics;
 mod build_stats;
 pub mod chunk;
 mod chunk_builder;
+mod chunk_cache;
 pub mod chunk_error;
+mod chunk_event;
+mod chunk_geometry;
 pub mod chunk_loader;
 pub mod chunk_manager;
-pub mod chunk_size;
+mod chunk_queue;
+mod chunk_record;
+mod chunk_retention;
+mod layer_sampler;
+mod region_file;
+mod render_cache;
+mod render_chunk;
+pub mod terrain_store;
 mod worker;
+pub mod world_layout;
+pub mod world_pos;
 
-use self::build_stats::BuildStats;
+use self::build_options::BuildOptions;
+pub use self::build_metrics::{BuildMetrics, MemoryUsage, StageMetrics};
+pub use self::build_stats::BuildStage;
+use self::build_stats::{BuildStats, BuildTimings};
 pub use self::chunk::Chunk;
 use self::chunk_builder::ChunkBuilder;
+use self::chunk_cache::ChunkCache;
 pub use self::chunk_error::ChunkError;
+pub use self::chunk_event::{ChunkEvent, ChunkListener};
+use self::chunk_event::ChunkEvents;
+use self::chunk_geometry::ChunkGeometry;
 pub use self::chunk_loader::ChunkLoader;
 pub use self::chunk_manager::ChunkManager;
-#[allow(unused)]
-pub use self::chunk_size::{get_chunk_pos, get_relative_pos, get_world_pos, CHUNK_SIZE};
+use self::chunk_queue::ChunkQueue;
+use self::chunk_record::ChunkRecord;
+use self::chunk_retention::ChunkRetention;
+pub use self::layer_sampler::LayerSampler;
+use self::render_cache::RenderCache;
+use self::render_chunk::RenderChunk;
+pub use self::terrain_store::TerrainStore;
 use self::worker::Worker;
+pub use self::world_layout::WorldLayout;
+pub use self::world_pos::WorldPos;
@@ -0,0 +1,158 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use core::{Point2i, Seed};
+
+const REGION_FILE_MAGIC: &[u8; 4] = b"WGRC";
+const REGION_FILE_VERSION: u32 = 2;
+// magic, version, seed, generator version, grid size, terrain hash
+const HEADER_SIZE: u64 = 4 + 4 + 32 + 4 + 4 + 8;
+// chunk pos and record size
+const RECORD_HEADER_SIZE: u64 = 4 + 4 + 4;
+
+/*
+    Region file layout, all values little endian:
+    magic "WGRC", version u32, world seed [u8; 32], generator version u32, grid size i32,
+    terrain hash u64, then records in the order they were written: chunk pos i32 i32, record size u32, record bytes.
+    A later record of the same chunk replaces the earlier one.
+*/
+
+/// Identifies the world and generator the cached chunks were built with
+#[derive(Clone, Copy)]
+pub struct RegionHeader {
+    pub seed: Seed,
+    pub generator_version: u32,
+    pub grid_size: i32,
+    pub terrain_hash: u64,
+}
+
+impl RegionHeader {
+    fn write(&self, writer: &mut impl Write) -> Result<(), Error> {
+        writer.write_all(REGION_FILE_MAGIC)?;
+        writer.write_u32::<LittleEndian>(REGION_FILE_VERSION)?;
+        writer.write_all(self.seed.as_bytes())?;
+        writer.write_u32::<LittleEndian>(self.generator_version)?;
+        writer.write_i32::<LittleEndian>(self.grid_size)?;
+        writer.write_u64::<LittleEndian>(self.terrain_hash)
+    }
+
+    fn matches(&self, reader: &mut impl Read) -> Result<bool, Error> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        let version = reader.read_u32::<LittleEndian>()?;
+        let mut seed = [0u8; 32];
+        reader.read_exact(&mut seed)?;
+        let generator_version = reader.read_u32::<LittleEndian>()?;
+        let grid_size = reader.read_i32::<LittleEndian>()?;
+        let terrain_hash = reader.read_u64::<LittleEndian>()?;
+        Ok(&magic == REGION_FILE_MAGIC
+            && version == REGION_FILE_VERSION
+            && &seed == self.seed.as_bytes()
+            && generator_version == self.generator_version
+            && grid_size == self.grid_size
+            && terrain_hash == self.terrain_hash)
+    }
+}
+
+/// Append-only file of the chunk records of one region
+pub struct RegionFile {
+    file: File,
+    // offset and size of the latest record per chunk
+    index: BTreeMap<Point2i, (u64, u32)>,
+    end: u64,
+}
+
+impl RegionFile {
+    /// Files of another world, generator version or with an unknown layout are cleared
+    pub fn open(path: &Path, header: &RegionHeader) -> Result<Self, Error> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)?;
+        let length = file.metadata()?.len();
+        let valid = length >= HEADER_SIZE && header.matches(&mut file)?;
+        if !valid {
+            if length > 0 {
+                warn!("Clearing outdated chunk cache file '{}'", path.display());
+            }
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            header.write(&mut file)?;
+            return Ok(Self {
+                file: file,
+                index: BTreeMap::new(),
+                end: HEADER_SIZE,
+            });
+        }
+
+        let mut index = BTreeMap::new();
+        let mut end = HEADER_SIZE;
+        {
+            let mut reader = BufReader::new(&mut file);
+            while end + RECORD_HEADER_SIZE <= length {
+                let x = reader.read_i32::<LittleEndian>()?;
+                let y = reader.read_i32::<LittleEndian>()?;
+                let size = reader.read_u32::<LittleEndian>()?;
+                let offset = end + RECORD_HEADER_SIZE;
+                if offset + size as u64 > length {
+                    break;
+                }
+                reader.seek_relative(size as i64)?;
+                index.insert(Point2i::new(x, y), (offset, size));
+                end = offset + size as u64;
+            }
+        }
+        if end < length {
+            // the last write was interrupted
+            warn!("Truncating incomplete record of '{}'", path.display());
+            file.set_len(end)?;
+        }
+        Ok(Self {
+            file: file,
+            index: index,
+            end: end,
+        })
+    }
+
+    pub fn get_chunk_count(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn read(&mut self, chunk_pos: Point2i) -> Result<Option<Vec<u8>>, Error> {
+        match self.index.get(&chunk_pos) {
+            Some((offset, size)) => {
+                let mut data = vec![0u8; *size as usize];
+                self.file.seek(SeekFrom::Start(*offset))?;
+                self.file.read_exact(&mut data)?;
+                Ok(Some(data))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn append(&mut self, chunk_pos: Point2i, data: &[u8]) -> Result<(), Error> {
+        if data.len() > u32::MAX as usize {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "chunk record too large",
+            ));
+        }
+        let mut buffer = Vec::with_capacity(RECORD_HEADER_SIZE as usize + data.len());
+        buffer.write_i32::<LittleEndian>(chunk_pos[0])?;
+        buffer.write_i32::<LittleEndian>(chunk_pos[1])?;
+        buffer.write_u32::<LittleEndian>(data.len() as u32)?;
+        buffer.extend_from_slice(data);
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(&buffer)?;
+        self.index.insert(
+            chunk_pos,
+            (self.end + RECORD_HEADER_SIZE, data.len() as u32),
+        );
+        self.end += buffer.len() as u64;
+        Ok(())
+    }
+}
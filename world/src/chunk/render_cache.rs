@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use super::chunk_geometry::LOD_COUNT;
+use super::{Chunk, ChunkError, ChunkGeometry, RenderChunk, WorldLayout};
+use crate::SurfaceInfo;
+use core::light::SceneLights;
+use core::{
+    Config, GraphicsError, Point2f, Point2i, Point3f, RenderInfo, Renderable, ShaderProgram,
+    ShaderProgramBuilder, Texture,
+};
+
+// Chunks switch their level of detail this many chunks beyond the lod distance,
+// so they don't flicker between two levels at the boundary
+const LOD_HYSTERESIS: f32 = 1.;
+
+/// Mirrors the chunks of the terrain store into meshes and textures for rendering
+pub struct RenderCache {
+    shader: Rc<ShaderProgram>,
+    surface_texture: Texture,
+    chunk_map: BTreeMap<Point2i, RenderChunk>,
+    lod_distances: [i32; 3],
+    lod_center: Point2f,
+}
+
+impl RenderCache {
+    /// Chunks beyond the first two lod distances are rendered at coarser levels
+    pub fn new(
+        config: &Config,
+        layout: WorldLayout,
+        surface_info: &SurfaceInfo,
+        lod_distances: [i32; 3],
+    ) -> Result<Self, ChunkError> {
+        let surface_texture = surface_info.create_texture_array()?;
+        let surface_shader_dir = config.get_str("surface_shader_dir")?;
+        let surface_shader_program = load_surface_shader(surface_shader_dir)?;
+        surface_shader_program
+            .set_resource_integer("grid_size", layout.get_grid_size())
+            .map_err(GraphicsError::from)?;
+        surface_shader_program
+            .set_resource_float("grid_spacing", layout.get_grid_spacing())
+            .map_err(GraphicsError::from)?;
+        surface_shader_program
+            .set_resource_integer("analysis_map", 1)
+            .map_err(GraphicsError::from)?;
+        surface_shader_program
+            .set_resource_integer("road_mask", 2)
+            .map_err(GraphicsError::from)?;
+        surface_shader_program
+            .set_resource_integer("splat_map", 3)
+            .map_err(GraphicsError::from)?;
+        surface_shader_program
+            .set_resource_integer("texture_array", 4)
+            .map_err(GraphicsError::from)?;
+
+        Ok(Self {
+            shader: Rc::new(surface_shader_program),
+            surface_texture: surface_texture,
+            chunk_map: BTreeMap::new(),
+            lod_distances: lod_distances,
+            lod_center: Point2f::from_scalar(0.),
+        })
+    }
+
+    /// Uploads the chunk, replacing an earlier one at the same position
+    pub fn insert(
+        &mut self,
+        chunk: &Chunk,
+        geometry: &ChunkGeometry,
+        render_pos: Point2f,
+    ) -> Result<(), ChunkError> {
+        let mut render_chunk = RenderChunk::new(chunk, geometry, render_pos)?;
+        let distance = get_lod_distance(chunk.get_pos(), self.lod_center);
+        render_chunk.set_lod(select_lod(0, distance, &self.lod_distances));
+        self.chunk_map.insert(chunk.get_pos(), render_chunk);
+        Ok(())
+    }
+
+    /// The GPU resources are kept as long as the returned chunk lives
+    pub fn remove(&mut self, chunk_pos: Point2i) -> Option<RenderChunk> {
+        self.chunk_map.remove(&chunk_pos)
+    }
+
+    /// Inserts a chunk removed earlier, the render origin may have moved in between
+    pub fn restore(
+        &mut self,
+        chunk_pos: Point2i,
+        mut render_chunk: RenderChunk,
+        render_pos: Point2f,
+    ) {
+        render_chunk.set_render_pos(render_pos);
+        let distance = get_lod_distance(chunk_pos, self.lod_center);
+        render_chunk.set_lod(select_lod(
+            render_chunk.get_lod(),
+            distance,
+            &self.lod_distances,
+        ));
+        self.chunk_map.insert(chunk_pos, render_chunk);
+    }
+
+    /// Estimated bytes of all meshes and textures
+    pub fn get_gpu_size(&self) -> usize {
+        self.chunk_map.values().map(|c| c.get_gpu_size()).sum()
+    }
+
+    /// Uploads the modified surface of the chunk, the geometry is rebuilt unless the chunk
+    /// is volumetric
+    pub fn update(
+        &mut self,
+        chunk: &Chunk,
+        geometry: Option<&ChunkGeometry>,
+    ) -> Result<(), ChunkError> {
+        if let Some(render_chunk) = self.chunk_map.get_mut(&chunk.get_pos()) {
+            render_chunk.update_surface(chunk, geometry)?;
+        }
+        Ok(())
+    }
+
+    /// Positions of all chunks relative to the render origin
+    pub fn set_render_positions<F: Fn(Point2i) -> Point2f>(&mut self, get_render_pos: F) {
+        for (pos, render_chunk) in self.chunk_map.iter_mut() {
+            render_chunk.set_render_pos(get_render_pos(*pos));
+        }
+    }
+
+    /// Center in chunks, chunks keep their level of detail within the hysteresis
+    pub fn update_lods(&mut self, center_pos: Point2f) {
+        self.lod_center = center_pos;
+        for (pos, render_chunk) in self.chunk_map.iter_mut() {
+            let distance = get_lod_distance(*pos, center_pos);
+            render_chunk.set_lod(select_lod(
+                render_chunk.get_lod(),
+                distance,
+                &self.lod_distances,
+            ));
+        }
+    }
+
+    pub fn update_shader_resources(
+        &mut self,
+        world_center: Point3f,
+        fog_color: Point3f,
+        scene_lights: &SceneLights,
+    ) -> Result<(), GraphicsError> {
+        self.shader.use_program();
+        self.shader
+            .set_resource_vec3("view_pos", &world_center.as_glm())?;
+        self.shader
+            .set_resource_vec3("fog_color", &fog_color.as_glm())?;
+
+        scene_lights.update_lights_for_shader(&self.shader)?;
+        Ok(())
+    }
+
+    // Expects the water shader to be active
+    pub fn render_water(&self, info: &mut RenderInfo) -> Result<(), GraphicsError> {
+        for render_chunk in self.chunk_map.values() {
+            render_chunk.render_water(info)?;
+        }
+        Ok(())
+    }
+}
+
+impl Renderable for RenderCache {
+    fn render<'a>(&self, info: &'a mut RenderInfo) -> Result<(), GraphicsError> {
+        info.push_shader(self.shader.clone());
+        self.surface_texture.activate(4);
+
+        for render_chunk in self.chunk_map.values() {
+            info.set_lod(render_chunk.get_lod());
+            render_chunk.render(info)?;
+        }
+        info.set_lod(0);
+
+        info.pop_shader();
+        Ok(())
+    }
+}
+
+fn load_surface_shader(directory: &str) -> Result<ShaderProgram, GraphicsError> {
+    let surface_shader_program = ShaderProgramBuilder::new()
+        .add_vertex_shader((directory.to_owned() + "/VertexShader.glsl").as_str())
+        .add_fragment_shader((directory.to_owned() + "/FragmentShader.glsl").as_str())
+        .add_resource("mvp")
+        .add_resource("model")
+        .add_resource("grid_size")
+        .add_resource("grid_spacing")
+        .add_resource("vertex_normals")
+        .add_resource("analysis_map")
+        .add_resource("analysis_available")
+        .add_resource("road_mask")
+        .add_resource("road_available")
+        .add_resource("splat_map")
+        .add_resource("splat_available")
+        .add_resource("texture_array")
+        .add_resource("view_pos")
+        .add_resource("fog_color")
+        .add_resource("active_lights")
+        .add_resource("scene_lights[0].color")
+        .add_resource("scene_lights[0].world_pos")
+        .add_resource("scene_lights[0].absolute_intensity")
+        .add_resource("scene_lights[0].ambient_intensity")
+        .add_resource("scene_lights[0].diffuse_intensity")
+        .add_resource("scene_lights[0].specular_intensity")
+        .add_resource("scene_lights[0].specular_shininess")
+        .add_resource("scene_lights[1].color")
+        .add_resource("scene_lights[1].world_pos")
+        .add_resource("scene_lights[1].absolute_intensity")
+        .add_resource("scene_lights[1].ambient_intensity")
+        .add_resource("scene_lights[1].diffuse_intensity")
+        .add_resource("scene_lights[1].specular_intensity")
+        .add_resource("scene_lights[1].specular_shininess")
+        .finish()?;
+    Ok(surface_shader_program)
+}
+
+// Distance of the chunk center to the center, in chunks
+fn get_lod_distance(chunk_pos: Point2i, center_pos: Point2f) -> f32 {
+    (Point2f::from(chunk_pos) + Point2f::from_scalar(0.5) - center_pos).length()
+}
+
+// Switches to a finer level only once the chunk is the hysteresis closer than the lod distance,
+// and to a coarser one once it's the hysteresis further away
+pub(super) fn select_lod(lod: u8, distance: f32, lod_distances: &[i32; 3]) -> u8 {
+    let mut lod = (lod as usize).min(LOD_COUNT - 1);
+    while lod > 0 && distance < lod_distances[lod - 1] as f32 - LOD_HYSTERESIS {
+        lod -= 1;
+    }
+    while lod + 1 < LOD_COUNT && distance > lod_distances[lod] as f32 + LOD_HYSTERESIS {
+        lod += 1;
+    }
+    lod as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lod_hysteresis() {
+        let lod_distances = [10, 20, 30];
+        assert_eq!(0, select_lod(0, 5., &lod_distances));
+        assert_eq!(2, select_lod(0, 25., &lod_distances));
+        // within the hysteresis around the near distance the level is kept
+        assert_eq!(0, select_lod(0, 10.5, &lod_distances));
+        assert_eq!(1, select_lod(1, 10.5, &lod_distances));
+        assert_eq!(1, select_lod(0, 11.5, &lod_distances));
+        assert_eq!(0, select_lod(1, 8.5, &lod_distances));
+    }
+}
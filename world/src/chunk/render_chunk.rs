@@ -0,0 +1,222 @@
+use std::convert::TryInto;
+
+use super::{Chunk, ChunkError, ChunkGeometry};
+use core::graphics::{mesh::Triangle, GraphicsError};
+use core::traits::{RenderInfo, Renderable, Translatable};
+use core::{BoundingBox, Mesh, Model, Point2f, Point3f, Texture};
+
+// Position, normal and uv of a vertex
+const VERTEX_BYTES: usize = 32;
+// RGBA32F for height and normals, analysis and splat weights, R32F for the road mask
+const TEXEL_BYTES: usize = 16;
+const ROAD_TEXEL_BYTES: usize = 4;
+
+/// Meshes and textures of a chunk on the GPU
+pub struct RenderChunk {
+    model: Model,
+    lod_meshes: Vec<Mesh>,
+    lod: u8,
+    water_mesh: Option<Mesh>,
+    vertex_normals: bool,
+    height_normal_texture: Texture,
+    analysis_texture: Option<Texture>,
+    road_texture: Option<Texture>,
+    splat_texture: Option<Texture>,
+    bounding_box: BoundingBox,
+    gpu_size: usize,
+}
+
+impl RenderChunk {
+    /// Position of the chunk origin relative to the render origin
+    pub fn new(
+        chunk: &Chunk,
+        geometry: &ChunkGeometry,
+        render_pos: Point2f,
+    ) -> Result<Self, ChunkError> {
+        let mut model = Model::default();
+        model.set_translation(render_pos.extend(0.));
+
+        let water_triangles = geometry.get_water_triangles();
+        let water_mesh: Option<Mesh> = if water_triangles.is_empty() {
+            None
+        } else {
+            Some(water_triangles.try_into()?)
+        };
+        let analysis_texture: Option<Texture> = match chunk.get_analysis() {
+            Some(a) => Some(a.try_into()?),
+            None => None,
+        };
+        let road_texture: Option<Texture> = match chunk.get_road_mask() {
+            Some(mask) => Some(mask.try_into()?),
+            None => None,
+        };
+        let splat_texture: Option<Texture> = match chunk.get_splat_map() {
+            Some(splat) => Some(splat.try_into()?),
+            None => None,
+        };
+
+        let mut render_chunk = Self {
+            model: model,
+            lod_meshes: create_lod_meshes(geometry.get_lod_triangles())?,
+            lod: 0,
+            water_mesh: water_mesh,
+            vertex_normals: chunk.get_density().is_some(),
+            height_normal_texture: chunk.get_heightmap().clone().try_into()?,
+            analysis_texture: analysis_texture,
+            road_texture: road_texture,
+            splat_texture: splat_texture,
+            bounding_box: build_bounding_box(chunk),
+            gpu_size: 0,
+        };
+        render_chunk.gpu_size = render_chunk.estimate_gpu_size(chunk);
+        Ok(render_chunk)
+    }
+
+    /// Position of the chunk origin relative to the render origin
+    pub fn set_render_pos(&mut self, render_pos: Point2f) {
+        self.model.set_translation(render_pos.extend(0.));
+    }
+
+    pub fn get_lod(&self) -> u8 {
+        self.lod
+    }
+
+    /// Level of detail rendered, volumetric chunks have the full resolution only
+    pub fn set_lod(&mut self, lod: u8) {
+        self.lod = lod;
+    }
+
+    /// Uploads the surface of the chunk after its heightmap was modified.
+    /// Volumetric chunks keep their mesh, as it doesn't depend on the heightmap alone.
+    pub fn update_surface(
+        &mut self,
+        chunk: &Chunk,
+        geometry: Option<&ChunkGeometry>,
+    ) -> Result<(), ChunkError> {
+        if let Some(geometry) = geometry {
+            self.lod_meshes = create_lod_meshes(geometry.get_lod_triangles())?;
+        }
+        chunk
+            .get_heightmap()
+            .write_texture(&self.height_normal_texture)?;
+        if let (Some(analysis), Some(texture)) = (chunk.get_analysis(), &self.analysis_texture) {
+            analysis.write_texture(texture)?;
+        }
+        if let (Some(splat), Some(texture)) = (chunk.get_splat_map(), &self.splat_texture) {
+            splat.write_texture(texture)?;
+        }
+        self.bounding_box = build_bounding_box(chunk);
+        self.gpu_size = self.estimate_gpu_size(chunk);
+        Ok(())
+    }
+
+    /// Estimated bytes of the meshes and textures
+    pub fn get_gpu_size(&self) -> usize {
+        self.gpu_size
+    }
+
+    fn estimate_gpu_size(&self, chunk: &Chunk) -> usize {
+        let vertex_count: usize = self
+            .lod_meshes
+            .iter()
+            .chain(self.water_mesh.iter())
+            .map(|mesh| mesh.get_vertex_count() as usize)
+            .sum();
+        let size = chunk.get_heightmap().get_size();
+        let cells = (size * size) as usize;
+        let texture_count =
+            1 + self.analysis_texture.is_some() as usize + self.splat_texture.is_some() as usize;
+        let road_bytes = if self.road_texture.is_some() {
+            cells * ROAD_TEXEL_BYTES
+        } else {
+            0
+        };
+        vertex_count * VERTEX_BYTES + cells * texture_count * TEXEL_BYTES + road_bytes
+    }
+
+    // Expects the water shader to be active
+    pub fn render_water(&self, info: &mut RenderInfo) -> Result<(), GraphicsError> {
+        if let Some(water_mesh) = &self.water_mesh {
+            let mvp = info.get_camera().create_mvp_matrix(&self.model);
+            if self.bounding_box.is_visible(mvp) {
+                let shader = info.get_active_shader()?;
+                shader.set_resource_mat4("mvp", &mvp)?;
+                shader.set_resource_mat4("model", self.model.get_matrix_ref())?;
+                water_mesh.render(info)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn prepare_rendering(&self, info: &RenderInfo) -> Result<bool, GraphicsError> {
+        let mvp = info.get_camera().create_mvp_matrix(&self.model);
+        if self.bounding_box.is_visible(mvp) {
+            let shader = info.get_active_shader()?;
+            shader.set_resource_mat4("mvp", &mvp)?;
+            shader.set_resource_mat4("model", self.model.get_matrix_ref())?;
+            self.height_normal_texture.activate(0);
+            shader.set_resource_integer("vertex_normals", self.vertex_normals as i32)?;
+            match &self.analysis_texture {
+                Some(texture) => {
+                    texture.activate(1);
+                    shader.set_resource_integer("analysis_available", 1)?;
+                }
+                None => shader.set_resource_integer("analysis_available", 0)?,
+            }
+            match &self.road_texture {
+                Some(texture) => {
+                    texture.activate(2);
+                    shader.set_resource_integer("road_available", 1)?;
+                }
+                None => shader.set_resource_integer("road_available", 0)?,
+            }
+            match &self.splat_texture {
+                Some(texture) => {
+                    texture.activate(3);
+                    shader.set_resource_integer("splat_available", 1)?;
+                }
+                None => shader.set_resource_integer("splat_available", 0)?,
+            }
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+impl Renderable for RenderChunk {
+    /// Renders the mesh of the level of detail set in the render info
+    fn render<'a>(&self, info: &'a mut RenderInfo) -> Result<(), GraphicsError> {
+        if self.prepare_rendering(info)? {
+            let lod = (info.get_lod() as usize).min(self.lod_meshes.len() - 1);
+            self.lod_meshes[lod].render(info)?;
+        }
+        Ok(())
+    }
+}
+
+fn create_lod_meshes(lod_triangles: &[Vec<Triangle>]) -> Result<Vec<Mesh>, ChunkError> {
+    debug_assert!(!lod_triangles.is_empty());
+    let mut lod_meshes = Vec::with_capacity(lod_triangles.len());
+    for triangles in lod_triangles {
+        lod_meshes.push(triangles.as_slice().try_into()?);
+    }
+    Ok(lod_meshes)
+}
+
+// Heights are taken from the pyramid of the heightmap if it was built
+fn build_bounding_box(chunk: &Chunk) -> BoundingBox {
+    let height_map = chunk.get_heightmap();
+    let max_xy = (height_map.get_size() - 1) as f32 * height_map.get_scale_factor();
+    let [min_z, max_z] = match chunk.get_density() {
+        Some(density) => density.get_z_range(),
+        None => [height_map.get_min(), height_map.get_max()],
+    };
+    let max_z = match chunk.get_water_map().get_max_level() {
+        Some(level) => f32::max(level, max_z),
+        None => max_z,
+    };
+    let min = Point3f::new(0., 0., min_z);
+    let max = Point3f::new(max_xy, max_xy, max_z);
+    BoundingBox::from_min_max(min, max)
+}
@@ -0,0 +1,441 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{
+    BuildOptions, BuildTimings, Chunk, ChunkBuilder, ChunkError, LayerSampler, WorldLayout,
+};
+use crate::analysis::AnalysisLayer;
+use crate::architect::Architect;
+use crate::{Brush, EditStore, HeightSampler};
+use core::{Point2f, Point2i, Point3f};
+
+const RAY_STEP: f32 = 0.25;
+const RAY_REFINEMENT_STEPS: u32 = 8;
+// Height queries on volumetric chunks look for the first surface below this offset,
+// so walking up small steps and standing in caves both work
+const HEIGHT_PROBE_OFFSET: f32 = 1.;
+const HEIGHT_PROBE_DISTANCE: f32 = 512.;
+
+/// Loaded chunks and all terrain queries on them, without any graphics resources.
+/// Positions passed to and returned from the store are relative to the render origin,
+/// which is moved along with the player to keep them small.
+pub struct TerrainStore {
+    layout: WorldLayout,
+    origin: Point2i,
+    chunk_map: BTreeMap<Point2i, Chunk>,
+    material_names: Vec<String>,
+}
+
+impl TerrainStore {
+    pub fn new(layout: WorldLayout) -> Self {
+        Self {
+            layout: layout,
+            origin: Point2i::from_scalar(0),
+            chunk_map: BTreeMap::new(),
+            material_names: Vec::new(),
+        }
+    }
+
+    /// Names of the surface materials, in the channel order of the splat maps
+    pub fn with_material_names(mut self, material_names: Vec<String>) -> Self {
+        self.material_names = material_names;
+        self
+    }
+
+    /// Builds the chunk at the absolute position on the calling thread and inserts it, for use
+    /// without a chunk loader or graphics context. Built with the default build options.
+    pub fn generate(
+        &mut self,
+        architect: &Architect,
+        chunk_pos: Point2i,
+    ) -> Result<&Chunk, ChunkError> {
+        let chunk = ChunkBuilder::build_chunk(
+            chunk_pos,
+            architect,
+            &BuildOptions::default(),
+            None,
+            None,
+            &mut BuildTimings::new(),
+        )?;
+        self.insert(chunk);
+        Ok(&self.chunk_map[&chunk_pos])
+    }
+
+    /// Replaces a chunk at the same position
+    pub fn insert(&mut self, chunk: Chunk) {
+        self.chunk_map.insert(chunk.get_pos(), chunk);
+    }
+
+    pub fn remove(&mut self, chunk_pos: Point2i) -> Option<Chunk> {
+        self.chunk_map.remove(&chunk_pos)
+    }
+
+    /// Chunks are given by their absolute position
+    pub fn get_chunk(&self, chunk_pos: Point2i) -> Option<&Chunk> {
+        self.chunk_map.get(&chunk_pos)
+    }
+
+    pub fn get_chunk_map(&self) -> &BTreeMap<Point2i, Chunk> {
+        &self.chunk_map
+    }
+
+    pub fn get_chunk_count(&self) -> usize {
+        self.chunk_map.len()
+    }
+
+    /// Estimated bytes of the terrain data of all chunks
+    pub fn get_memory_size(&self) -> usize {
+        self.chunk_map.values().map(|c| c.get_memory_size()).sum()
+    }
+
+    /// Height of the terrain surface below the given position
+    pub fn get_height(&self, world_pos: Point3f) -> f32 {
+        let is_volumetric = self
+            .get_chunk_at(world_pos)
+            .and_then(|chunk| chunk.get_density())
+            .is_some();
+        let probe = world_pos + Point3f::new(0., 0., HEIGHT_PROBE_OFFSET);
+        let hit = if is_volumetric {
+            self.raycast(probe, Point3f::new(0., 0., -1.), HEIGHT_PROBE_DISTANCE)
+        } else {
+            None
+        };
+        match hit {
+            Some(hit_pos) => hit_pos[2],
+            None => self.sample_bilinear(world_pos.as_xy()),
+        }
+    }
+
+    /// Volumetric chunks are solid where their density is positive,
+    /// heightmap chunks and unloaded chunks below their surface
+    pub fn is_solid(&self, world_pos: Point3f) -> bool {
+        match self.get_chunk_at(world_pos) {
+            Some(chunk) => match chunk.get_density() {
+                Some(density) => {
+                    let local_pos = world_pos.as_xy() - self.get_render_pos(chunk.get_pos());
+                    density.sample(local_pos.extend(world_pos[2])) > 0.
+                }
+                None => world_pos[2] < self.sample_bilinear(world_pos.as_xy()),
+            },
+            None => world_pos[2] < self.sample_bilinear(world_pos.as_xy()),
+        }
+    }
+
+    /// First intersection of the ray with the terrain surface within the given distance
+    pub fn raycast(
+        &self,
+        origin: Point3f,
+        direction: Point3f,
+        max_distance: f32,
+    ) -> Option<Point3f> {
+        let direction = direction.as_normalized();
+        if self.is_solid(origin) {
+            return Some(origin);
+        }
+        let mut free_distance = 0.;
+        let mut distance = RAY_STEP;
+        while distance <= max_distance {
+            if self.is_solid(origin + direction * distance) {
+                let mut solid_distance = distance;
+                for _ in 0..RAY_REFINEMENT_STEPS {
+                    let middle = (free_distance + solid_distance) / 2.;
+                    if self.is_solid(origin + direction * middle) {
+                        solid_distance = middle;
+                    } else {
+                        free_distance = middle;
+                    }
+                }
+                return Some(origin + direction * solid_distance);
+            }
+            free_distance = distance;
+            distance += RAY_STEP;
+        }
+        None
+    }
+
+    /// Applies the brush to the heightmaps of all loaded chunks within its radius and records
+    /// the changes in the edit store. Volumetric chunks and water bodies are left unchanged.
+    /// Returns the positions of the chunks whose surface changed.
+    pub fn apply_brush(
+        &mut self,
+        brush: &Brush,
+        center: Point2f,
+        intensity: f32,
+        edit_store: &mut EditStore,
+        options: &BuildOptions,
+    ) -> BTreeSet<Point2i> {
+        let heights = brush.calculate_heights(self, center, intensity);
+
+        let mut modified = BTreeSet::new();
+        for (grid_pos, height) in heights.iter() {
+            // shared edges have the same procedural height in every chunk, so the delta
+            // is recorded for all of them, loaded or not, to keep their edges together
+            let (owner_pos, owner_local_pos) = self.split_grid_pos(*grid_pos);
+            let applied_delta = edit_store
+                .get_delta(owner_pos)
+                .map_or(0., |delta| delta.get(owner_local_pos));
+            let procedural_height = self.get_grid_height(*grid_pos) - applied_delta;
+            for (chunk_pos, local_pos) in self.get_containing_chunks(*grid_pos) {
+                match self.chunk_map.get_mut(&chunk_pos) {
+                    Some(chunk) if chunk.get_density().is_some() => continue,
+                    Some(chunk) => {
+                        chunk.get_heightmap_mut().set(local_pos, *height);
+                        modified.insert(chunk_pos);
+                    }
+                    None => {}
+                }
+                edit_store.set_delta(chunk_pos, local_pos, height - procedural_height);
+                if let (Some(chunk), Some(delta)) = (
+                    self.chunk_map.get_mut(&chunk_pos),
+                    edit_store.get_delta(chunk_pos),
+                ) {
+                    chunk.set_edit_revision(delta.get_revision());
+                }
+            }
+            // normals on the edges of neighbouring chunks depend on this height too
+            for offset in [(1, 0), (-1, 0), (0, 1), (0, -1)].iter() {
+                let nb_pos = *grid_pos + Point2i::new(offset.0, offset.1);
+                for (chunk_pos, _) in self.get_containing_chunks(nb_pos) {
+                    if self.chunk_map.contains_key(&chunk_pos) {
+                        modified.insert(chunk_pos);
+                    }
+                }
+            }
+        }
+
+        for chunk_pos in modified.iter() {
+            let normals = self.calculate_chunk_normals(*chunk_pos);
+            if let Some(chunk) = self.chunk_map.get_mut(chunk_pos) {
+                let heightmap = chunk.get_heightmap_mut();
+                for (local_pos, normal) in normals {
+                    heightmap.set_normal(local_pos, normal);
+                }
+                chunk.update_surface(options);
+            }
+        }
+        modified
+    }
+
+    /// Water surface level at the nearest grid point, none if dry or not loaded
+    pub fn get_water_level(&self, world_pos: Point2f) -> Option<f32> {
+        let grid_pos = self.layout.get_grid_pos(world_pos);
+        let (chunk_pos, local_pos) = self.split_grid_pos(grid_pos);
+        self.chunk_map
+            .get(&chunk_pos)
+            .and_then(|chunk| chunk.get_water_map().get_level(local_pos))
+    }
+
+    /// Road surface coverage at the nearest grid point, zero if there is no road or not loaded
+    pub fn get_road_coverage(&self, world_pos: Point2f) -> f32 {
+        let grid_pos = self.layout.get_grid_pos(world_pos);
+        let (chunk_pos, local_pos) = self.split_grid_pos(grid_pos);
+        self.chunk_map
+            .get(&chunk_pos)
+            .and_then(|chunk| chunk.get_road_mask())
+            .map_or(0., |mask| mask.get(local_pos))
+    }
+
+    /// Name of the dominant surface material at the nearest grid point, none if not loaded
+    pub fn get_surface_material(&self, world_pos: Point2f) -> Option<&str> {
+        let grid_pos = self.layout.get_grid_pos(world_pos);
+        let (chunk_pos, local_pos) = self.split_grid_pos(grid_pos);
+        let splat = self.chunk_map.get(&chunk_pos)?.get_splat_map()?;
+        self.material_names
+            .get(splat.get_dominant(local_pos))
+            .map(|name| name.as_str())
+    }
+
+    pub fn get_layer_sampler(&self, layer: AnalysisLayer) -> LayerSampler<'_> {
+        LayerSampler::new(&self.chunk_map, self.layout, self.origin, layer)
+    }
+
+    pub fn get_layout(&self) -> WorldLayout {
+        self.layout
+    }
+
+    /// Chunk whose origin is the render origin
+    pub fn get_origin(&self) -> Point2i {
+        self.origin
+    }
+
+    /// Moves the render origin, all positions relative to the old origin become invalid
+    pub fn set_origin(&mut self, origin: Point2i) {
+        self.origin = origin;
+    }
+
+    /// Origin of the chunk relative to the render origin
+    pub fn get_render_pos(&self, chunk_pos: Point2i) -> Point2f {
+        self.layout.get_world_pos(chunk_pos - self.origin, None)
+    }
+
+    /// Absolute position of the chunk containing the position
+    pub fn get_chunk_pos(&self, render_pos: Point3f) -> Point2i {
+        self.layout.get_chunk_pos(render_pos) + self.origin
+    }
+
+    fn get_chunk_at(&self, world_pos: Point3f) -> Option<&Chunk> {
+        self.chunk_map.get(&self.get_chunk_pos(world_pos))
+    }
+
+    fn calculate_chunk_normals(&self, chunk_pos: Point2i) -> Vec<(Point2i, Point3f)> {
+        let grid_size = self.layout.get_grid_size();
+        let mut normals = Vec::with_capacity((grid_size * grid_size) as usize);
+        for y in 0..grid_size {
+            for x in 0..grid_size {
+                let local_pos = Point2i::new(x, y);
+                normals.push((
+                    local_pos,
+                    self.get_grid_normal(
+                        self.layout
+                            .join_grid_pos(chunk_pos - self.origin, local_pos),
+                    ),
+                ));
+            }
+        }
+        normals
+    }
+
+    // Splits a grid position relative to the render origin into absolute chunk and local position
+    fn split_grid_pos(&self, grid_pos: Point2i) -> (Point2i, Point2i) {
+        let (chunk_pos, local_pos) = self.layout.split_grid_pos(grid_pos);
+        (chunk_pos + self.origin, local_pos)
+    }
+
+    fn get_containing_chunks(&self, grid_pos: Point2i) -> Vec<(Point2i, Point2i)> {
+        self.layout
+            .get_containing_chunks(grid_pos)
+            .into_iter()
+            .map(|(chunk_pos, local_pos)| (chunk_pos + self.origin, local_pos))
+            .collect()
+    }
+}
+
+// Samples across chunk borders, so positions on shared chunk edges yield the same result
+// regardless of the chunk they are assigned to. Unloaded chunks have a height of zero.
+impl HeightSampler for TerrainStore {
+    fn get_grid_height(&self, grid_pos: Point2i) -> f32 {
+        let (chunk_pos, local_pos) = self.split_grid_pos(grid_pos);
+        match self.chunk_map.get(&chunk_pos) {
+            Some(chunk) => chunk.get_heightmap().get(local_pos),
+            None => 0.,
+        }
+    }
+
+    fn has_grid_height(&self, grid_pos: Point2i) -> bool {
+        let (chunk_pos, _) = self.split_grid_pos(grid_pos);
+        self.chunk_map.contains_key(&chunk_pos)
+    }
+
+    fn get_grid_spacing(&self) -> f32 {
+        self.layout.get_grid_spacing()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BrushKind, HeightMap, WaterMap};
+    use core::Seed;
+
+    fn create_chunk(layout: WorldLayout, chunk_pos: Point2i, height: f32) -> Chunk {
+        let mut heightmap = HeightMap::new(layout.get_grid_size(), layout.get_grid_spacing());
+        heightmap.normalize([height, height]);
+        let water = WaterMap::new(layout.get_grid_size(), layout.get_grid_spacing());
+        Chunk::new(chunk_pos, heightmap, None, None, water)
+    }
+
+    #[test]
+    fn test_queries_without_graphics() {
+        let layout = WorldLayout::new(16., 8);
+        let mut store = TerrainStore::new(layout);
+        store.insert(create_chunk(layout, Point2i::new(0, 0), 5.));
+        store.insert(create_chunk(layout, Point2i::new(1, 0), 5.));
+        assert_eq!(2, store.get_chunk_count());
+        assert_eq!(5., store.get_height(Point3f::new(20., 3., 10.)));
+        assert!(store.is_solid(Point3f::new(3., 3., 4.)));
+        let hit = store.raycast(Point3f::new(3., 3., 10.), Point3f::new(0., 0., -1.), 20.);
+        assert!((hit.unwrap()[2] - 5.).abs() < 1e-2);
+
+        // unloaded chunks have a height of zero
+        store.remove(Point2i::new(1, 0));
+        assert_eq!(0., store.get_height(Point3f::new(24., 3., 10.)));
+
+        // positions follow the render origin
+        store.set_origin(Point2i::new(1, 0));
+        assert_eq!(5., store.get_height(Point3f::new(-8., 8., 10.)));
+        assert_eq!(
+            Point2f::new(-16., 0.),
+            store.get_render_pos(Point2i::new(0, 0))
+        );
+    }
+
+    #[test]
+    fn test_generate_without_graphics() {
+        let layout = WorldLayout::new(16., 8);
+        let architect = Architect::from_seed(Seed::from_string("HEADLESS")).with_layout(layout);
+        let mut store = TerrainStore::new(layout);
+        let chunk_pos = Point2i::new(-1, 2);
+        let chunk = store.generate(&architect, chunk_pos).unwrap();
+        assert_eq!(chunk_pos, chunk.get_pos());
+        assert_eq!(layout.get_grid_size(), chunk.get_heightmap().get_size());
+
+        let world_pos = layout.get_world_pos(chunk_pos, Some(Point2f::new(4., 6.)));
+        let expected = architect.get_surface_height(world_pos);
+        let height = store.get_height(world_pos.extend(0.));
+        assert!(
+            (height - expected).abs() < 1e-3,
+            "{} != {}",
+            height,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_brush_skips_unloaded_edges() {
+        let layout = WorldLayout::new(16., 8);
+        let mut store = TerrainStore::new(layout);
+        store.insert(create_chunk(layout, Point2i::new(0, 0), 5.));
+        let mut edit_store = EditStore::new(Seed::from_string("BRUSH"), layout.get_grid_size());
+        let brush = Brush::new(BrushKind::Raise, 6.);
+        store.apply_brush(
+            &brush,
+            Point2f::new(16., 8.),
+            1.,
+            &mut edit_store,
+            &BuildOptions::default(),
+        );
+        let heightmap = store.get_chunk(Point2i::new(0, 0)).unwrap().get_heightmap();
+        // the edge belongs to the unloaded neighbour, whose height is unknown
+        assert_eq!(5., heightmap.get(Point2i::new(8, 4)));
+        assert!(heightmap.get(Point2i::new(7, 4)) > 5.);
+    }
+
+    #[test]
+    fn test_brush_records_unloaded_edges() {
+        let layout = WorldLayout::new(16., 8);
+        let mut store = TerrainStore::new(layout);
+        store.insert(create_chunk(layout, Point2i::new(1, 0), 5.));
+        let mut edit_store = EditStore::new(Seed::from_string("BRUSH"), layout.get_grid_size());
+        let brush = Brush::new(BrushKind::Raise, 6.);
+        for _ in 0..2 {
+            store.apply_brush(
+                &brush,
+                Point2f::new(16., 8.),
+                1.,
+                &mut edit_store,
+                &BuildOptions::default(),
+            );
+        }
+        let loaded = edit_store.get_delta(Point2i::new(1, 0)).unwrap();
+        let unloaded = edit_store.get_delta(Point2i::new(0, 0)).unwrap();
+        assert!(loaded.get(Point2i::new(0, 4)) > 0.);
+        assert_eq!(
+            loaded.get(Point2i::new(0, 4)),
+            unloaded.get(Point2i::new(8, 4))
+        );
+        let heightmap = store.get_chunk(Point2i::new(1, 0)).unwrap().get_heightmap();
+        assert_eq!(
+            5. + loaded.get(Point2i::new(0, 4)),
+            heightmap.get(Point2i::new(0, 4))
+        );
+    }
+}
@@ -1,18 +1,26 @@
+use std::any::Any;
 use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
-use std::thread;
+use std::sync::{Arc, Condvar, Mutex};
 use std::time::{Duration, Instant};
 
-use super::{BuildStats, ChunkBuilder, ChunkError};
+use super::{
+    BuildOptions, BuildStats, BuildTimings, ChunkBuilder, ChunkCache, ChunkError, ChunkQueue,
+};
 use crate::architect::Architect;
+use crate::EditStore;
 use core::Point2i;
 
 #[derive(Clone)]
 pub struct Worker {
     architect: Arc<Architect>,
+    options: BuildOptions,
+    edit_store: Arc<Mutex<EditStore>>,
+    chunk_cache: Option<Arc<ChunkCache>>,
     stop: Arc<AtomicBool>,
-    input_queue: Arc<Mutex<VecDeque<Point2i>>>,
+    input_queue: Arc<Mutex<ChunkQueue>>,
+    work_signal: Arc<Condvar>,
     output_queue: Arc<Mutex<VecDeque<ChunkBuilder>>>,
     build_stats: Arc<Mutex<BuildStats>>,
 }
@@ -20,41 +28,65 @@ pub struct Worker {
 impl Worker {
     pub fn new(
         architect: Arc<Architect>,
+        options: BuildOptions,
+        edit_store: Arc<Mutex<EditStore>>,
+        chunk_cache: Option<Arc<ChunkCache>>,
         stop: Arc<AtomicBool>,
-        input_queue: Arc<Mutex<VecDeque<Point2i>>>,
+        input_queue: Arc<Mutex<ChunkQueue>>,
+        work_signal: Arc<Condvar>,
         output_queue: Arc<Mutex<VecDeque<ChunkBuilder>>>,
         build_stats: Arc<Mutex<BuildStats>>,
     ) -> Worker {
         Worker {
             architect: architect,
+            options: options,
+            edit_store: edit_store,
+            chunk_cache: chunk_cache,
             stop: stop,
             input_queue: input_queue,
+            work_signal: work_signal,
             output_queue: output_queue,
             build_stats: build_stats,
         }
     }
 
+    /// Builds requested chunks until stopped. A failed build releases its position, so the
+    /// chunk can be requested again. A panicking builder ends the work with an error.
     pub fn work(&self) -> Result<(), ChunkError> {
-        while !self.stop.load(Ordering::Relaxed) {
-            self.work_cycle()?;
-        }
-        Ok(())
-    }
-
-    fn work_cycle(&self) -> Result<(), ChunkError> {
-        let sleep_time = Duration::from_millis(500);
-        if let Some(pos) = self.get_chunk_pos()? {
-            let build_start = Instant::now();
-            self.build_chunk(pos)?;
-            self.handle_build_stats(&build_start)?;
-        } else {
-            thread::sleep(sleep_time);
+        while let Some(pos) = self.wait_for_chunk_pos()? {
+            let result = match panic::catch_unwind(AssertUnwindSafe(|| self.build_chunk(pos))) {
+                Ok(result) => result,
+                Err(payload) => Err(ChunkError::BuilderPanic(pos, get_panic_message(&payload))),
+            };
+            if let Err(e) = result {
+                self.input_queue
+                    .lock()
+                    .map(|mut q| q.finish(pos))
+                    .or(Err(ChunkError::MutexPoison))?;
+                match e {
+                    ChunkError::BuilderPanic(..) => return Err(e),
+                    _ => error!("Failed to build chunk {}: {}", pos, e),
+                }
+            }
         }
         Ok(())
     }
 
     fn build_chunk(&self, chunk_pos: Point2i) -> Result<(), ChunkError> {
-        let builder = ChunkBuilder::new(chunk_pos, self.architect.as_ref())?;
+        let edit_delta = self
+            .edit_store
+            .lock()
+            .map(|store| store.get_delta(chunk_pos).cloned())
+            .or(Err(ChunkError::MutexPoison))?;
+        let build_start = Instant::now();
+        let builder = ChunkBuilder::new(
+            chunk_pos,
+            self.architect.as_ref(),
+            &self.options,
+            edit_delta.as_ref(),
+            self.chunk_cache.as_deref(),
+        )?;
+        self.handle_build_stats(builder.get_timings(), build_start.elapsed())?;
 
         self.output_queue
             .lock()
@@ -62,20 +94,41 @@ impl Worker {
             .or(Err(ChunkError::MutexPoison))
     }
 
-    fn handle_build_stats(&self, build_start: &Instant) -> Result<(), ChunkError> {
-        let build_time =
-            build_start.elapsed().as_secs() as u32 * 1000 + build_start.elapsed().subsec_millis();
-
+    fn handle_build_stats(
+        &self,
+        timings: &BuildTimings,
+        build_time: Duration,
+    ) -> Result<(), ChunkError> {
         self.build_stats
             .lock()
-            .map(|mut bs| bs.add_time(build_time))
+            .map(|mut bs| bs.add_build(timings, build_time))
             .or(Err(ChunkError::MutexPoison))
     }
 
-    fn get_chunk_pos(&self) -> Result<Option<Point2i>, ChunkError> {
-        self.input_queue
-            .lock()
-            .map(|mut q| q.pop_front())
-            .or(Err(ChunkError::MutexPoison))
+    // Blocks until a position is requested, none once the worker is stopped
+    fn wait_for_chunk_pos(&self) -> Result<Option<Point2i>, ChunkError> {
+        let mut queue = self.input_queue.lock().or(Err(ChunkError::MutexPoison))?;
+        loop {
+            if self.stop.load(Ordering::Relaxed) {
+                return Ok(None);
+            }
+            if let Some(pos) = queue.pop() {
+                return Ok(Some(pos));
+            }
+            queue = self
+                .work_signal
+                .wait(queue)
+                .or(Err(ChunkError::MutexPoison))?;
+        }
+    }
+}
+
+fn get_panic_message(payload: &Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("unknown cause")
     }
 }
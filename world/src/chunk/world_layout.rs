@@ -0,0 +1,378 @@
+use core::{Config, Point2f, Point2i, Point3f};
+
+const DEFAULT_CHUNK_SIZE: f32 = 64.;
+const DEFAULT_RESOLUTION: i32 = 64;
+const MAX_RESOLUTION: i32 = 1024;
+
+/// Upper bound for the grid points of a chunk along each axis
+pub const MAX_GRID_SIZE: i32 = MAX_RESOLUTION + 1;
+
+/*
+    Coordinate spaces:
+    - world: absolute positions in world units
+    - chunk: chunk positions, chunk (0, 0) spans [0, chunk size) on both axes
+    - relative: world units relative to the origin of the containing chunk
+    - grid: absolute sample grid positions, grid (0, 0) lies at the world origin
+    - local: sample grid positions within a chunk, from 0 to resolution inclusive
+
+    A world position belongs to exactly one chunk, chunk extents are half-open.
+    Grid points on a shared edge belong to both chunks, shared corners to all four.
+*/
+
+/// Size of the chunks in world units and the number of grid cells along each chunk axis.
+/// Neighbouring chunks share their edge grid points, so a chunk has 2^k + 1 grid points
+/// along each axis. By default a chunk spans 64 world units with one grid cell per unit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldLayout {
+    chunk_size: f32,
+    resolution: i32,
+}
+
+impl Default for WorldLayout {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHUNK_SIZE, DEFAULT_RESOLUTION)
+    }
+}
+
+impl WorldLayout {
+    /// The resolution must be a power of two, so chunk heightmaps can be triangulated adaptively
+    pub fn new(chunk_size: f32, resolution: i32) -> Self {
+        debug_assert!(chunk_size > 0.);
+        debug_assert!(resolution > 0 && resolution & (resolution - 1) == 0);
+        debug_assert!(resolution <= MAX_RESOLUTION);
+        Self {
+            chunk_size: chunk_size,
+            resolution: resolution,
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        let mut chunk_size = config.get_float_or_default("chunk_size", DEFAULT_CHUNK_SIZE);
+        if chunk_size <= 0. {
+            warn!(
+                "Invalid chunk size {}, using {}",
+                chunk_size, DEFAULT_CHUNK_SIZE
+            );
+            chunk_size = DEFAULT_CHUNK_SIZE;
+        }
+        let mut resolution = config.get_int_or_default("chunk_resolution", DEFAULT_RESOLUTION);
+        if resolution <= 0 || resolution & (resolution - 1) != 0 {
+            warn!(
+                "Chunk resolution {} is not a power of two, using {}",
+                resolution, DEFAULT_RESOLUTION
+            );
+            resolution = DEFAULT_RESOLUTION;
+        } else if resolution > MAX_RESOLUTION {
+            warn!(
+                "Chunk resolution {} is above {}, using {}",
+                resolution, MAX_RESOLUTION, MAX_RESOLUTION
+            );
+            resolution = MAX_RESOLUTION;
+        }
+        Self::new(chunk_size, resolution)
+    }
+
+    /// In world units
+    pub fn get_chunk_size(&self) -> f32 {
+        self.chunk_size
+    }
+
+    /// Grid cells along each chunk axis
+    pub fn get_resolution(&self) -> i32 {
+        self.resolution
+    }
+
+    /// Grid points along each chunk axis, including both edges
+    pub fn get_grid_size(&self) -> i32 {
+        self.resolution + 1
+    }
+
+    /// Distance between neighbouring grid points in world units
+    pub fn get_grid_spacing(&self) -> f32 {
+        self.chunk_size / self.resolution as f32
+    }
+
+    /// Chunk whose extent contains the world position, positions on a shared edge
+    /// belong to the chunk with the larger coordinate
+    pub fn get_chunk_pos(&self, world_pos: Point3f) -> Point2i {
+        Point2i::new(
+            self.get_chunk_coord(world_pos[0]),
+            self.get_chunk_coord(world_pos[1]),
+        )
+    }
+
+    /// Between zero inclusive and the chunk size exclusive
+    pub fn get_relative_pos(&self, world_pos: Point3f) -> Point2f {
+        let chunk_pos = self.get_chunk_pos(world_pos);
+        world_pos.as_xy() - self.get_world_pos(chunk_pos, None)
+    }
+
+    /// Origin of the chunk, moved by the relative offset
+    pub fn get_world_pos(&self, chunk_pos: Point2i, offset: Option<Point2f>) -> Point2f {
+        Point2f::from(chunk_pos) * self.chunk_size
+            + match offset {
+                Some(off) => off,
+                None => Point2f::from_scalar(0.),
+            }
+    }
+
+    /// Nearest position on the global sample grid
+    pub fn get_grid_pos(&self, world_pos: Point2f) -> Point2i {
+        let grid_pos = world_pos / self.get_grid_spacing();
+        Point2i::new(grid_pos[0].round() as i32, grid_pos[1].round() as i32)
+    }
+
+    /// Splits a grid position into chunk position and local position.
+    /// The local position lies between zero inclusive and the resolution exclusive,
+    /// so grid points on shared edges are assigned to the chunk with the larger coordinate.
+    pub fn split_grid_pos(&self, grid_pos: Point2i) -> (Point2i, Point2i) {
+        let cells = self.resolution;
+        let chunk_pos = Point2i::new(grid_pos[0].div_euclid(cells), grid_pos[1].div_euclid(cells));
+        (chunk_pos, grid_pos - chunk_pos * cells)
+    }
+
+    /// All chunks containing the grid position, with the position within each chunk.
+    /// Positions on shared edges belong to two chunks, shared corners to four.
+    pub fn get_containing_chunks(&self, grid_pos: Point2i) -> Vec<(Point2i, Point2i)> {
+        let cells = self.resolution;
+        let (chunk_pos, local_pos) = self.split_grid_pos(grid_pos);
+        let mut result = vec![(chunk_pos, local_pos)];
+        if local_pos[0] == 0 {
+            result.push((
+                chunk_pos - Point2i::new(1, 0),
+                local_pos + Point2i::new(cells, 0),
+            ));
+        }
+        if local_pos[1] == 0 {
+            result.push((
+                chunk_pos - Point2i::new(0, 1),
+                local_pos + Point2i::new(0, cells),
+            ));
+        }
+        if local_pos[0] == 0 && local_pos[1] == 0 {
+            result.push((
+                chunk_pos - Point2i::new(1, 1),
+                local_pos + Point2i::new(cells, cells),
+            ));
+        }
+        result
+    }
+
+    /// Inverse of `split_grid_pos`, also accepts local positions on the far edges
+    pub fn join_grid_pos(&self, chunk_pos: Point2i, local_pos: Point2i) -> Point2i {
+        chunk_pos * self.resolution + local_pos
+    }
+
+    // Floor division, corrected against the chunk origins, so rounding
+    // can't move a position into a chunk whose extent doesn't contain it
+    fn get_chunk_coord(&self, world_coord: f32) -> i32 {
+        let chunk_coord = (world_coord / self.chunk_size).floor() as i32;
+        if (chunk_coord.saturating_add(1) as f32) * self.chunk_size <= world_coord {
+            chunk_coord.saturating_add(1)
+        } else if chunk_coord as f32 * self.chunk_size > world_coord {
+            chunk_coord.saturating_sub(1)
+        } else {
+            chunk_coord
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::Seed;
+    use rand::rngs::StdRng;
+    use rand::Rng;
+
+    const SAMPLES: usize = 10000;
+
+    fn create_layouts() -> Vec<WorldLayout> {
+        vec![
+            WorldLayout::default(),
+            WorldLayout::new(128., 32),
+            WorldLayout::new(100., 64),
+            WorldLayout::new(0.75, 8),
+        ]
+    }
+
+    fn create_rng() -> StdRng {
+        Seed::from_string("COORDINATES").into()
+    }
+
+    #[test]
+    fn test_negative_world_pos_to_chunk_pos() {
+        assert_eq!(
+            Point2i::new(-1, -1),
+            WorldLayout::default().get_chunk_pos(Point3f::new(-1., -1., 0.))
+        );
+    }
+
+    #[test]
+    fn test_negative_chunk_pos_to_world_pos() {
+        let layout = WorldLayout::default();
+        assert_eq!(
+            Point2f::from_scalar(-layout.get_chunk_size()),
+            layout.get_world_pos(Point2i::new(-1, -1), None)
+        );
+    }
+
+    #[test]
+    fn test_default_chunk_extent() {
+        let layout = WorldLayout::default();
+        assert_eq!(64., layout.get_chunk_size());
+        assert_eq!(65, layout.get_grid_size());
+        assert_eq!(1., layout.get_grid_spacing());
+    }
+
+    #[test]
+    fn test_grid_spacing() {
+        let layout = WorldLayout::new(128., 32);
+        assert_eq!(33, layout.get_grid_size());
+        assert_eq!(4., layout.get_grid_spacing());
+        assert_eq!(
+            Point2i::new(2, -3),
+            layout.get_grid_pos(Point2f::new(9., -11.))
+        );
+        let (chunk_pos, local_pos) = layout.split_grid_pos(Point2i::new(33, -1));
+        assert_eq!(Point2i::new(1, -1), chunk_pos);
+        assert_eq!(Point2i::new(1, 31), local_pos);
+    }
+
+    fn assert_roundtrip(chunk_pos: Point2i) {
+        let layout = WorldLayout::default();
+        assert_eq!(
+            chunk_pos,
+            layout.get_chunk_pos(layout.get_world_pos(chunk_pos, None).extend(0.))
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_zero_zero() {
+        assert_roundtrip(Point2i::new(0, 0));
+    }
+
+    #[test]
+    fn test_roundtrip_pos_pos() {
+        assert_roundtrip(Point2i::new(1, 1));
+    }
+
+    #[test]
+    fn test_roundtrip_neg_neg() {
+        assert_roundtrip(Point2i::new(-1, -1));
+    }
+
+    #[test]
+    fn test_roundtrip_pos_neg() {
+        assert_roundtrip(Point2i::new(1, -1));
+    }
+
+    #[test]
+    fn test_roundtrip_neg_pos() {
+        assert_roundtrip(Point2i::new(-1, 1));
+    }
+
+    #[test]
+    fn test_shared_edges_near_origin() {
+        let layout = WorldLayout::default();
+        let chunk_pos = |x, y| layout.get_chunk_pos(Point3f::new(x, y, 0.));
+        assert_eq!(Point2i::new(0, 0), chunk_pos(0., 0.));
+        assert_eq!(Point2i::new(-1, 0), chunk_pos(-1e-3, 0.));
+        assert_eq!(Point2i::new(0, -1), chunk_pos(63.99, -64.));
+        assert_eq!(Point2i::new(1, -2), chunk_pos(64., -64.01));
+        assert_eq!(
+            Point2f::new(0.5, 63.5),
+            layout.get_relative_pos(Point3f::new(-63.5, -0.5, 0.))
+        );
+    }
+
+    #[test]
+    fn test_chunk_world_roundtrip_random() {
+        let mut rng = create_rng();
+        for layout in create_layouts() {
+            for _ in 0..SAMPLES {
+                // world positions are f32, beyond this range neighbouring origins collapse
+                let chunk_pos = Point2i::new(
+                    rng.gen_range(-(1 << 20)..(1 << 20)),
+                    rng.gen_range(-(1 << 20)..(1 << 20)),
+                );
+                let origin = layout.get_world_pos(chunk_pos, None);
+                assert_eq!(chunk_pos, layout.get_chunk_pos(origin.extend(0.)));
+                assert_eq!(
+                    Point2f::from_scalar(0.),
+                    layout.get_relative_pos(origin.extend(0.))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_world_pos_within_chunk_random() {
+        let mut rng = create_rng();
+        for layout in create_layouts() {
+            let size = layout.get_chunk_size();
+            for _ in 0..SAMPLES {
+                let world_pos =
+                    Point3f::new(rng.gen_range(-1e6..1e6), rng.gen_range(-1e6..1e6), 0.);
+                let chunk_pos = layout.get_chunk_pos(world_pos);
+                let origin = layout.get_world_pos(chunk_pos, None);
+                let extent = layout.get_world_pos(chunk_pos + Point2i::from_scalar(1), None);
+                let relative_pos = layout.get_relative_pos(world_pos);
+                for i in 0..2 {
+                    assert!(origin[i] <= world_pos[i] && world_pos[i] < extent[i]);
+                    assert!(relative_pos[i] >= 0. && relative_pos[i] <= size);
+                }
+            }
+        }
+    }
+
+    fn create_grid_positions(rng: &mut StdRng) -> Vec<Point2i> {
+        let extremes = [i32::MIN, i32::MIN + 1, -1, 0, 1, i32::MAX - 1, i32::MAX];
+        let mut positions: Vec<Point2i> = extremes
+            .iter()
+            .flat_map(|x| extremes.iter().map(move |y| Point2i::new(*x, *y)))
+            .collect();
+        positions.extend((0..SAMPLES).map(|_| Point2i::new(rng.gen(), rng.gen())));
+        positions
+    }
+
+    #[test]
+    fn test_split_grid_pos_whole_range() {
+        let mut rng = create_rng();
+        let positions = create_grid_positions(&mut rng);
+        for layout in create_layouts() {
+            let cells = layout.get_resolution();
+            for grid_pos in positions.iter() {
+                let (chunk_pos, local_pos) = layout.split_grid_pos(*grid_pos);
+                for i in 0..2 {
+                    assert!(local_pos[i] >= 0 && local_pos[i] < cells);
+                }
+                assert_eq!(*grid_pos, layout.join_grid_pos(chunk_pos, local_pos));
+            }
+        }
+    }
+
+    #[test]
+    fn test_containing_chunks_whole_range() {
+        let mut rng = create_rng();
+        let positions = create_grid_positions(&mut rng);
+        for layout in create_layouts() {
+            let cells = layout.get_resolution();
+            for grid_pos in positions.iter() {
+                let containing = layout.get_containing_chunks(*grid_pos);
+                let (_, local_pos) = layout.split_grid_pos(*grid_pos);
+                let edges = (0..2).filter(|i| local_pos[*i] == 0).count();
+                assert_eq!(1 << edges, containing.len());
+                for (chunk_pos, local_pos) in containing {
+                    for i in 0..2 {
+                        assert!(local_pos[i] >= 0 && local_pos[i] <= cells);
+                        // chunks beyond the i32 grid range are valid, so join in i64
+                        assert_eq!(
+                            grid_pos[i] as i64,
+                            chunk_pos[i] as i64 * cells as i64 + local_pos[i] as i64
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
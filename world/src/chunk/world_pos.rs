@@ -0,0 +1,90 @@
+use super::WorldLayout;
+use core::{Point2i, Point3d, Point3f};
+
+/// Position given by its chunk and the offset from the chunk origin, precise anywhere
+/// in the world. The offset lies within the chunk on the xy-plane, z is absolute.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldPos {
+    chunk_pos: Point2i,
+    offset: Point3f,
+}
+
+impl WorldPos {
+    /// Offsets beyond the chunk extent move the position into the neighbouring chunk
+    pub fn new(layout: &WorldLayout, chunk_pos: Point2i, offset: Point3f) -> Self {
+        let shift = layout.get_chunk_pos(offset);
+        Self {
+            chunk_pos: chunk_pos + shift,
+            offset: offset - layout.get_world_pos(shift, None).extend(0.),
+        }
+    }
+
+    pub fn from_absolute(layout: &WorldLayout, pos: Point3d) -> Self {
+        let chunk_size = layout.get_chunk_size() as f64;
+        let chunk_pos = Point2i::new(
+            (pos[0] / chunk_size).floor() as i32,
+            (pos[1] / chunk_size).floor() as i32,
+        );
+        let offset = Point3f::new(
+            (pos[0] - chunk_pos[0] as f64 * chunk_size) as f32,
+            (pos[1] - chunk_pos[1] as f64 * chunk_size) as f32,
+            pos[2] as f32,
+        );
+        Self::new(layout, chunk_pos, offset)
+    }
+
+    pub fn get_chunk_pos(&self) -> Point2i {
+        self.chunk_pos
+    }
+
+    pub fn get_offset(&self) -> Point3f {
+        self.offset
+    }
+
+    pub fn translate(&self, layout: &WorldLayout, offset: Point3f) -> Self {
+        Self::new(layout, self.chunk_pos, self.offset + offset)
+    }
+
+    /// Vector from this position to the other one, precise as long as both are close
+    pub fn get_offset_to(&self, layout: &WorldLayout, other: &WorldPos) -> Point3f {
+        layout
+            .get_world_pos(other.chunk_pos - self.chunk_pos, None)
+            .extend(0.)
+            + other.offset
+            - self.offset
+    }
+
+    pub fn as_absolute(&self, layout: &WorldLayout) -> Point3d {
+        let chunk_size = layout.get_chunk_size() as f64;
+        Point3d::new(
+            self.chunk_pos[0] as f64 * chunk_size + self.offset[0] as f64,
+            self.chunk_pos[1] as f64 * chunk_size + self.offset[1] as f64,
+            self.offset[2] as f64,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_normalized() {
+        let layout = WorldLayout::default();
+        let pos = WorldPos::new(&layout, Point2i::new(2, 0), Point3f::new(-1., 130., 5.));
+        assert_eq!(Point2i::new(1, 2), pos.get_chunk_pos());
+        assert_eq!(Point3f::new(63., 2., 5.), pos.get_offset());
+    }
+
+    #[test]
+    fn test_precise_far_from_origin() {
+        let layout = WorldLayout::default();
+        let far = Point2i::from_scalar(1 << 24);
+        let a = WorldPos::new(&layout, far, Point3f::new(10., 10., 0.));
+        let b = a.translate(&layout, Point3f::new(0.01, 60., 2.));
+        let offset = a.get_offset_to(&layout, &b);
+        assert!((offset - Point3f::new(0.01, 60., 2.)).length() < 1e-4);
+        let absolute = b.as_absolute(&layout);
+        assert_eq!(b, WorldPos::from_absolute(&layout, absolute));
+    }
+}
@@ -0,0 +1,229 @@
+use crate::analysis::AnalysisMap;
+use crate::HeightSampler;
+use core::{Point2d, Point2f, Point2i};
+
+// Distance from the equator at y = 0 to the poles in world units
+const POLE_DISTANCE: f64 = 131072.;
+const EQUATOR_TEMPERATURE: f32 = 30.;
+const POLE_TEMPERATURE: f32 = -30.;
+// Temperature drop per world unit above the sea level
+const LAPSE_RATE: f32 = 0.02;
+
+// Moisture the air can hold is 1 above the maximum temperature and falls off linearly below it
+const SATURATION_TEMPERATURE: [f32; 2] = [-20., 30.];
+const MIN_CAPACITY: f32 = 0.1;
+// Share of the missing moisture picked up by the air per cell over the sea
+const EVAPORATION: f32 = 0.1;
+// Share of the moisture rained out per cell without any lift
+const BACKGROUND_RAIN: f32 = 0.02;
+// Slope at which all moisture is rained out by lifting the air over the terrain
+const OROGRAPHIC_SLOPE: f32 = 1.;
+// The moisture is simulated twice along each row, restarting with dry air every 2 * FETCH
+// cells, offset by FETCH between both runs. The runs are blended with the weight falling to
+// zero where they restart, so the precipitation only depends on the absolute position and
+// maps of neighbouring regions agree on their shared edges.
+const FETCH: i64 = 64;
+
+/// Coarse temperature and precipitation over a square of the world.
+/// Moisture is picked up over the sea and carried along the prevailing wind, rising air rains
+/// out on windward slopes and leaves a rain shadow behind the mountains.
+pub struct ClimateMap {
+    origin: Point2d,
+    temperature: AnalysisMap,
+    precipitation: AnalysisMap,
+}
+
+impl ClimateMap {
+    /// Size is the number of grid points along each axis, the height function takes absolute positions.
+    /// The origin is expected to lie on the grid of the spacing.
+    pub fn simulate<H: Fn(Point2d) -> f32>(
+        height_fn: H,
+        origin: Point2d,
+        size: i32,
+        spacing: f32,
+        sea_level: f32,
+    ) -> Self {
+        let mut temperature = AnalysisMap::new(size, spacing);
+        let mut precipitation = AnalysisMap::new(size, spacing);
+        let first_column = (origin[0] / spacing as f64).round() as i64;
+        let last_column = first_column + size as i64 - 1;
+        for y in 0..size {
+            let row_y = origin[1] + (y as f64) * spacing as f64;
+            let latitude = get_latitude(row_y);
+            let forward = get_prevailing_wind(latitude)[0] > 0.;
+            // heights of the row are shared by both runs, starting up to 2 * FETCH cells upwind
+            let mut heights: Vec<Option<f32>> = vec![None; (size as i64 + 4 * FETCH) as usize];
+            let mut get_height = |column: i64| {
+                let index = (column - first_column + 2 * FETCH) as usize;
+                *heights[index].get_or_insert_with(|| {
+                    let pos = Point2d::new(column as f64 * spacing as f64, row_y);
+                    height_fn(pos).max(sea_level)
+                })
+            };
+            let mut rain_sum = vec![0.; size as usize];
+            for offset in [0, FETCH].iter() {
+                let columns: Vec<i64> = if forward {
+                    let start = first_column - (first_column - offset).rem_euclid(2 * FETCH);
+                    (start..=last_column).collect()
+                } else {
+                    let start = last_column + (offset - last_column).rem_euclid(2 * FETCH);
+                    (first_column..=start).rev().collect()
+                };
+
+                let mut moisture = 0.;
+                let mut previous_height = sea_level;
+                for column in columns {
+                    let phase = (column - offset).rem_euclid(2 * FETCH);
+                    if phase == 0 {
+                        moisture = 0.;
+                        previous_height = sea_level;
+                    }
+                    let height = get_height(column);
+                    let cell_temperature = get_temperature(latitude, height - sea_level);
+                    let capacity = get_capacity(cell_temperature);
+                    let mut rain = if height <= sea_level {
+                        moisture += (capacity - moisture).max(0.) * EVAPORATION;
+                        moisture * BACKGROUND_RAIN
+                    } else {
+                        let slope = (height - previous_height).max(0.) / spacing;
+                        let lift = (slope / OROGRAPHIC_SLOPE).min(1.);
+                        moisture * (BACKGROUND_RAIN + (1. - BACKGROUND_RAIN) * lift)
+                    };
+                    // cold air can't hold the remaining moisture
+                    rain += (moisture - rain - capacity).max(0.);
+                    moisture -= rain;
+                    previous_height = height;
+
+                    if column >= first_column && column <= last_column {
+                        let x = (column - first_column) as i32;
+                        let weight = 1. - (phase - FETCH).abs() as f32 / FETCH as f32;
+                        rain_sum[x as usize] += weight * rain;
+                        temperature.set(Point2i::new(x, y), cell_temperature);
+                    }
+                }
+            }
+            for (x, rain) in rain_sum.iter().enumerate() {
+                precipitation.set(Point2i::new(x as i32, y), *rain);
+            }
+        }
+        Self {
+            origin: origin,
+            temperature: temperature,
+            precipitation: precipitation,
+        }
+    }
+
+    pub fn get_origin(&self) -> Point2d {
+        self.origin
+    }
+
+    /// Temperature in degrees celsius
+    pub fn get_temperature(&self, absolute_pos: Point2d) -> f32 {
+        self.temperature
+            .sample_bilinear((absolute_pos - self.origin).as_f32())
+    }
+
+    /// Share of the air moisture rained out per grid cell, between 0 and 1
+    pub fn get_precipitation(&self, absolute_pos: Point2d) -> f32 {
+        self.precipitation
+            .sample_bilinear((absolute_pos - self.origin).as_f32())
+    }
+
+    pub fn get_wind(&self, absolute_pos: Point2d) -> Point2f {
+        get_prevailing_wind(get_latitude(absolute_pos[1]))
+    }
+
+    pub fn get_temperature_map(&self) -> &AnalysisMap {
+        &self.temperature
+    }
+
+    pub fn get_precipitation_map(&self) -> &AnalysisMap {
+        &self.precipitation
+    }
+}
+
+/// 0 at the equator, -1 and 1 at the poles
+pub fn get_latitude(absolute_y: f64) -> f32 {
+    (absolute_y / POLE_DISTANCE).max(-1.).min(1.) as f32
+}
+
+/// Unit direction the wind blows towards: easterly trade winds near the equator,
+/// westerlies at mid latitudes and polar easterlies
+pub fn get_prevailing_wind(latitude: f32) -> Point2f {
+    let band = latitude.abs();
+    if band < 1. / 3. || band >= 2. / 3. {
+        Point2f::new(-1., 0.)
+    } else {
+        Point2f::new(1., 0.)
+    }
+}
+
+fn get_temperature(latitude: f32, elevation: f32) -> f32 {
+    EQUATOR_TEMPERATURE + (POLE_TEMPERATURE - EQUATOR_TEMPERATURE) * latitude.abs()
+        - LAPSE_RATE * elevation
+}
+
+fn get_capacity(temperature: f32) -> f32 {
+    let t = (temperature - SATURATION_TEMPERATURE[0])
+        / (SATURATION_TEMPERATURE[1] - SATURATION_TEMPERATURE[0]);
+    t.max(MIN_CAPACITY).min(1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sea west of x = 0, a ridge peaking at x = 2048
+    fn ridge_height(pos: Point2d) -> f32 {
+        if pos[0] < 0. {
+            -10.
+        } else {
+            (300. - (pos[0] - 2048.).abs() as f32 * 0.2).max(10.)
+        }
+    }
+
+    #[test]
+    fn test_temperature_by_latitude_and_elevation() {
+        let flat = |_| 10.;
+        let spacing = 128.;
+        let equator = ClimateMap::simulate(flat, Point2d::new(0., 0.), 8, spacing, 0.);
+        let north =
+            ClimateMap::simulate(flat, Point2d::new(0., POLE_DISTANCE / 2.), 8, spacing, 0.);
+        let pos = Point2d::new(256., 0.);
+        let north_pos = Point2d::new(256., POLE_DISTANCE / 2.);
+        assert!(equator.get_temperature(pos) > north.get_temperature(north_pos));
+
+        let mountain = ClimateMap::simulate(|_| 500., Point2d::new(0., 0.), 8, spacing, 0.);
+        assert!(mountain.get_temperature(pos) < equator.get_temperature(pos));
+    }
+
+    #[test]
+    fn test_rain_shadow() {
+        // westerlies at mid latitudes carry the moisture from the sea over the ridge
+        let origin = Point2d::new(-1024., POLE_DISTANCE / 2.);
+        let climate = ClimateMap::simulate(ridge_height, origin, 48, 128., 0.);
+        let windward = Point2d::new(1536., origin[1]);
+        let leeward = Point2d::new(2560., origin[1]);
+        assert_eq!(Point2f::new(1., 0.), climate.get_wind(windward));
+        assert!(climate.get_precipitation(windward) > 2. * climate.get_precipitation(leeward));
+    }
+
+    #[test]
+    fn test_shared_edge_of_neighbouring_maps() {
+        let spacing = 128.;
+        let size = 17;
+        let origin = Point2d::new(-1024., POLE_DISTANCE / 2.);
+        let neighbour_origin = origin + Point2d::new((size - 1) as f64 * spacing as f64, 0.);
+        let climate = ClimateMap::simulate(ridge_height, origin, size, spacing, 0.);
+        let neighbour = ClimateMap::simulate(ridge_height, neighbour_origin, size, spacing, 0.);
+        for y in 0..size {
+            assert_eq!(
+                climate
+                    .get_precipitation_map()
+                    .get(Point2i::new(size - 1, y)),
+                neighbour.get_precipitation_map().get(Point2i::new(0, y))
+            );
+        }
+        assert!(neighbour.get_precipitation(neighbour_origin) > 0.);
+    }
+}
@@ -0,0 +1,3 @@
+mod climate_map;
+
+pub use self::climate_map::ClimateMap;
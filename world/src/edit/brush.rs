@@ -0,0 +1,246 @@
+use std::fmt;
+
+use crate::{HeightSampler, Noise};
+use core::{Point2f, Point2i};
+
+pub enum BrushKind {
+    Raise,
+    Lower,
+    /// Pulls heights towards the given height
+    Flatten(f32),
+    /// Pulls heights towards the average of their neighbours
+    Smooth,
+    /// Adds the noise, sampled at the world position
+    NoiseStamp(Box<dyn Noise>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Falloff {
+    Constant,
+    Linear,
+    Smooth,
+}
+
+/// Height modification around a center, weighted by the distance to the center.
+/// The strength is given in height units for raising, lowering and noise stamps,
+/// and as blend factor towards the target height for flattening and smoothing.
+pub struct Brush {
+    kind: BrushKind,
+    radius: f32,
+    strength: f32,
+    falloff: Falloff,
+}
+
+impl Brush {
+    pub fn new(kind: BrushKind, radius: f32) -> Self {
+        debug_assert!(radius > 0.);
+        Self {
+            kind: kind,
+            radius: radius,
+            strength: 1.,
+            falloff: Falloff::Smooth,
+        }
+    }
+
+    pub fn with_strength(mut self, strength: f32) -> Self {
+        self.strength = strength;
+        self
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        self.falloff = falloff;
+        self
+    }
+
+    pub fn get_kind(&self) -> &BrushKind {
+        &self.kind
+    }
+
+    pub fn get_radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn set_flatten_height(&mut self, height: f32) {
+        if let BrushKind::Flatten(_) = self.kind {
+            self.kind = BrushKind::Flatten(height);
+        }
+    }
+
+    /// Weight in [0, 1] at the given distance from the center
+    pub fn get_weight(&self, distance: f32) -> f32 {
+        if distance >= self.radius {
+            return 0.;
+        }
+        let t = distance / self.radius;
+        match self.falloff {
+            Falloff::Constant => 1.,
+            Falloff::Linear => 1. - t,
+            Falloff::Smooth => 1. - t * t * (3. - 2. * t),
+        }
+    }
+
+    /// New heights of all grid points within the radius around the center, with the strength
+    /// scaled by the given intensity. Heights are read from the sampler before any modification.
+    /// Grid points without a known height are skipped.
+    pub fn calculate_heights<S: HeightSampler + ?Sized>(
+        &self,
+        sampler: &S,
+        center: Point2f,
+        intensity: f32,
+    ) -> Vec<(Point2i, f32)> {
+        let spacing = sampler.get_grid_spacing();
+        let min = Point2i::new(
+            ((center[0] - self.radius) / spacing).floor() as i32,
+            ((center[1] - self.radius) / spacing).floor() as i32,
+        );
+        let max = Point2i::new(
+            ((center[0] + self.radius) / spacing).ceil() as i32,
+            ((center[1] + self.radius) / spacing).ceil() as i32,
+        );
+        let amount = self.strength * intensity;
+
+        let mut heights = Vec::new();
+        for y in min[1]..max[1] + 1 {
+            for x in min[0]..max[0] + 1 {
+                let grid_pos = Point2i::new(x, y);
+                let world_pos = Point2f::from(grid_pos) * spacing;
+                let weight = self.get_weight((world_pos - center).length());
+                if weight <= 0. || !sampler.has_grid_height(grid_pos) {
+                    continue;
+                }
+                let height = sampler.get_grid_height(grid_pos);
+                let new_height = match &self.kind {
+                    BrushKind::Raise => height + amount * weight,
+                    BrushKind::Lower => height - amount * weight,
+                    BrushKind::Flatten(target) => {
+                        height + (target - height) * f32::min(1., amount * weight)
+                    }
+                    BrushKind::Smooth => {
+                        let average = get_neighbour_average(sampler, grid_pos);
+                        height + (average - height) * f32::min(1., amount * weight)
+                    }
+                    BrushKind::NoiseStamp(noise) => {
+                        height + noise.get_noise(world_pos) * amount * weight
+                    }
+                };
+                heights.push((grid_pos, new_height));
+            }
+        }
+        heights
+    }
+}
+
+impl fmt::Display for BrushKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BrushKind::Raise => write!(f, "raise"),
+            BrushKind::Lower => write!(f, "lower"),
+            BrushKind::Flatten(height) => write!(f, "flatten to {:.1}", height),
+            BrushKind::Smooth => write!(f, "smooth"),
+            BrushKind::NoiseStamp(_) => write!(f, "noise stamp"),
+        }
+    }
+}
+
+// Neighbours without a known height are left out
+fn get_neighbour_average<S: HeightSampler + ?Sized>(sampler: &S, grid_pos: Point2i) -> f32 {
+    let mut sum = 0.;
+    let mut count = 0;
+    for dy in -1..2 {
+        for dx in -1..2 {
+            let nb_pos = grid_pos + Point2i::new(dx, dy);
+            if sampler.has_grid_height(nb_pos) {
+                sum += sampler.get_grid_height(nb_pos);
+                count += 1;
+            }
+        }
+    }
+    sum / count as f32
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    struct RidgeSampler;
+
+    impl HeightSampler for RidgeSampler {
+        fn get_grid_height(&self, grid_pos: Point2i) -> f32 {
+            if grid_pos[0] == 0 {
+                10.
+            } else {
+                0.
+            }
+        }
+        fn get_grid_spacing(&self) -> f32 {
+            1.
+        }
+    }
+
+    // Heights left of the y axis are unknown
+    struct HalfSampler;
+
+    impl HeightSampler for HalfSampler {
+        fn get_grid_height(&self, grid_pos: Point2i) -> f32 {
+            if grid_pos[0] < 0 {
+                0.
+            } else {
+                5.
+            }
+        }
+        fn get_grid_spacing(&self) -> f32 {
+            1.
+        }
+        fn has_grid_height(&self, grid_pos: Point2i) -> bool {
+            grid_pos[0] >= 0
+        }
+    }
+
+    fn get_height_at(heights: &[(Point2i, f32)], pos: Point2i) -> f32 {
+        heights.iter().find(|(p, _)| *p == pos).unwrap().1
+    }
+
+    #[test]
+    fn test_weight_falloff() {
+        let brush = Brush::new(BrushKind::Raise, 4.).with_falloff(Falloff::Linear);
+        assert_eq!(1., brush.get_weight(0.));
+        assert_eq!(0.5, brush.get_weight(2.));
+        assert_eq!(0., brush.get_weight(4.));
+    }
+
+    #[test]
+    fn test_raise_within_radius() {
+        let brush = Brush::new(BrushKind::Raise, 2.).with_strength(3.);
+        let heights = brush.calculate_heights(&RidgeSampler, Point2f::new(5., 5.), 1.);
+        assert_eq!(3., get_height_at(&heights, Point2i::new(5, 5)));
+        assert!(heights
+            .iter()
+            .all(|(p, _)| (*p - Point2i::new(5, 5)).length() < 2.));
+    }
+
+    #[test]
+    fn test_flatten_full_strength() {
+        let brush = Brush::new(BrushKind::Flatten(4.), 3.).with_falloff(Falloff::Constant);
+        let heights = brush.calculate_heights(&RidgeSampler, Point2f::new(0., 0.), 1.);
+        assert!(heights.iter().all(|(_, h)| *h == 4.));
+    }
+
+    #[test]
+    fn test_skip_unknown_heights() {
+        let brush = Brush::new(BrushKind::Smooth, 3.).with_falloff(Falloff::Constant);
+        let heights = brush.calculate_heights(&HalfSampler, Point2f::new(0., 0.), 1.);
+        assert!(heights.iter().all(|(p, _)| p[0] >= 0));
+        assert_eq!(5., get_height_at(&heights, Point2i::new(0, 0)));
+    }
+
+    #[test]
+    fn test_smooth_lowers_ridge() {
+        let brush = Brush::new(BrushKind::Smooth, 2.).with_falloff(Falloff::Constant);
+        let heights = brush.calculate_heights(&RidgeSampler, Point2f::new(0., 0.), 1.);
+        let ridge = get_height_at(&heights, Point2i::new(0, 0));
+        let side = get_height_at(&heights, Point2i::new(1, 0));
+        assert!(ridge < 10. && ridge > 0.);
+        assert!(side > 0.);
+    }
+}
@@ -0,0 +1,94 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Error, ErrorKind, Read, Write};
+
+use crate::HeightMap;
+use core::traits::{Loadable, Saveable};
+use core::Point2i;
+
+/// Height offsets of a chunk relative to its procedural heights
+#[derive(Clone)]
+pub struct EditDelta {
+    size: i32,
+    delta_list: Vec<f32>,
+    // counts the changes since loading, so chunks built from an older state can be told apart
+    revision: u32,
+}
+
+impl EditDelta {
+    pub fn new(size: i32) -> Self {
+        let mut delta_list = Vec::new();
+        delta_list.resize((size * size) as usize, 0.);
+        Self {
+            size: size,
+            delta_list: delta_list,
+            revision: 0,
+        }
+    }
+
+    pub fn get_size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn set(&mut self, pos: Point2i, delta: f32) {
+        let index = self.get_index(pos);
+        self.delta_list[index] = delta;
+        self.revision += 1;
+    }
+
+    pub fn get_revision(&self) -> u32 {
+        self.revision
+    }
+
+    pub fn get(&self, pos: Point2i) -> f32 {
+        self.delta_list[self.get_index(pos)]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.delta_list.iter().all(|d| *d == 0.)
+    }
+
+    /// Normals of the heightmap are not updated
+    pub fn apply_to(&self, heightmap: &mut HeightMap) {
+        debug_assert!(heightmap.get_size() == self.size);
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let pos = Point2i::new(x, y);
+                heightmap.set(pos, heightmap.get(pos) + self.get(pos));
+            }
+        }
+    }
+
+    fn get_index(&self, pos: Point2i) -> usize {
+        debug_assert!(pos[0] >= 0 && pos[1] >= 0 && pos[0] < self.size && pos[1] < self.size);
+        (pos[0] + pos[1] * self.size) as usize
+    }
+}
+
+impl Saveable for EditDelta {
+    fn save(&self, writer: &mut impl Write) -> Result<(), Error> {
+        writer.write_i32::<LittleEndian>(self.size)?;
+        for delta in self.delta_list.iter() {
+            writer.write_f32::<LittleEndian>(*delta)?;
+        }
+        Ok(())
+    }
+}
+
+impl Loadable for EditDelta {
+    fn load(&mut self, reader: &mut impl Read) -> Result<(), Error> {
+        let size = reader.read_i32::<LittleEndian>()?;
+        if size <= 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid edit delta size: {}", size),
+            ));
+        }
+        let mut delta_list = Vec::with_capacity((size * size) as usize);
+        for _ in 0..size * size {
+            delta_list.push(reader.read_f32::<LittleEndian>()?);
+        }
+        self.size = size;
+        self.delta_list = delta_list;
+        Ok(())
+    }
+}
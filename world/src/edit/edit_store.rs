@@ -0,0 +1,218 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::EditDelta;
+use core::traits::{Loadable, Saveable};
+use core::{Point2i, Seed};
+
+const EDIT_FILE_MAGIC: &[u8; 4] = b"WGED";
+const EDIT_FILE_VERSION: u32 = 1;
+
+/*
+    Edit file layout, all values little endian:
+    magic "WGED", version u32, world seed [u8; 32], chunk count u32,
+    then per chunk: chunk pos i32 i32, delta size i32, delta size^2 * f32
+*/
+
+/// Terrain edits of a world, as deltas per chunk
+pub struct EditStore {
+    seed: Seed,
+    grid_size: i32,
+    delta_map: BTreeMap<Point2i, EditDelta>,
+    modified: bool,
+}
+
+impl EditStore {
+    /// Deltas cover the given number of grid points along each chunk axis
+    pub fn new(seed: Seed, grid_size: i32) -> Self {
+        Self {
+            seed: seed,
+            grid_size: grid_size,
+            delta_map: BTreeMap::new(),
+            modified: false,
+        }
+    }
+
+    pub fn get_file_path(directory: &Path, seed: Seed) -> PathBuf {
+        directory.join(format!("{}.edits", seed))
+    }
+
+    /// Empty store if there is no file yet
+    pub fn load_file(path: &Path, seed: Seed, grid_size: i32) -> Result<Self, Error> {
+        let mut store = Self::new(seed, grid_size);
+        if path.exists() {
+            let mut reader = BufReader::new(File::open(path)?);
+            store.load(&mut reader)?;
+        }
+        Ok(store)
+    }
+
+    pub fn save_file(&mut self, path: &Path) -> Result<(), Error> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        // write to a temporary file first, so a failed save can't corrupt existing edits
+        let tmp_path = path.with_extension("edits.tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            self.save(&mut writer)?;
+            writer.flush()?;
+        }
+        fs::rename(&tmp_path, path)?;
+        self.modified = false;
+        Ok(())
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn get_chunk_count(&self) -> usize {
+        self.delta_map.len()
+    }
+
+    pub fn get_delta(&self, chunk_pos: Point2i) -> Option<&EditDelta> {
+        self.delta_map.get(&chunk_pos)
+    }
+
+    /// Offset of the grid point from its procedural height
+    pub fn set_delta(&mut self, chunk_pos: Point2i, local_pos: Point2i, delta: f32) {
+        let grid_size = self.grid_size;
+        self.delta_map
+            .entry(chunk_pos)
+            .or_insert_with(|| EditDelta::new(grid_size))
+            .set(local_pos, delta);
+        self.modified = true;
+    }
+}
+
+impl Saveable for EditStore {
+    fn save(&self, writer: &mut impl Write) -> Result<(), Error> {
+        writer.write_all(EDIT_FILE_MAGIC)?;
+        writer.write_u32::<LittleEndian>(EDIT_FILE_VERSION)?;
+        writer.write_all(self.seed.as_bytes())?;
+        let deltas: Vec<(&Point2i, &EditDelta)> = self
+            .delta_map
+            .iter()
+            .filter(|(_, delta)| !delta.is_empty())
+            .collect();
+        writer.write_u32::<LittleEndian>(deltas.len() as u32)?;
+        for (chunk_pos, delta) in deltas {
+            writer.write_i32::<LittleEndian>(chunk_pos[0])?;
+            writer.write_i32::<LittleEndian>(chunk_pos[1])?;
+            delta.save(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl Loadable for EditStore {
+    fn load(&mut self, reader: &mut impl Read) -> Result<(), Error> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != EDIT_FILE_MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not an edit file"));
+        }
+        let version = reader.read_u32::<LittleEndian>()?;
+        if version != EDIT_FILE_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported edit file version: {}", version),
+            ));
+        }
+        let mut seed = [0u8; 32];
+        reader.read_exact(&mut seed)?;
+        if &seed != self.seed.as_bytes() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "edit file belongs to a different world seed",
+            ));
+        }
+
+        let count = reader.read_u32::<LittleEndian>()?;
+        let mut delta_map = BTreeMap::new();
+        for _ in 0..count {
+            let x = reader.read_i32::<LittleEndian>()?;
+            let y = reader.read_i32::<LittleEndian>()?;
+            let mut delta = EditDelta::new(1);
+            delta.load(reader)?;
+            if delta.get_size() != self.grid_size {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "edit delta size {} does not match chunk grid size {}",
+                        delta.get_size(),
+                        self.grid_size
+                    ),
+                ));
+            }
+            delta_map.insert(Point2i::new(x, y), delta);
+        }
+        self.delta_map = delta_map;
+        self.modified = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::io::Cursor;
+
+    fn create_store(seed: Seed) -> EditStore {
+        let mut store = EditStore::new(seed, 65);
+        store.set_delta(Point2i::new(-3, 7), Point2i::new(5, 6), 2.5);
+        store.set_delta(Point2i::new(-3, 7), Point2i::new(5, 6), 3.5);
+        store.set_delta(Point2i::new(1, 0), Point2i::new(0, 64), -4.);
+        store
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let seed = Seed::from_string("EDITS");
+        let mut buffer = Vec::new();
+        create_store(seed).save(&mut buffer).unwrap();
+
+        let mut loaded = EditStore::new(seed, 65);
+        loaded.load(&mut Cursor::new(buffer)).unwrap();
+        assert_eq!(2, loaded.get_chunk_count());
+        let delta = loaded.get_delta(Point2i::new(-3, 7)).unwrap();
+        assert_eq!(3.5, delta.get(Point2i::new(5, 6)));
+        assert_eq!(0., delta.get(Point2i::new(6, 5)));
+        let delta = loaded.get_delta(Point2i::new(1, 0)).unwrap();
+        assert_eq!(-4., delta.get(Point2i::new(0, 64)));
+    }
+
+    #[test]
+    fn test_seed_mismatch() {
+        let mut buffer = Vec::new();
+        create_store(Seed::from_string("EDITS"))
+            .save(&mut buffer)
+            .unwrap();
+        let mut loaded = EditStore::new(Seed::from_string("OTHER"), 65);
+        assert!(loaded.load(&mut Cursor::new(buffer)).is_err());
+    }
+
+    #[test]
+    fn test_grid_size_mismatch() {
+        let seed = Seed::from_string("EDITS");
+        let mut buffer = Vec::new();
+        create_store(seed).save(&mut buffer).unwrap();
+        let mut loaded = EditStore::new(seed, 33);
+        assert!(loaded.load(&mut Cursor::new(buffer)).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let seed = Seed::from_string("EDITS");
+        let mut buffer = Vec::new();
+        create_store(seed).save(&mut buffer).unwrap();
+        buffer[4] = 99;
+        let mut loaded = EditStore::new(seed, 65);
+        assert!(loaded.load(&mut Cursor::new(buffer)).is_err());
+    }
+}
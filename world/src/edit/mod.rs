@@ -0,0 +1,7 @@
+mod brush;
+mod edit_delta;
+mod edit_store;
+
+pub use self::brush::{Brush, BrushKind, Falloff};
+pub use self::edit_delta::EditDelta;
+pub use self::edit_store::EditStore;
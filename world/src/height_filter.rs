@@ -0,0 +1,327 @@
+use std::cmp::Ordering;
+
+use crate::{AnalysisMap, HeightMap, HeightSampler};
+use core::{Point2f, Point2i};
+
+/// How the heights of a stamp are combined with the heights below
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BlendMode {
+    Replace,
+    Add,
+    Max,
+    Min,
+}
+
+/// Heightmap placed onto another one, centered on the stamp position
+pub struct Stamp<'a> {
+    heightmap: &'a HeightMap,
+    rotation: f32,
+    scale: f32,
+    height_scale: f32,
+    blend_mode: BlendMode,
+}
+
+impl<'a> Stamp<'a> {
+    pub fn new(heightmap: &'a HeightMap) -> Self {
+        Self {
+            heightmap: heightmap,
+            rotation: 0.,
+            scale: 1.,
+            height_scale: 1.,
+            blend_mode: BlendMode::Replace,
+        }
+    }
+
+    /// Counter-clockwise, in radians
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Horizontal scale of the stamp
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        debug_assert!(scale > 0.);
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_height_scale(mut self, height_scale: f32) -> Self {
+        self.height_scale = height_scale;
+        self
+    }
+
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    // Extent of the stamp heightmap before scaling, in world units
+    fn get_extent(&self) -> f32 {
+        (self.heightmap.get_size() - 1) as f32 * self.heightmap.get_scale_factor()
+    }
+
+    // Height of the stamp at an offset from its center, none outside of the stamp
+    fn sample(&self, offset: Point2f) -> Option<f32> {
+        let (sin, cos) = (-self.rotation).sin_cos();
+        let rotated = Point2f::new(
+            offset[0] * cos - offset[1] * sin,
+            offset[0] * sin + offset[1] * cos,
+        );
+        let extent = self.get_extent();
+        let local = rotated / self.scale + Point2f::from_scalar(extent / 2.);
+        // tolerate rounding errors of the rotation on the border
+        let margin = 1e-3 * self.heightmap.get_scale_factor();
+        if local[0] < -margin
+            || local[1] < -margin
+            || local[0] > extent + margin
+            || local[1] > extent + margin
+        {
+            None
+        } else {
+            Some(self.heightmap.sample_bilinear(local) * self.height_scale)
+        }
+    }
+
+    fn blend(&self, height: f32, stamp_height: f32) -> f32 {
+        match self.blend_mode {
+            BlendMode::Replace => stamp_height,
+            BlendMode::Add => height + stamp_height,
+            BlendMode::Max => height.max(stamp_height),
+            BlendMode::Min => height.min(stamp_height),
+        }
+    }
+}
+
+/// Filters work on the heights of the map only, positions outside of it repeat the border.
+/// Radii are given in grid cells, normals are recalculated after each operation.
+/// A radius or sigma of zero leaves the heights unchanged.
+impl HeightMap {
+    pub fn blur_box(&mut self, radius: i32) {
+        debug_assert!(radius >= 0);
+        if radius <= 0 {
+            return;
+        }
+        let kernel = vec![1. / (2 * radius + 1) as f32; (2 * radius + 1) as usize];
+        self.convolve_separable(&kernel);
+    }
+
+    /// The kernel extends to three standard deviations
+    pub fn blur_gaussian(&mut self, sigma: f32) {
+        self.convolve_separable(&get_gaussian_kernel(sigma));
+    }
+
+    pub fn median(&mut self, radius: i32) {
+        self.filter_window(radius, |window| {
+            window.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+            window[window.len() / 2]
+        });
+    }
+
+    /// Unsharp masking, adds the difference to the gaussian blurred heights times the amount
+    pub fn sharpen(&mut self, sigma: f32, amount: f32) {
+        let mut blurred = self.clone();
+        blurred.convolve_separable(&get_gaussian_kernel(sigma));
+        self.map_heights(|index, height| height + (height - blurred.get_by_index(index)) * amount);
+    }
+
+    /// Morphological erosion, the minimum height within the radius
+    pub fn erode(&mut self, radius: i32) {
+        self.filter_window(radius, |window| {
+            window.iter().cloned().fold(f32::MAX, f32::min)
+        });
+    }
+
+    /// Morphological dilation, the maximum height within the radius
+    pub fn dilate(&mut self, radius: i32) {
+        self.filter_window(radius, |window| {
+            window.iter().cloned().fold(f32::MIN, f32::max)
+        });
+    }
+
+    /// Scales the heights linearly to the range, a flat map is moved to the lower bound
+    pub fn normalize(&mut self, range: [f32; 2]) {
+        let min = self.get_min();
+        let max = self.get_max();
+        let factor = if max > min {
+            (range[1] - range[0]) / (max - min)
+        } else {
+            0.
+        };
+        self.map_heights(|_, height| range[0] + (height - min) * factor);
+    }
+
+    pub fn clamp(&mut self, range: [f32; 2]) {
+        self.map_heights(|_, height| height.max(range[0]).min(range[1]));
+    }
+
+    /// Blends the other map in where the mask is 1, keeps the heights where it's 0.
+    /// All maps need the same size.
+    pub fn blend(&mut self, other: &HeightMap, mask: &AnalysisMap) {
+        debug_assert!(other.get_size() == self.get_size() && mask.get_size() == self.get_size());
+        self.map_heights(|index, height| {
+            let weight = mask.get_list()[index].max(0.).min(1.);
+            height + (other.get_by_index(index) - height) * weight
+        });
+    }
+
+    /// Position of the stamp center in world units, relative to the map origin
+    pub fn apply_stamp(&mut self, stamp: &Stamp, pos: Point2f) {
+        let size = self.get_size();
+        let scale_factor = self.get_scale_factor();
+        let radius = stamp.get_extent() * stamp.scale * f32::sqrt(2.) / 2.;
+        let min = ((pos - Point2f::from_scalar(radius)) / scale_factor)
+            .apply(|v| (v.floor() as i32).max(0).min(size - 1));
+        let max = ((pos + Point2f::from_scalar(radius)) / scale_factor)
+            .apply(|v| (v.ceil() as i32).max(0).min(size - 1));
+        for y in min[1]..max[1] + 1 {
+            for x in min[0]..max[0] + 1 {
+                let grid_pos = Point2i::new(x, y);
+                let offset = Point2f::from(grid_pos) * scale_factor - pos;
+                if let Some(stamp_height) = stamp.sample(offset) {
+                    let height = stamp.blend(self.get(grid_pos), stamp_height);
+                    self.set(grid_pos, height);
+                }
+            }
+        }
+        self.update_normals_from_heights();
+    }
+
+    fn convolve_separable(&mut self, kernel: &[f32]) {
+        let radius = (kernel.len() / 2) as i32;
+        for direction in [Point2i::new(1, 0), Point2i::new(0, 1)].iter() {
+            let source = self.clone();
+            self.map_heights(|index, _| {
+                let pos = get_grid_pos(index, source.get_size());
+                kernel.iter().enumerate().fold(0., |acc, (i, weight)| {
+                    let offset = *direction * (i as i32 - radius);
+                    acc + weight * source.get_grid_height(pos + offset)
+                })
+            });
+        }
+    }
+
+    fn filter_window<F: Fn(&mut Vec<f32>) -> f32>(&mut self, radius: i32, filter: F) {
+        debug_assert!(radius >= 0);
+        if radius <= 0 {
+            return;
+        }
+        let source = self.clone();
+        let mut window = Vec::with_capacity(((2 * radius + 1) * (2 * radius + 1)) as usize);
+        self.map_heights(|index, _| {
+            let pos = get_grid_pos(index, source.get_size());
+            window.clear();
+            for dy in -radius..radius + 1 {
+                for dx in -radius..radius + 1 {
+                    window.push(source.get_grid_height(pos + Point2i::new(dx, dy)));
+                }
+            }
+            filter(&mut window)
+        });
+    }
+
+    // Replaces every height by the result of the function, called with index and height
+    fn map_heights<F: FnMut(usize, f32) -> f32>(&mut self, mut f: F) {
+        for index in 0..self.get_list().len() {
+            let height = f(index, self.get_by_index(index));
+            self.set_by_index(index, height);
+        }
+        self.update_normals_from_heights();
+    }
+}
+
+fn get_grid_pos(index: usize, size: i32) -> Point2i {
+    Point2i::new(index as i32 % size, index as i32 / size)
+}
+
+// A sigma of zero doesn't blur at all
+fn get_gaussian_kernel(sigma: f32) -> Vec<f32> {
+    debug_assert!(sigma >= 0.);
+    if sigma <= 0. {
+        return vec![1.];
+    }
+    let radius = (3. * sigma).ceil() as i32;
+    let weights: Vec<f32> = (-radius..radius + 1)
+        .map(|i| (-(i * i) as f32 / (2. * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = weights.iter().sum();
+    weights.into_iter().map(|w| w / sum).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn create_spike(size: i32) -> HeightMap {
+        let mut hm = HeightMap::new(size, 1.);
+        hm.set(Point2i::from_scalar(size / 2), 10.);
+        hm
+    }
+
+    #[test]
+    fn test_blur_keeps_volume() {
+        let mut hm = create_spike(9);
+        hm.blur_gaussian(1.);
+        let center = hm.get(Point2i::from_scalar(4));
+        assert!(center < 10. && center > 0.);
+        assert!((hm.get_list().iter().sum::<f32>() - 10.).abs() < 1e-3);
+        assert!(hm.get_normal(Point2i::new(3, 4))[0] > 0.);
+    }
+
+    #[test]
+    fn test_zero_radius_keeps_heights() {
+        let spike = create_spike(9);
+        let mut hm = spike.clone();
+        hm.blur_box(0);
+        hm.blur_gaussian(0.);
+        hm.sharpen(0., 1.);
+        hm.median(0);
+        assert_eq!(spike.get_list(), hm.get_list());
+    }
+
+    #[test]
+    fn test_median_removes_spike() {
+        let mut hm = create_spike(5);
+        hm.median(1);
+        assert!(hm.get_list().iter().all(|h| *h == 0.));
+    }
+
+    #[test]
+    fn test_erode_dilate() {
+        let mut hm = create_spike(5);
+        hm.dilate(1);
+        assert_eq!(10., hm.get(Point2i::new(1, 1)));
+        assert_eq!(0., hm.get(Point2i::new(0, 0)));
+        hm.erode(1);
+        assert_eq!(10., hm.get(Point2i::new(2, 2)));
+        assert_eq!(0., hm.get(Point2i::new(1, 1)));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut hm = create_spike(3);
+        hm.normalize([-1., 1.]);
+        assert_eq!(-1., hm.get_min());
+        assert_eq!(1., hm.get_max());
+    }
+
+    #[test]
+    fn test_stamp_rotated() {
+        // ramp rising along x, rotated to rise along y
+        let mut ramp = HeightMap::new(5, 1.);
+        for y in 0..5 {
+            for x in 0..5 {
+                ramp.set(Point2i::new(x, y), x as f32);
+            }
+        }
+        let mut hm = HeightMap::new(9, 1.);
+        let stamp = Stamp::new(&ramp)
+            .with_rotation(PI / 2.)
+            .with_blend_mode(BlendMode::Add);
+        hm.apply_stamp(&stamp, Point2f::from_scalar(4.));
+        assert!((hm.get(Point2i::new(4, 2)) - 0.).abs() < 1e-4);
+        assert!((hm.get(Point2i::new(4, 6)) - 4.).abs() < 1e-4);
+        assert!((hm.get(Point2i::new(2, 5)) - 3.).abs() < 1e-4);
+        assert_eq!(0., hm.get(Point2i::new(0, 8)));
+    }
+}
@@ -1,9 +1,14 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::cmp::Ordering;
 use std::convert::TryInto;
+use std::io::{Error, ErrorKind, Read, Write};
 
-use crate::{chunk::ChunkError, triangulate, Noise};
+use crate::height_pyramid::{HeightLevel, HeightPyramid};
+use crate::chunk::world_layout::MAX_GRID_SIZE;
+use crate::{chunk::ChunkError, triangulate, HeightSampler, Noise, Rtin};
 use core::graphics::mesh::Triangle;
-use core::{GraphicsError, Mesh, Point2f, Point2i, Point3f, Texture, TextureBuilder};
+use core::traits::{Loadable, Saveable};
+use core::{GraphicsError, Mesh, Point2d, Point2f, Point2i, Point3f, Texture, TextureBuilder};
 
 #[derive(Clone)]
 pub struct HeightMap {
@@ -12,6 +17,7 @@ pub struct HeightMap {
     origin: Point2f,
     height_list: Vec<f32>,
     normal_list: Vec<Point3f>,
+    pyramid: Option<HeightPyramid>,
 }
 
 impl HeightMap {
@@ -27,6 +33,7 @@ impl HeightMap {
             origin: Point2f::from_scalar(0.),
             height_list: height_list,
             normal_list: normal_list,
+            pyramid: None,
         }
     }
 
@@ -36,9 +43,7 @@ impl HeightMap {
         let mut height_list = Vec::with_capacity((size * size) as usize);
         for y in 0..size {
             for x in 0..size {
-                let h = noise.get_noise(
-                    origin + Point2f::new(x as f32 * scale_factor, y as f32 * scale_factor),
-                );
+                let h = noise.get_noise_precise(get_sample_pos(origin, scale_factor, x, y));
                 height_list.push(h);
             }
         }
@@ -51,94 +56,57 @@ impl HeightMap {
             origin: origin,
             height_list: height_list,
             normal_list: normal_list,
+            pyramid: None,
         };
         hm.update_normals(noise);
         hm
     }
 
-    fn update_normals(&mut self, fallback_noise: &dyn Noise) {
+    /// Normals on the border use the noise for the heights outside of the map
+    pub fn update_normals(&mut self, fallback_noise: &dyn Noise) {
         for y in 0..self.size {
             for x in 0..self.size {
                 let r = if x + 1 == self.size {
-                    fallback_noise.get_noise(
-                        Point2f::new(
-                            (x + 1) as f32 * self.scale_factor,
-                            y as f32 * self.scale_factor,
-                        ) + self.origin,
-                    )
+                    fallback_noise.get_noise_precise(self.get_sample_pos(x + 1, y))
                 } else {
                     self.get(Point2i::new(x + 1, y))
                 };
                 let l = if x == 0 {
-                    fallback_noise.get_noise(
-                        Point2f::new(
-                            (x - 1) as f32 * self.scale_factor,
-                            y as f32 * self.scale_factor,
-                        ) + self.origin,
-                    )
+                    fallback_noise.get_noise_precise(self.get_sample_pos(x - 1, y))
                 } else {
                     self.get(Point2i::new(x - 1, y))
                 };
                 let b = if y + 1 == self.size {
-                    fallback_noise.get_noise(
-                        Point2f::new(
-                            x as f32 * self.scale_factor,
-                            (y + 1) as f32 * self.scale_factor,
-                        ) + self.origin,
-                    )
+                    fallback_noise.get_noise_precise(self.get_sample_pos(x, y + 1))
                 } else {
                     self.get(Point2i::new(x, y + 1))
                 };
                 let t = if y == 0 {
-                    fallback_noise.get_noise(
-                        Point2f::new(
-                            x as f32 * self.scale_factor,
-                            (y - 1) as f32 * self.scale_factor,
-                        ) + self.origin,
-                    )
+                    fallback_noise.get_noise_precise(self.get_sample_pos(x, y - 1))
                 } else {
                     self.get(Point2i::new(x, y - 1))
                 };
 
-                let normal = Point3f::new((r - l) / (2.), (b - t) / (2. * self.scale_factor), 1.)
-                    .as_normalized();
+                let normal = Point3f::new(
+                    (r - l) / (2. * self.scale_factor),
+                    (b - t) / (2. * self.scale_factor),
+                    1.,
+                )
+                .as_normalized();
                 self.set_normal(Point2i::new(x, y), normal);
             }
         }
     }
 
-    pub fn get_interpolated_height(&self, relative_pos: Point2f) -> f32 {
-        let root_pos = Point2i::new(
-            clamp(
-                (relative_pos[0].floor() / self.scale_factor) as i32,
-                0,
-                self.size - 1,
-            ),
-            clamp(
-                (relative_pos[1].floor() / self.scale_factor) as i32,
-                0,
-                self.size - 1,
-            ),
-        );
-        let reference_height: [f32; 4] = [
-            self.get(root_pos),
-            self.get(Point2i::new(
-                i32::min(root_pos[0] + 1, self.size - 1),
-                root_pos[1],
-            )),
-            self.get(Point2i::new(
-                root_pos[0],
-                i32::min(root_pos[1] + 1, self.size - 1),
-            )),
-            self.get(Point2i::new(
-                i32::min(root_pos[0] + 1, self.size - 1),
-                i32::min(root_pos[1] + 1, self.size - 1),
-            )),
-        ];
-        let relative_point = relative_pos - Point2f::from(root_pos);
-
-        let res = interpolate(relative_point, reference_height);
-        res
+    /// Normals from the heights of the map only, border heights are repeated outwards
+    pub fn update_normals_from_heights(&mut self) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let pos = Point2i::new(x, y);
+                let normal = self.get_grid_normal(pos);
+                self.set_normal(pos, normal);
+            }
+        }
     }
 
     pub fn triangulate(&self) -> Option<Vec<Triangle>> {
@@ -167,16 +135,81 @@ impl HeightMap {
         }
     }
 
+    pub fn triangulate_adaptive(&self, max_error: f32, skirt_depth: f32) -> Option<Vec<Triangle>> {
+        Rtin::new(self).map(|rtin| rtin.triangulate(max_error, skirt_depth))
+    }
+
     #[allow(unused)]
     pub fn get_size(&self) -> i32 {
         self.size
     }
 
+    pub fn get_scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    pub fn get_origin(&self) -> Point2f {
+        self.origin
+    }
+
     pub fn get_list(&self) -> &[f32] {
         self.height_list.as_slice()
     }
 
+    /// Builds the mip pyramid of the current heights, it's dropped on the next change
+    pub fn build_pyramid(&mut self) {
+        self.pyramid = Some(HeightPyramid::from_heightmap(self));
+    }
+
+    pub fn get_pyramid(&self) -> Option<&HeightPyramid> {
+        self.pyramid.as_ref()
+    }
+
+    /// Heights at the level of detail, none without a pyramid
+    pub fn get_level(&self, lod: usize) -> Option<&HeightLevel> {
+        self.pyramid.as_ref().map(|pyramid| pyramid.get_level(lod))
+    }
+
+    /// Bilinear sample of the averaged heights at the level of detail, the full resolution
+    /// without a pyramid. Positions are in world units relative to the map origin.
+    pub fn sample_lod(&self, pos: Point2f, lod: usize) -> f32 {
+        match self.get_level(lod) {
+            Some(level) => level.sample_bilinear(pos),
+            None => self.sample_bilinear(pos),
+        }
+    }
+
+    /// Heightmap of the averaged heights at the level of detail, none without a pyramid.
+    /// Normals are taken from the full resolution.
+    pub fn get_reduced(&self, lod: usize) -> Option<HeightMap> {
+        let level = self.get_level(lod)?;
+        let size = level.get_cell_count() + 1;
+        let step = (level.get_grid_spacing() / self.scale_factor).round() as i32;
+        let mut height_list = Vec::with_capacity((size * size) as usize);
+        let mut normal_list = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                height_list.push(level.get_grid_height(Point2i::new(x, y)));
+                normal_list.push(self.get_normal(Point2i::new(
+                    (x * step).min(self.size - 1),
+                    (y * step).min(self.size - 1),
+                )));
+            }
+        }
+        Some(Self {
+            size: size,
+            scale_factor: level.get_grid_spacing(),
+            origin: self.origin,
+            height_list: height_list,
+            normal_list: normal_list,
+            pyramid: None,
+        })
+    }
+
     pub fn get_min(&self) -> f32 {
+        if let Some(pyramid) = &self.pyramid {
+            return pyramid.get_range()[0];
+        }
         match self.height_list.iter().min_by(|a, b| {
             if a > b {
                 Ordering::Greater
@@ -192,6 +225,9 @@ impl HeightMap {
     }
 
     pub fn get_max(&self) -> f32 {
+        if let Some(pyramid) = &self.pyramid {
+            return pyramid.get_range()[1];
+        }
         match self.height_list.iter().max_by(|a, b| {
             if a > b {
                 Ordering::Greater
@@ -209,6 +245,7 @@ impl HeightMap {
     pub fn set(&mut self, pos: Point2i, height: f32) {
         let index = self.calculate_index(pos);
         self.height_list[index] = height;
+        self.pyramid = None;
     }
 
     pub fn get(&self, pos: Point2i) -> f32 {
@@ -221,9 +258,24 @@ impl HeightMap {
         let index = self.calculate_index(pos);
         self.normal_list[index] = normal;
     }
+    /// Writes heights and normals into an existing texture of matching size
+    pub fn write_texture(&self, texture: &Texture) -> Result<(), GraphicsError> {
+        let height_normal_list: Vec<f32> = self
+            .height_list
+            .iter()
+            .zip(self.normal_list.iter())
+            .fold(Vec::new(), |mut acc, (h, n)| {
+                acc.extend(&[*h, n[0], n[1], n[2]]);
+                acc
+            });
+        texture.write_data(height_normal_list.as_slice())?;
+        Ok(())
+    }
+
     #[allow(unused)]
     pub fn set_by_index(&mut self, index: usize, height: f32) {
         self.height_list[index] = height;
+        self.pyramid = None;
     }
 
     #[allow(unused)]
@@ -246,6 +298,88 @@ impl HeightMap {
         debug_assert!(pos[0] >= 0 && pos[1] >= 0);
         ((pos[0] % self.size) + self.size * (pos[1] % self.size)) as usize
     }
+
+    fn get_sample_pos(&self, x: i32, y: i32) -> Point2d {
+        get_sample_pos(self.origin, self.scale_factor, x, y)
+    }
+}
+
+// World position of a grid point, in double precision so grid points stay apart far from the origin
+fn get_sample_pos(origin: Point2f, scale_factor: f32, x: i32, y: i32) -> Point2d {
+    Point2d::from(origin) + Point2d::new(x as f64, y as f64) * scale_factor as f64
+}
+
+impl HeightSampler for HeightMap {
+    fn get_grid_height(&self, grid_pos: Point2i) -> f32 {
+        self.get(Point2i::new(
+            grid_pos[0].max(0).min(self.size - 1),
+            grid_pos[1].max(0).min(self.size - 1),
+        ))
+    }
+
+    fn get_grid_spacing(&self) -> f32 {
+        self.scale_factor
+    }
+}
+
+impl Saveable for HeightMap {
+    fn save(&self, writer: &mut impl Write) -> Result<(), Error> {
+        writer.write_i32::<LittleEndian>(self.size)?;
+        writer.write_f32::<LittleEndian>(self.scale_factor)?;
+        writer.write_f32::<LittleEndian>(self.origin[0])?;
+        writer.write_f32::<LittleEndian>(self.origin[1])?;
+        for height in self.height_list.iter() {
+            writer.write_f32::<LittleEndian>(*height)?;
+        }
+        for normal in self.normal_list.iter() {
+            for i in 0..3 {
+                writer.write_f32::<LittleEndian>(normal[i])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Loadable for HeightMap {
+    fn load(&mut self, reader: &mut impl Read) -> Result<(), Error> {
+        let size = read_map_size(reader, "heightmap")?;
+        let scale_factor = reader.read_f32::<LittleEndian>()?;
+        let origin = Point2f::new(
+            reader.read_f32::<LittleEndian>()?,
+            reader.read_f32::<LittleEndian>()?,
+        );
+        let mut height_list = Vec::with_capacity((size * size) as usize);
+        for _ in 0..size * size {
+            height_list.push(reader.read_f32::<LittleEndian>()?);
+        }
+        let mut normal_list = Vec::with_capacity((size * size) as usize);
+        for _ in 0..size * size {
+            normal_list.push(Point3f::new(
+                reader.read_f32::<LittleEndian>()?,
+                reader.read_f32::<LittleEndian>()?,
+                reader.read_f32::<LittleEndian>()?,
+            ));
+        }
+        self.size = size;
+        self.scale_factor = scale_factor;
+        self.origin = origin;
+        self.height_list = height_list;
+        self.normal_list = normal_list;
+        self.pyramid = None;
+        Ok(())
+    }
+}
+
+// Rejects sizes no chunk can have before anything is allocated, so corrupt files fail early
+pub(crate) fn read_map_size(reader: &mut impl Read, name: &str) -> Result<i32, Error> {
+    let size = reader.read_i32::<LittleEndian>()?;
+    if size <= 0 || size > MAX_GRID_SIZE {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid {} size: {}", name, size),
+        ));
+    }
+    Ok(size)
 }
 
 impl TryInto<Texture> for HeightMap {
@@ -254,15 +388,7 @@ impl TryInto<Texture> for HeightMap {
         let texture = TextureBuilder::new_2d(Point2i::from_scalar(self.size))
             .format_rgba32f()
             .finish()?;
-        let height_normal_list: Vec<f32> = self
-            .height_list
-            .iter()
-            .zip(self.normal_list.iter())
-            .fold(Vec::new(), |mut acc, (h, n)| {
-                acc.extend(&[*h, n[0], n[1], n[2]]);
-                acc
-            });
-        texture.write_data(height_normal_list.as_slice())?;
+        self.write_texture(&texture)?;
         Ok(texture)
     }
 }
@@ -276,29 +402,51 @@ impl TryInto<Mesh> for HeightMap {
     }
 }
 
-fn interpolate(p: Point2f, reference: [f32; 4]) -> f32 {
-    let anchor = [p[0].floor() as i32, p[1].floor() as i32];
-    let a = anchor[0] as f32 + 1. - p[0];
-    let b = p[0] - anchor[0] as f32;
-    let r_1 = a * reference[0] + b * reference[1];
-    let r_2 = a * reference[2] + b * reference[3];
-    let c = anchor[1] as f32 + 1. - p[1];
-    let d = p[1] - anchor[1] as f32;
-    c * r_1 + d * r_2
-}
-
-fn clamp<T>(value: T, min: T, max: T) -> T
-where
-    T: Ord,
-{
-    T::min(T::max(value, min), max)
-}
-
 #[cfg(test)]
 mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_sampling_with_scale_factor() {
+        let mut hm = HeightMap::new(4, 2.);
+        for y in 0..4 {
+            for x in 0..4 {
+                hm.set(Point2i::new(x, y), x as f32);
+            }
+        }
+        assert_eq!(1.5, hm.sample_bilinear(Point2f::new(3., 5.)));
+        assert_eq!(3., hm.sample_bilinear(Point2f::new(6., 6.)));
+        assert_eq!(1.5, hm.sample_bicubic(Point2f::new(3., 1.)));
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_size() {
+        for size in &[0, MAX_GRID_SIZE + 1, i32::MAX] {
+            let mut buffer = Vec::new();
+            buffer.write_i32::<LittleEndian>(*size).unwrap();
+            let mut hm = HeightMap::new(1, 1.);
+            assert!(hm.load(&mut buffer.as_slice()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_reduced_keeps_extent() {
+        let mut hm = HeightMap::new(9, 1.);
+        for y in 0..9 {
+            for x in 0..9 {
+                hm.set(Point2i::new(x, y), x as f32);
+            }
+        }
+        assert!(hm.get_reduced(1).is_none());
+        hm.build_pyramid();
+        let reduced = hm.get_reduced(2).unwrap();
+        assert_eq!(3, reduced.get_size());
+        assert_eq!(4., reduced.get_scale_factor());
+        assert_eq!(4., reduced.get(Point2i::new(1, 2)));
+        assert!(reduced.get_reduced(1).is_none());
+    }
+
     #[test]
     fn check_heightmap_triangulation() {
         let hm = HeightMap::new(16, 1.);
@@ -0,0 +1,297 @@
+use crate::{HeightMap, HeightSampler};
+use core::{Point2f, Point2i, Point3f};
+
+/// One level of a height pyramid. Cells cover 2^level grid cells of the heightmap,
+/// averages are given on the cell corners.
+#[derive(Clone)]
+pub struct HeightLevel {
+    cell_count: i32,
+    spacing: f32,
+    min_list: Vec<f32>,
+    max_list: Vec<f32>,
+    avg_list: Vec<f32>,
+}
+
+impl HeightLevel {
+    fn from_heightmap(heightmap: &HeightMap) -> Self {
+        let cell_count = heightmap.get_size() - 1;
+        let mut min_list = Vec::with_capacity((cell_count * cell_count) as usize);
+        let mut max_list = Vec::with_capacity((cell_count * cell_count) as usize);
+        for y in 0..cell_count {
+            for x in 0..cell_count {
+                let corners = [
+                    heightmap.get(Point2i::new(x, y)),
+                    heightmap.get(Point2i::new(x + 1, y)),
+                    heightmap.get(Point2i::new(x, y + 1)),
+                    heightmap.get(Point2i::new(x + 1, y + 1)),
+                ];
+                min_list.push(corners.iter().cloned().fold(f32::MAX, f32::min));
+                max_list.push(corners.iter().cloned().fold(f32::MIN, f32::max));
+            }
+        }
+        Self {
+            cell_count: cell_count,
+            spacing: heightmap.get_scale_factor(),
+            min_list: min_list,
+            max_list: max_list,
+            avg_list: heightmap.get_list().to_vec(),
+        }
+    }
+
+    // Halves the resolution, ranges cover all child cells, averages are tent filtered
+    fn from_level(level: &HeightLevel) -> Self {
+        let cell_count = (level.cell_count + 1) / 2;
+        let mut min_list = Vec::with_capacity((cell_count * cell_count) as usize);
+        let mut max_list = Vec::with_capacity((cell_count * cell_count) as usize);
+        for y in 0..cell_count {
+            for x in 0..cell_count {
+                let mut range = [f32::MAX, f32::MIN];
+                for child in [[0, 0], [1, 0], [0, 1], [1, 1]].iter() {
+                    let child_cell = Point2i::new(2 * x + child[0], 2 * y + child[1]);
+                    if child_cell[0] < level.cell_count && child_cell[1] < level.cell_count {
+                        let child_range = level.get_cell_range(child_cell);
+                        range = [range[0].min(child_range[0]), range[1].max(child_range[1])];
+                    }
+                }
+                min_list.push(range[0]);
+                max_list.push(range[1]);
+            }
+        }
+        const WEIGHTS: [f32; 3] = [0.25, 0.5, 0.25];
+        let mut avg_list = Vec::with_capacity(((cell_count + 1) * (cell_count + 1)) as usize);
+        for y in 0..cell_count + 1 {
+            for x in 0..cell_count + 1 {
+                let mut avg = 0.;
+                for (j, weight_y) in WEIGHTS.iter().enumerate() {
+                    for (i, weight_x) in WEIGHTS.iter().enumerate() {
+                        let pos = Point2i::new(2 * x + i as i32 - 1, 2 * y + j as i32 - 1);
+                        avg += weight_x * weight_y * level.get_grid_height(pos);
+                    }
+                }
+                avg_list.push(avg);
+            }
+        }
+        Self {
+            cell_count: cell_count,
+            spacing: level.spacing * 2.,
+            min_list: min_list,
+            max_list: max_list,
+            avg_list: avg_list,
+        }
+    }
+
+    /// Cells along each axis
+    pub fn get_cell_count(&self) -> i32 {
+        self.cell_count
+    }
+
+    /// Lowest and highest height within the cell
+    pub fn get_cell_range(&self, cell: Point2i) -> [f32; 2] {
+        debug_assert!(cell[0] >= 0 && cell[1] >= 0);
+        debug_assert!(cell[0] < self.cell_count && cell[1] < self.cell_count);
+        let index = (cell[0] + cell[1] * self.cell_count) as usize;
+        [self.min_list[index], self.max_list[index]]
+    }
+
+    /// Conservative height range within the rectangle, given in world units relative to the map origin
+    pub fn get_range_in(&self, min: Point2f, max: Point2f) -> [f32; 2] {
+        let first = self.get_cell(min);
+        let last = self.get_cell(max);
+        let mut range = [f32::MAX, f32::MIN];
+        for y in first[1]..last[1] + 1 {
+            for x in first[0]..last[0] + 1 {
+                let cell_range = self.get_cell_range(Point2i::new(x, y));
+                range = [range[0].min(cell_range[0]), range[1].max(cell_range[1])];
+            }
+        }
+        range
+    }
+
+    // Box around the terrain within the cell, relative to the map origin
+    fn get_cell_bounds(&self, cell: Point2i) -> (Point3f, Point3f) {
+        let range = self.get_cell_range(cell);
+        let min = Point2f::from(cell) * self.spacing;
+        let max = Point2f::from(cell + Point2i::from_scalar(1)) * self.spacing;
+        (min.extend(range[0]), max.extend(range[1]))
+    }
+
+    fn get_cell(&self, pos: Point2f) -> Point2i {
+        (pos / self.spacing).apply(|v| (v.floor() as i32).max(0).min(self.cell_count - 1))
+    }
+}
+
+impl HeightSampler for HeightLevel {
+    fn get_grid_height(&self, grid_pos: Point2i) -> f32 {
+        let size = self.cell_count + 1;
+        let x = grid_pos[0].max(0).min(size - 1);
+        let y = grid_pos[1].max(0).min(size - 1);
+        self.avg_list[(x + y * size) as usize]
+    }
+
+    fn get_grid_spacing(&self) -> f32 {
+        self.spacing
+    }
+}
+
+/// Min, max and average heights of a heightmap at halving resolutions,
+/// from the full resolution at level 0 up to a single cell
+#[derive(Clone)]
+pub struct HeightPyramid {
+    level_list: Vec<HeightLevel>,
+}
+
+impl HeightPyramid {
+    pub fn from_heightmap(heightmap: &HeightMap) -> Self {
+        debug_assert!(heightmap.get_size() > 1);
+        let mut level_list = vec![HeightLevel::from_heightmap(heightmap)];
+        while level_list[level_list.len() - 1].get_cell_count() > 1 {
+            let level = HeightLevel::from_level(&level_list[level_list.len() - 1]);
+            level_list.push(level);
+        }
+        Self {
+            level_list: level_list,
+        }
+    }
+
+    pub fn get_level_count(&self) -> usize {
+        self.level_list.len()
+    }
+
+    /// Levels above the coarsest one return the coarsest one
+    pub fn get_level(&self, lod: usize) -> &HeightLevel {
+        &self.level_list[lod.min(self.level_list.len() - 1)]
+    }
+
+    /// Lowest and highest height of the whole map
+    pub fn get_range(&self) -> [f32; 2] {
+        self.level_list[self.level_list.len() - 1].get_cell_range(Point2i::new(0, 0))
+    }
+
+    /// Distance along the ray at which it enters the bounds of the first full resolution cell
+    /// it hits. The terrain can't be hit earlier, none if it can't be hit at all.
+    /// The ray is given in world units relative to the map origin.
+    pub fn intersect_ray(&self, origin: Point3f, direction: Point3f) -> Option<f32> {
+        self.intersect_cell(
+            self.level_list.len() - 1,
+            Point2i::new(0, 0),
+            origin,
+            direction,
+        )
+    }
+
+    fn intersect_cell(
+        &self,
+        lod: usize,
+        cell: Point2i,
+        origin: Point3f,
+        direction: Point3f,
+    ) -> Option<f32> {
+        let (min, max) = self.level_list[lod].get_cell_bounds(cell);
+        let distance = intersect_box(min, max, origin, direction)?;
+        if lod == 0 {
+            return Some(distance);
+        }
+
+        let child_level = &self.level_list[lod - 1];
+        let mut children: Vec<(f32, Point2i)> = Vec::with_capacity(4);
+        for child in [[0, 0], [1, 0], [0, 1], [1, 1]].iter() {
+            let child_cell = Point2i::new(2 * cell[0] + child[0], 2 * cell[1] + child[1]);
+            if child_cell[0] < child_level.cell_count && child_cell[1] < child_level.cell_count {
+                let (child_min, child_max) = child_level.get_cell_bounds(child_cell);
+                if let Some(child_distance) = intersect_box(child_min, child_max, origin, direction)
+                {
+                    children.push((child_distance, child_cell));
+                }
+            }
+        }
+        // nearest children first, later children can only improve on a hit if entered before it
+        children.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        let mut nearest: Option<f32> = None;
+        for (child_distance, child_cell) in children {
+            if nearest.map_or(false, |n| n <= child_distance) {
+                break;
+            }
+            if let Some(hit) = self.intersect_cell(lod - 1, child_cell, origin, direction) {
+                nearest = Some(nearest.map_or(hit, |n| n.min(hit)));
+            }
+        }
+        nearest
+    }
+}
+
+// Distance along the ray at which it enters the box, zero if it starts inside
+fn intersect_box(min: Point3f, max: Point3f, origin: Point3f, direction: Point3f) -> Option<f32> {
+    let mut near = 0.;
+    let mut far = f32::MAX;
+    for axis in 0..3 {
+        if direction[axis].abs() < 1e-9 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+        } else {
+            let a = (min[axis] - origin[axis]) / direction[axis];
+            let b = (max[axis] - origin[axis]) / direction[axis];
+            near = f32::max(near, a.min(b));
+            far = f32::min(far, a.max(b));
+        }
+    }
+    if near <= far {
+        Some(near)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_spike() -> HeightMap {
+        let mut hm = HeightMap::new(17, 2.);
+        hm.set(Point2i::new(12, 4), 10.);
+        hm.set(Point2i::new(3, 3), -5.);
+        hm
+    }
+
+    #[test]
+    fn test_ranges() {
+        let pyramid = HeightPyramid::from_heightmap(&create_spike());
+        assert_eq!(5, pyramid.get_level_count());
+        assert_eq!([-5., 10.], pyramid.get_range());
+        let level = pyramid.get_level(2);
+        assert_eq!(4, level.get_cell_count());
+        assert_eq!([0., 10.], level.get_cell_range(Point2i::new(2, 0)));
+        assert_eq!(
+            [0., 0.],
+            level.get_range_in(Point2f::new(16., 16.), Point2f::new(32., 32.))
+        );
+        assert_eq!(
+            [-5., 0.],
+            level.get_range_in(Point2f::new(0., 0.), Point2f::new(7., 7.))
+        );
+    }
+
+    #[test]
+    fn test_coarse_levels_keep_average() {
+        let mut hm = HeightMap::new(9, 1.);
+        hm.normalize([3., 3.]);
+        let pyramid = HeightPyramid::from_heightmap(&hm);
+        let level = pyramid.get_level(2);
+        assert_eq!(4., level.get_grid_spacing());
+        assert_eq!(3., level.sample_bilinear(Point2f::new(5., 1.)));
+    }
+
+    #[test]
+    fn test_ray_intersection() {
+        let pyramid = HeightPyramid::from_heightmap(&create_spike());
+        // straight down onto the spike, entering the bounds of the cells around it at height 10
+        let hit = pyramid.intersect_ray(Point3f::new(24., 8., 20.), Point3f::new(0., 0., -1.));
+        assert_eq!(Some(10.), hit);
+        // passing above the spike
+        let miss = pyramid.intersect_ray(Point3f::new(-1., 8., 11.), Point3f::new(1., 0., 0.));
+        assert_eq!(None, miss);
+        // grazing the flat terrain
+        let flat = pyramid.intersect_ray(Point3f::new(-4., 30., 1.), Point3f::new(1., 0., -0.1));
+        assert!((flat.unwrap() - 10.).abs() < 1e-4);
+    }
+}
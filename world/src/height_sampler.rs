@@ -0,0 +1,233 @@
+use core::{Point2f, Point2i, Point3f};
+
+/// Sampling of a regular height grid at arbitrary positions.
+/// Positions are given in world units relative to the grid origin,
+/// grid points lie at multiples of the grid spacing.
+pub trait HeightSampler {
+    /// Implementors decide how positions outside of their grid are handled
+    fn get_grid_height(&self, grid_pos: Point2i) -> f32;
+    fn get_grid_spacing(&self) -> f32;
+
+    /// False where the height is only a placeholder, like in chunks that aren't loaded
+    fn has_grid_height(&self, _grid_pos: Point2i) -> bool {
+        true
+    }
+
+    fn sample_bilinear(&self, pos: Point2f) -> f32 {
+        let (anchor, t) = get_anchor(pos, self.get_grid_spacing());
+        let heights = [
+            self.get_grid_height(anchor),
+            self.get_grid_height(anchor + Point2i::new(1, 0)),
+            self.get_grid_height(anchor + Point2i::new(0, 1)),
+            self.get_grid_height(anchor + Point2i::new(1, 1)),
+        ];
+        let bottom = heights[0] * (1. - t[0]) + heights[1] * t[0];
+        let top = heights[2] * (1. - t[0]) + heights[3] * t[0];
+        bottom * (1. - t[1]) + top * t[1]
+    }
+
+    fn sample_bicubic(&self, pos: Point2f) -> f32 {
+        let (anchor, t) = get_anchor(pos, self.get_grid_spacing());
+        sample_cubic(self, anchor, cubic_weights(t[0]), cubic_weights(t[1]))
+    }
+
+    /// Partial derivatives of the bicubic surface, in height per world unit
+    fn sample_gradient(&self, pos: Point2f) -> Point2f {
+        let (anchor, t) = get_anchor(pos, self.get_grid_spacing());
+        let dx = sample_cubic(
+            self,
+            anchor,
+            cubic_derivative_weights(t[0]),
+            cubic_weights(t[1]),
+        );
+        let dy = sample_cubic(
+            self,
+            anchor,
+            cubic_weights(t[0]),
+            cubic_derivative_weights(t[1]),
+        );
+        Point2f::new(dx, dy) / self.get_grid_spacing()
+    }
+
+    /// Angle between surface and the xy-plane, in radians
+    fn sample_slope(&self, pos: Point2f) -> f32 {
+        self.sample_gradient(pos).length().atan()
+    }
+
+    /// Direction of steepest descent, in radians counter-clockwise from the x-axis.
+    /// Flat surfaces have an aspect of zero.
+    fn sample_aspect(&self, pos: Point2f) -> f32 {
+        let gradient = self.sample_gradient(pos);
+        if gradient.length() < 1e-6 {
+            0.
+        } else {
+            f32::atan2(-gradient[1], -gradient[0])
+        }
+    }
+
+    /// Surface normal at a grid point, from central differences of the neighbouring grid points
+    fn get_grid_normal(&self, grid_pos: Point2i) -> Point3f {
+        let r = self.get_grid_height(grid_pos + Point2i::new(1, 0));
+        let l = self.get_grid_height(grid_pos - Point2i::new(1, 0));
+        let b = self.get_grid_height(grid_pos + Point2i::new(0, 1));
+        let t = self.get_grid_height(grid_pos - Point2i::new(0, 1));
+        let spacing = self.get_grid_spacing();
+        Point3f::new((r - l) / (2. * spacing), (b - t) / (2. * spacing), 1.).as_normalized()
+    }
+
+    /// Laplacian of the bicubic surface, positive in valleys and negative on ridges
+    fn sample_curvature(&self, pos: Point2f) -> f32 {
+        let (anchor, t) = get_anchor(pos, self.get_grid_spacing());
+        let dxx = sample_cubic(
+            self,
+            anchor,
+            cubic_second_weights(t[0]),
+            cubic_weights(t[1]),
+        );
+        let dyy = sample_cubic(
+            self,
+            anchor,
+            cubic_weights(t[0]),
+            cubic_second_weights(t[1]),
+        );
+        (dxx + dyy) / self.get_grid_spacing().powf(2.)
+    }
+}
+
+fn get_anchor(pos: Point2f, spacing: f32) -> (Point2i, Point2f) {
+    let grid_pos = pos / spacing;
+    let anchor = Point2i::new(grid_pos[0].floor() as i32, grid_pos[1].floor() as i32);
+    (anchor, grid_pos - Point2f::from(anchor))
+}
+
+fn sample_cubic<S: HeightSampler + ?Sized>(
+    sampler: &S,
+    anchor: Point2i,
+    weights_x: [f32; 4],
+    weights_y: [f32; 4],
+) -> f32 {
+    let mut sum = 0.;
+    for (y, wy) in weights_y.iter().enumerate() {
+        for (x, wx) in weights_x.iter().enumerate() {
+            let offset = Point2i::new(x as i32 - 1, y as i32 - 1);
+            sum += wx * wy * sampler.get_grid_height(anchor + offset);
+        }
+    }
+    sum
+}
+
+// Catmull-Rom spline weights for the samples at -1, 0, 1, 2 relative to the anchor
+fn cubic_weights(t: f32) -> [f32; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        0.5 * (-t + 2. * t2 - t3),
+        0.5 * (2. - 5. * t2 + 3. * t3),
+        0.5 * (t + 4. * t2 - 3. * t3),
+        0.5 * (-t2 + t3),
+    ]
+}
+
+fn cubic_derivative_weights(t: f32) -> [f32; 4] {
+    let t2 = t * t;
+    [
+        0.5 * (-1. + 4. * t - 3. * t2),
+        0.5 * (-10. * t + 9. * t2),
+        0.5 * (1. + 8. * t - 9. * t2),
+        0.5 * (-2. * t + 3. * t2),
+    ]
+}
+
+fn cubic_second_weights(t: f32) -> [f32; 4] {
+    [
+        0.5 * (4. - 6. * t),
+        0.5 * (-10. + 18. * t),
+        0.5 * (8. - 18. * t),
+        0.5 * (-2. + 6. * t),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::f32::consts;
+
+    struct PlaneSampler {
+        spacing: f32,
+        gradient: Point2f,
+    }
+
+    impl HeightSampler for PlaneSampler {
+        fn get_grid_height(&self, grid_pos: Point2i) -> f32 {
+            let p = Point2f::from(grid_pos) * self.spacing;
+            p[0] * self.gradient[0] + p[1] * self.gradient[1]
+        }
+        fn get_grid_spacing(&self) -> f32 {
+            self.spacing
+        }
+    }
+
+    struct BowlSampler;
+
+    impl HeightSampler for BowlSampler {
+        fn get_grid_height(&self, grid_pos: Point2i) -> f32 {
+            (grid_pos[0] * grid_pos[0] + grid_pos[1] * grid_pos[1]) as f32
+        }
+        fn get_grid_spacing(&self) -> f32 {
+            1.
+        }
+    }
+
+    fn assert_close(expected: f32, value: f32) {
+        assert!(
+            (expected - value).abs() < 1e-3,
+            "expected {}, got {}",
+            expected,
+            value
+        );
+    }
+
+    #[test]
+    fn test_interpolation_reproduces_plane() {
+        let sampler = PlaneSampler {
+            spacing: 3.,
+            gradient: Point2f::new(0.5, -2.),
+        };
+        let pos = Point2f::new(7.3, -4.1);
+        let expected = 7.3 * 0.5 + 4.1 * 2.;
+        assert_close(expected, sampler.sample_bilinear(pos));
+        assert_close(expected, sampler.sample_bicubic(pos));
+    }
+
+    #[test]
+    fn test_gradient_of_plane() {
+        let sampler = PlaneSampler {
+            spacing: 2.,
+            gradient: Point2f::new(1., 0.),
+        };
+        let gradient = sampler.sample_gradient(Point2f::new(3.5, 1.2));
+        assert_close(1., gradient[0]);
+        assert_close(0., gradient[1]);
+        assert_close(
+            consts::FRAC_PI_4,
+            sampler.sample_slope(Point2f::new(3.5, 1.2)),
+        );
+        assert_close(
+            consts::PI,
+            sampler.sample_aspect(Point2f::new(3.5, 1.2)).abs(),
+        );
+    }
+
+    #[test]
+    fn test_bowl_curvature_positive() {
+        assert_close(4., BowlSampler.sample_curvature(Point2f::new(0., 0.)));
+        assert!(BowlSampler.sample_curvature(Point2f::new(2.5, -1.5)) > 0.);
+    }
+
+    #[test]
+    fn test_interpolation_exact_at_grid_points() {
+        assert_close(25., BowlSampler.sample_bilinear(Point2f::new(3., 4.)));
+        assert_close(25., BowlSampler.sample_bicubic(Point2f::new(3., 4.)));
+    }
+}
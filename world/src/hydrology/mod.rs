@@ -0,0 +1,5 @@
+mod priority_flood;
+mod water_map;
+
+pub use self::priority_flood::priority_flood;
+pub use self::water_map::{WaterKind, WaterMap};
@@ -0,0 +1,121 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/*
+    Priority-flood depression filling, as described by
+    Barnes, Lehman & Mulla (2014). Cells on the border of the grid
+    and cells below the sea level drain off, every other cell is
+    raised to the lowest level at which water can spill out of it.
+*/
+
+pub fn priority_flood(heights: &[f32], size: i32, sea_level: f32) -> Vec<f32> {
+    debug_assert!(heights.len() == (size * size) as usize);
+    let mut filled = heights.to_vec();
+    let mut visited = Vec::new();
+    visited.resize(heights.len(), false);
+    let mut queue = BinaryHeap::new();
+
+    for y in 0..size {
+        for x in 0..size {
+            let index = (x + y * size) as usize;
+            let is_border = x == 0 || y == 0 || x == size - 1 || y == size - 1;
+            if is_border || heights[index] < sea_level {
+                filled[index] = f32::max(heights[index], sea_level);
+                visited[index] = true;
+                queue.push(FloodCell {
+                    level: filled[index],
+                    index: index,
+                });
+            }
+        }
+    }
+
+    while let Some(cell) = queue.pop() {
+        let x = cell.index as i32 % size;
+        let y = cell.index as i32 / size;
+        for dy in -1..2 {
+            for dx in -1..2 {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= size || ny >= size {
+                    continue;
+                }
+                let nb_index = (nx + ny * size) as usize;
+                if !visited[nb_index] {
+                    visited[nb_index] = true;
+                    filled[nb_index] = f32::max(heights[nb_index], cell.level);
+                    queue.push(FloodCell {
+                        level: filled[nb_index],
+                        index: nb_index,
+                    });
+                }
+            }
+        }
+    }
+    filled
+}
+
+// Ordered so that the binary heap yields the lowest level first
+struct FloodCell {
+    level: f32,
+    index: usize,
+}
+
+impl Ord for FloodCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .level
+            .partial_cmp(&self.level)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+impl PartialOrd for FloodCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for FloodCell {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FloodCell {}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_fill_pit() {
+        let heights = [
+            5., 5., 5., 5., //
+            5., 1., 2., 5., //
+            5., 2., 1., 3., //
+            5., 5., 5., 5., //
+        ];
+        let filled = priority_flood(&heights, 4, -10.);
+        assert_eq!(
+            vec![3., 3., 3., 3.],
+            vec![filled[5], filled[6], filled[9], filled[10]]
+        );
+        assert_eq!(5., filled[0]);
+        assert_eq!(3., filled[11]);
+    }
+
+    #[test]
+    fn test_below_sea_level_not_filled() {
+        let heights = [
+            5., 5., 5., 5., //
+            5., -2., 1., 5., //
+            5., 1., 1., 5., //
+            5., 5., 5., 5., //
+        ];
+        let filled = priority_flood(&heights, 4, 0.);
+        assert_eq!(0., filled[5]);
+        assert_eq!(1., filled[6]);
+    }
+}
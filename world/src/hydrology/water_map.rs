@@ -0,0 +1,334 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Error, ErrorKind, Read, Write};
+
+use crate::height_map::read_map_size;
+use core::graphics::mesh::{Triangle, Vertex};
+use core::traits::{Loadable, Saveable};
+use core::{Point2i, Point3f};
+
+use super::priority_flood;
+
+// Lakes shallower than this are considered dry, avoids puddles from numerical noise
+const MIN_LAKE_DEPTH: f32 = 1e-2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WaterKind {
+    Dry,
+    Lake,
+    Ocean,
+}
+
+/// Water mask and surface level per grid cell
+#[derive(Clone)]
+pub struct WaterMap {
+    size: i32,
+    scale_factor: f32,
+    kind_list: Vec<WaterKind>,
+    level_list: Vec<f32>,
+}
+
+impl WaterMap {
+    /// Map without any water
+    pub fn new(size: i32, scale_factor: f32) -> Self {
+        debug_assert!(size > 0);
+        Self {
+            size: size,
+            scale_factor: scale_factor,
+            kind_list: vec![WaterKind::Dry; (size * size) as usize],
+            level_list: vec![0.; (size * size) as usize],
+        }
+    }
+
+    pub fn from_heights(heights: &[f32], size: i32, scale_factor: f32, sea_level: f32) -> Self {
+        let filled = priority_flood(heights, size, sea_level);
+        let kind_list = heights
+            .iter()
+            .zip(filled.iter())
+            .map(|(h, f)| {
+                if *h < sea_level {
+                    WaterKind::Ocean
+                } else if f - h > MIN_LAKE_DEPTH {
+                    WaterKind::Lake
+                } else {
+                    WaterKind::Dry
+                }
+            })
+            .collect();
+        Self {
+            size: size,
+            scale_factor: scale_factor,
+            kind_list: kind_list,
+            level_list: filled,
+        }
+    }
+
+    /// Water surface levels given per grid point, points below the sea level are ocean
+    pub fn from_levels(
+        heights: &[f32],
+        levels: &[Option<f32>],
+        size: i32,
+        scale_factor: f32,
+        sea_level: f32,
+    ) -> Self {
+        debug_assert!(heights.len() == (size * size) as usize && levels.len() == heights.len());
+        let (kind_list, level_list) = heights
+            .iter()
+            .zip(levels.iter())
+            .map(|(h, level)| match level {
+                _ if *h < sea_level => (WaterKind::Ocean, sea_level),
+                Some(l) if l - h > MIN_LAKE_DEPTH => (WaterKind::Lake, *l),
+                _ => (WaterKind::Dry, *h),
+            })
+            .unzip();
+        Self {
+            size: size,
+            scale_factor: scale_factor,
+            kind_list: kind_list,
+            level_list: level_list,
+        }
+    }
+
+    pub fn crop(&self, offset: Point2i, size: i32) -> Self {
+        debug_assert!(offset[0] + size <= self.size && offset[1] + size <= self.size);
+        let mut kind_list = Vec::with_capacity((size * size) as usize);
+        let mut level_list = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let index = self.get_index(offset + Point2i::new(x, y));
+                kind_list.push(self.kind_list[index]);
+                level_list.push(self.level_list[index]);
+            }
+        }
+        Self {
+            size: size,
+            scale_factor: self.scale_factor,
+            kind_list: kind_list,
+            level_list: level_list,
+        }
+    }
+
+    pub fn get_size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn get_kind(&self, pos: Point2i) -> WaterKind {
+        self.kind_list[self.get_index(pos)]
+    }
+
+    /// Water surface level, none if the cell is dry
+    pub fn get_level(&self, pos: Point2i) -> Option<f32> {
+        let index = self.get_index(pos);
+        match self.kind_list[index] {
+            WaterKind::Dry => None,
+            _ => Some(self.level_list[index]),
+        }
+    }
+
+    pub fn get_max_level(&self) -> Option<f32> {
+        self.kind_list
+            .iter()
+            .zip(self.level_list.iter())
+            .filter(|(kind, _)| **kind != WaterKind::Dry)
+            .map(|(_, level)| *level)
+            .fold(None, |acc, level| match acc {
+                Some(max) => Some(f32::max(max, level)),
+                None => Some(level),
+            })
+    }
+
+    /// Flat quads for every cell touching water, at the level of the highest wet corner.
+    /// Neighbouring cells of the same level are merged along the x-axis.
+    pub fn triangulate(&self) -> Vec<Triangle> {
+        let mut triangles = Vec::new();
+        for y in 0..self.size - 1 {
+            let mut run: Option<(i32, f32)> = None;
+            for x in 0..self.size {
+                let level = if x < self.size - 1 {
+                    self.get_cell_level(Point2i::new(x, y))
+                } else {
+                    None
+                };
+                match (run, level) {
+                    (Some((_, run_level)), Some(level)) if run_level == level => {}
+                    (Some((start, run_level)), _) => {
+                        self.add_quad(start, x, y, run_level, &mut triangles);
+                        run = level.map(|l| (x, l));
+                    }
+                    (None, _) => run = level.map(|l| (x, l)),
+                }
+            }
+        }
+        triangles
+    }
+
+    fn get_cell_level(&self, cell: Point2i) -> Option<f32> {
+        [(0, 0), (1, 0), (0, 1), (1, 1)]
+            .iter()
+            .filter_map(|(dx, dy)| self.get_level(cell + Point2i::new(*dx, *dy)))
+            .fold(None, |acc, level| match acc {
+                Some(max) => Some(f32::max(max, level)),
+                None => Some(level),
+            })
+    }
+
+    fn add_quad(
+        &self,
+        start_x: i32,
+        end_x: i32,
+        y: i32,
+        level: f32,
+        triangles: &mut Vec<Triangle>,
+    ) {
+        let corners = [
+            Point3f::new(start_x as f32, y as f32, 0.),
+            Point3f::new(end_x as f32, y as f32, 0.),
+            Point3f::new(end_x as f32, (y + 1) as f32, 0.),
+            Point3f::new(start_x as f32, (y + 1) as f32, 0.),
+        ];
+        let vertices: Vec<Vertex> = corners
+            .iter()
+            .map(|c| {
+                let mut vertex = Vertex::default();
+                vertex.set_pos(Point3f::new(
+                    c[0] * self.scale_factor,
+                    c[1] * self.scale_factor,
+                    level,
+                ));
+                vertex.set_normal(Point3f::new(0., 0., 1.));
+                vertex
+            })
+            .collect();
+        for indices in [[0, 1, 2], [0, 2, 3]].iter() {
+            let mut triangle = Triangle::new([
+                vertices[indices[0]],
+                vertices[indices[1]],
+                vertices[indices[2]],
+            ]);
+            triangle.force_ccw();
+            triangles.push(triangle);
+        }
+    }
+
+    fn get_index(&self, pos: Point2i) -> usize {
+        debug_assert!(pos[0] >= 0 && pos[1] >= 0 && pos[0] < self.size && pos[1] < self.size);
+        (pos[0] + pos[1] * self.size) as usize
+    }
+}
+
+impl Saveable for WaterMap {
+    fn save(&self, writer: &mut impl Write) -> Result<(), Error> {
+        writer.write_i32::<LittleEndian>(self.size)?;
+        writer.write_f32::<LittleEndian>(self.scale_factor)?;
+        for (kind, level) in self.kind_list.iter().zip(self.level_list.iter()) {
+            writer.write_u8(match kind {
+                WaterKind::Dry => 0,
+                WaterKind::Lake => 1,
+                WaterKind::Ocean => 2,
+            })?;
+            writer.write_f32::<LittleEndian>(*level)?;
+        }
+        Ok(())
+    }
+}
+
+impl Loadable for WaterMap {
+    fn load(&mut self, reader: &mut impl Read) -> Result<(), Error> {
+        let size = read_map_size(reader, "water map")?;
+        let scale_factor = reader.read_f32::<LittleEndian>()?;
+        let mut kind_list = Vec::with_capacity((size * size) as usize);
+        let mut level_list = Vec::with_capacity((size * size) as usize);
+        for _ in 0..size * size {
+            kind_list.push(match reader.read_u8()? {
+                0 => WaterKind::Dry,
+                1 => WaterKind::Lake,
+                2 => WaterKind::Ocean,
+                kind => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("invalid water kind: {}", kind),
+                    ))
+                }
+            });
+            level_list.push(reader.read_f32::<LittleEndian>()?);
+        }
+        self.size = size;
+        self.scale_factor = scale_factor;
+        self.kind_list = kind_list;
+        self.level_list = level_list;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn create_basin_heights(size: i32) -> Vec<f32> {
+        let center = (size / 2) as f32;
+        let mut heights = Vec::new();
+        for y in 0..size {
+            for x in 0..size {
+                let d = f32::max((x as f32 - center).abs(), (y as f32 - center).abs());
+                heights.push(if d < 3. { d } else { 10. });
+            }
+        }
+        heights
+    }
+
+    #[test]
+    fn test_basin_is_lake() {
+        let map = WaterMap::from_heights(&create_basin_heights(9), 9, 1., -5.);
+        assert_eq!(WaterKind::Lake, map.get_kind(Point2i::new(4, 4)));
+        assert_eq!(Some(10.), map.get_level(Point2i::new(4, 4)));
+        assert_eq!(WaterKind::Dry, map.get_kind(Point2i::new(0, 0)));
+        assert_eq!(None, map.get_level(Point2i::new(0, 0)));
+    }
+
+    #[test]
+    fn test_below_sea_level_is_ocean() {
+        let map = WaterMap::from_heights(&create_basin_heights(9), 9, 1., 1.5);
+        assert_eq!(WaterKind::Ocean, map.get_kind(Point2i::new(4, 4)));
+        assert_eq!(Some(1.5), map.get_level(Point2i::new(4, 4)));
+    }
+
+    #[test]
+    fn test_dry_map_no_triangles() {
+        let heights = vec![1.; 25];
+        let map = WaterMap::from_heights(&heights, 5, 1., 0.);
+        assert!(map.triangulate().is_empty());
+        assert_eq!(None, map.get_max_level());
+    }
+
+    #[test]
+    fn test_ocean_rows_merged() {
+        let heights = vec![-1.; 25];
+        let map = WaterMap::from_heights(&heights, 5, 1., 0.);
+        let triangles = map.triangulate();
+        assert_eq!(2 * 4, triangles.len());
+        assert!(triangles.iter().all(|t| t.get_normal()[2] > 0.));
+    }
+
+    #[test]
+    fn test_from_levels() {
+        let heights = [-1., 2., 5., 9.];
+        let levels = [None, Some(6.), Some(6.), Some(6.)];
+        let map = WaterMap::from_levels(&heights, &levels, 2, 1., 0.);
+        assert_eq!(Some(0.), map.get_level(Point2i::new(0, 0)));
+        assert_eq!(WaterKind::Ocean, map.get_kind(Point2i::new(0, 0)));
+        assert_eq!(Some(6.), map.get_level(Point2i::new(1, 0)));
+        assert_eq!(WaterKind::Lake, map.get_kind(Point2i::new(0, 1)));
+        assert_eq!(WaterKind::Dry, map.get_kind(Point2i::new(1, 1)));
+    }
+
+    #[test]
+    fn test_crop() {
+        let map = WaterMap::from_heights(&create_basin_heights(9), 9, 1., -5.);
+        let cropped = map.crop(Point2i::new(2, 2), 5);
+        assert_eq!(5, cropped.get_size());
+        assert_eq!(
+            map.get_kind(Point2i::new(4, 4)),
+            cropped.get_kind(Point2i::new(2, 2))
+        );
+    }
+}
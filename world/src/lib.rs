@@ -16,21 +16,51 @@ extern crate thiserror;
 
 extern crate core;
 
+mod analysis;
 mod architect;
 mod chunk;
+mod climate;
+mod edit;
+mod height_filter;
 mod height_map;
+mod height_pyramid;
+mod height_sampler;
+mod hydrology;
 mod noise;
+mod region_cache;
+mod road;
+mod rtin;
+mod settlement;
+mod surface;
+mod tectonics;
 mod triangulation;
+mod volume;
 mod water;
 pub mod world;
 pub mod world_error;
 pub mod world_state;
 
+pub use self::analysis::{AnalysisLayer, AnalysisMap, TerrainAnalysis};
 pub use self::architect::Architect;
-pub use self::chunk::CHUNK_SIZE;
+pub use self::chunk::{
+    BuildMetrics, BuildStage, Chunk, ChunkEvent, ChunkListener, ChunkManager, MemoryUsage,
+    StageMetrics, TerrainStore, WorldLayout, WorldPos,
+};
+pub use self::climate::ClimateMap;
+pub use self::edit::{Brush, BrushKind, EditDelta, EditStore, Falloff};
+pub use self::height_filter::{BlendMode, Stamp};
 pub use self::height_map::HeightMap;
-pub use self::noise::{Noise, NoiseBuilder};
+pub use self::height_pyramid::{HeightLevel, HeightPyramid};
+pub use self::height_sampler::HeightSampler;
+pub use self::hydrology::{WaterKind, WaterMap};
+pub use self::noise::{Noise, Noise3D, NoiseBuilder};
+pub use self::road::{Road, RoadPlanner};
+pub use self::rtin::Rtin;
+pub use self::settlement::SettlementSite;
+pub use self::surface::{MaterialRule, SplatMap, SurfaceInfo};
+pub use self::tectonics::{Boundary, BoundaryKind, Plate, PlateKind, PlateMap};
 pub use self::triangulation::triangulate;
+pub use self::volume::DensityField;
 pub use self::water::Water;
 pub use self::world::World;
 pub use self::world_error::WorldError;
@@ -1,5 +1,5 @@
 use crate::noise::Noise;
-use core::Point2f;
+use core::{Point2d, Point2f};
 
 pub struct FactoredNoise {
     base_noise: Box<dyn Noise>,
@@ -26,20 +26,20 @@ impl FactoredNoise {
         self.factor_noises.push(factor_noise);
     }
 
-    fn calculate_factor(&self, point: Point2f) -> f32 {
+    fn calculate_factor(&self, point: Point2d) -> f32 {
         1. + match self.merge_type {
             MergeType::SUM => self
                 .factor_noises
                 .iter()
-                .fold(0., |acc, n| acc + n.get_noise(point)),
+                .fold(0., |acc, n| acc + n.get_noise_precise(point)),
             MergeType::PRODUCT => self
                 .factor_noises
                 .iter()
-                .fold(0., |acc, n| acc * n.get_noise(point)),
+                .fold(0., |acc, n| acc * n.get_noise_precise(point)),
             MergeType::AVG => {
                 self.factor_noises
                     .iter()
-                    .fold(0., |acc, n| acc + n.get_noise(point))
+                    .fold(0., |acc, n| acc + n.get_noise_precise(point))
                     / self.factor_noises.len() as f32
             }
         }
@@ -85,7 +85,11 @@ impl FactoredNoise {
 
 impl Noise for FactoredNoise {
     fn get_noise(&self, point: Point2f) -> f32 {
-        let bn = self.base_noise.get_noise(point);
+        self.get_noise_precise(Point2d::from(point))
+    }
+
+    fn get_noise_precise(&self, point: Point2d) -> f32 {
+        let bn = self.base_noise.get_noise_precise(point);
         bn * self.calculate_factor(point)
     }
 
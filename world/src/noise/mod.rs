@@ -1,21 +1,26 @@
 mod factored_noise;
 pub mod noise;
+mod noise_3d;
 pub mod noise_builder;
 mod noise_modifier;
 mod octaved_noise;
+mod octaved_noise_3d;
 pub mod presets;
 mod repeating_noise;
 mod threshold_noise;
 mod simplex_noise;
+mod simplex_noise_3d;
 mod worley_noise;
 
 pub use self::factored_noise::{FactoredNoise, MergeType};
 pub use self::noise::Noise;
+pub use self::noise_3d::Noise3D;
 pub use self::noise_builder::NoiseBuilder;
 pub use self::noise_modifier::{ModifierType, NoiseModifier};
 pub use self::octaved_noise::OctavedNoise;
-pub use self::presets::{get_default_noise, get_default_tree_noise};
+pub use self::octaved_noise_3d::OctavedNoise3D;
 pub use self::repeating_noise::RepeatingNoise;
 pub use self::threshold_noise::{Threshold, ThresholdNoise};
 pub use self::simplex_noise::SimplexNoise;
+pub use self::simplex_noise_3d::SimplexNoise3D;
 pub use self::worley_noise::WorleyNoise;
@@ -1,7 +1,12 @@
-use core::Point2f;
+use core::{Point2d, Point2f};
 
 pub trait Noise: Sync + Send  {
     fn get_noise(&self, point: Point2f) -> f32;
+    /// Same as `get_noise`, for absolute positions far away from the origin.
+    /// Noises which lose precision on large coordinates should override this.
+    fn get_noise_precise(&self, point: Point2d) -> f32 {
+        self.get_noise(point.as_f32())
+    }
     fn get_range(&self) -> [f32; 2];
     fn get_cycle(&self) -> Point2f;
     fn is_infinite(&self) -> bool {
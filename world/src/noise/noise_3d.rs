@@ -0,0 +1,11 @@
+use core::{Point3d, Point3f};
+
+/// Volumetric counterpart of `Noise`
+pub trait Noise3D: Sync + Send {
+    fn get_noise(&self, point: Point3f) -> f32;
+    /// Same as `get_noise`, for absolute positions far away from the origin
+    fn get_noise_precise(&self, point: Point3d) -> f32 {
+        self.get_noise(point.as_f32())
+    }
+    fn get_range(&self) -> [f32; 2];
+}
@@ -1,11 +1,13 @@
 use super::{
-    FactoredNoise, MergeType, ModifierType, Noise, NoiseModifier, OctavedNoise, RepeatingNoise,
-    SimplexNoise, Threshold, ThresholdNoise, WorleyNoise,
+    FactoredNoise, MergeType, ModifierType, Noise, Noise3D, NoiseModifier, OctavedNoise,
+    OctavedNoise3D, RepeatingNoise, SimplexNoise, SimplexNoise3D, Threshold, ThresholdNoise,
+    WorleyNoise,
 };
 use core::{Point2f, Seed};
 
 pub struct NoiseBuilder {
     base_noise: u8,
+    worley_grid_size: i32,
     seed: Option<Seed>,
     octaves: Option<u8>,
     scale: Option<f32>,
@@ -22,6 +24,7 @@ impl NoiseBuilder {
     pub fn new() -> Self {
         Self {
             base_noise: 0,
+            worley_grid_size: 1,
             seed: None,
             octaves: None,
             scale: None,
@@ -40,8 +43,10 @@ impl NoiseBuilder {
         self
     }
 
-    pub fn base_worley(mut self) -> Self {
+    /// Grid size is the distance between the feature point cells in world units
+    pub fn base_worley(mut self, grid_size: i32) -> Self {
         self.base_noise = 1;
+        self.worley_grid_size = grid_size;
         self
     }
 
@@ -104,6 +109,7 @@ impl NoiseBuilder {
         match self.base_noise {
             1 => Box::new(WorleyNoise::from_seed(
                 self.seed.unwrap_or(Seed::from_entropy()),
+                self.worley_grid_size,
             )),
             _ => Box::new(SimplexNoise::from_seed(
                 self.seed.unwrap_or(Seed::from_entropy()),
@@ -174,4 +180,17 @@ impl NoiseBuilder {
         ));
         self.handle_factors(n)
     }
+
+    /// Volumetric simplex noise, only seed, octaves, scale, roughness and range apply
+    pub fn finish_3d(self) -> Box<dyn Noise3D> {
+        debug_assert!(self.base_noise == 0);
+        let mut noise = OctavedNoise3D::wrap(Box::new(SimplexNoise3D::from_seed(
+            self.seed.unwrap_or(Seed::from_entropy()),
+        )));
+        noise.set_octaves(self.octaves.unwrap_or(1));
+        noise.set_scale(self.scale.unwrap_or(1.));
+        noise.set_roughness(self.roughness.unwrap_or(0.8));
+        noise.set_range(self.range.unwrap_or([-1., 1.]));
+        Box::new(noise)
+    }
 }
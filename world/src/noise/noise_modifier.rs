@@ -1,5 +1,5 @@
 use super::Noise;
-use core::Point2f;
+use core::{Point2d, Point2f};
 
 #[derive(Clone, Copy)]
 pub enum ModifierType {
@@ -33,6 +33,10 @@ impl Noise for NoiseModifier {
         self.apply_modifier(self.noise.get_noise(point))
     }
 
+    fn get_noise_precise(&self, point: Point2d) -> f32 {
+        self.apply_modifier(self.noise.get_noise_precise(point))
+    }
+
     fn get_range(&self) -> [f32; 2] {
         let r = self.noise.get_range();
         [self.apply_modifier(r[0]), self.apply_modifier(r[1])]
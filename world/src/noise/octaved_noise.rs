@@ -1,5 +1,5 @@
 use super::Noise;
-use core::Point2f;
+use core::{Point2d, Point2f};
 
 const DEFAULT_OCTAVES: u8 = 4;
 const DEFAULT_ROUGHNESS: f32 = 0.8;
@@ -49,13 +49,17 @@ impl OctavedNoise {
 
 impl Noise for OctavedNoise {
     fn get_noise(&self, p: Point2f) -> f32 {
+        self.get_noise_precise(Point2d::from(p))
+    }
+
+    fn get_noise_precise(&self, p: Point2d) -> f32 {
         let mut sum: f32 = 0.;
-        let mut freq = self.scale;
+        let mut freq = self.scale as f64;
         let mut weight: f32 = 1.;
         let mut weight_sum: f32 = 0.;
 
         for _oct in 0..self.octaves {
-            sum += self.noise.get_noise(p * freq) * weight;
+            sum += self.noise.get_noise_precise(p * freq) * weight;
             weight_sum += weight;
             freq *= 2.;
             weight *= self.roughness;
@@ -0,0 +1,65 @@
+use super::Noise3D;
+use core::{Point3d, Point3f};
+
+pub struct OctavedNoise3D {
+    noise: Box<dyn Noise3D>,
+    octaves: u8,
+    roughness: f32,
+    scale: f32,
+    range: [f32; 2],
+}
+
+impl OctavedNoise3D {
+    pub fn wrap(wrapped_noise: Box<dyn Noise3D>) -> Self {
+        Self {
+            noise: wrapped_noise,
+            octaves: 1,
+            roughness: 0.8,
+            scale: 1.,
+            range: [-1., 1.],
+        }
+    }
+
+    pub fn set_octaves(&mut self, octave_count: u8) {
+        self.octaves = octave_count;
+    }
+
+    pub fn set_roughness(&mut self, roughness: f32) {
+        self.roughness = roughness;
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+
+    pub fn set_range(&mut self, new_range: [f32; 2]) {
+        self.range = new_range;
+    }
+}
+
+impl Noise3D for OctavedNoise3D {
+    fn get_noise(&self, p: Point3f) -> f32 {
+        self.get_noise_precise(Point3d::from(p))
+    }
+
+    fn get_noise_precise(&self, p: Point3d) -> f32 {
+        let mut sum: f32 = 0.;
+        let mut freq = self.scale as f64;
+        let mut weight: f32 = 1.;
+        let mut weight_sum: f32 = 0.;
+
+        for _oct in 0..self.octaves {
+            sum += self.noise.get_noise_precise(p * freq) * weight;
+            weight_sum += weight;
+            freq *= 2.;
+            weight *= self.roughness;
+        }
+        let sub_range = self.noise.get_range();
+        let normalized = (-sub_range[0] + (sum / weight_sum)) / (sub_range[1] - sub_range[0]);
+        self.range[0] + (self.range[1] - self.range[0]) * normalized
+    }
+
+    fn get_range(&self) -> [f32; 2] {
+        self.range
+    }
+}
@@ -1,6 +1,6 @@
 use rand::rngs::StdRng;
 
-use super::{ModifierType, Noise, NoiseBuilder};
+use super::{ModifierType, Noise, Noise3D, NoiseBuilder};
 use core::Seed;
 
 pub fn get_default_noise(seed: Seed) -> Box<dyn Noise> {
@@ -47,3 +47,13 @@ pub fn get_default_tree_noise(seed: Seed) -> Box<dyn Noise> {
         .range([-1.5, 1.])
         .finish()
 }
+
+pub fn get_default_cave_noise(seed: Seed) -> Box<dyn Noise3D> {
+    NoiseBuilder::new()
+        .seed(seed)
+        .octaves(2)
+        .scale(2e-2)
+        .roughness(0.5)
+        .range([-1., 1.])
+        .finish_3d()
+}
@@ -3,7 +3,7 @@ use rand::prelude::{SliceRandom, StdRng};
 use std::{f32, iter};
 
 use super::Noise;
-use core::{Point2f, Seed};
+use core::{Point2d, Point2f, Seed};
 
 /*
     Noise calculation based on code by
@@ -12,8 +12,8 @@ use core::{Point2f, Seed};
 */
 
 lazy_static! {
-    static ref F2: f32 = 0.5 * (f32::sqrt(3.) - 1.);
-    static ref G2: f32 = (3. - f32::sqrt(3.)) / 6.;
+    static ref F2: f64 = 0.5 * (f64::sqrt(3.) - 1.);
+    static ref G2: f64 = (3. - f64::sqrt(3.)) / 6.;
 }
 
 const GRADIENTS: [[i32; 2]; 12] = [
@@ -60,21 +60,29 @@ impl SimplexNoise {
 
 impl Noise for SimplexNoise {
     fn get_noise(&self, p: Point2f) -> f32 {
+        self.get_noise_precise(Point2d::from(p))
+    }
+
+    // The cell is located in double precision, only the offset within the cell is single precision
+    fn get_noise_precise(&self, p: Point2d) -> f32 {
         let skew = (p[0] + p[1]) * *F2;
         /*  if not floored, noise can have sharp edges on negative coordinates
             https://stackoverflow.com/questions/10705640/perlin-noise-with-negative-coordinate-input
         */
-        let skew_coord: [i32; 2] = [
-            f32::floor(p[0] + skew) as i32,
-            f32::floor(p[1] + skew) as i32,
+        let skew_coord: [i64; 2] = [
+            f64::floor(p[0] + skew) as i64,
+            f64::floor(p[1] + skew) as i64,
         ];
-        let unskew = (skew_coord[0] + skew_coord[1]) as f32 * *G2;
-
-        let cell_origin: [f32; 2] = [skew_coord[0] as f32 - unskew, skew_coord[1] as f32 - unskew];
+        let unskew = (skew_coord[0] + skew_coord[1]) as f64 * *G2;
+        let cell_offset = Point2f::new(
+            (p[0] - (skew_coord[0] as f64 - unskew)) as f32,
+            (p[1] - (skew_coord[1] as f64 - unskew)) as f32,
+        );
 
-        let corner = calculate_corners(p, cell_origin);
+        let corner = calculate_corners(cell_offset);
 
-        let table_base_index: [i32; 2] = [skew_coord[0] & 0xFF, skew_coord[1] & 0xFF];
+        let table_base_index: [i32; 2] =
+            [(skew_coord[0] & 0xFF) as i32, (skew_coord[1] & 0xFF) as i32];
         let table_offset: [[i32; 2]; 3] = [[0, 0], get_second_corner_offset(corner[0]), [1, 1]];
         let mut contrib_sum: f32 = 0.;
         for i in 0..3 {
@@ -98,18 +106,19 @@ impl Noise for SimplexNoise {
     }
 }
 
-fn calculate_corners(p: Point2f, cell_origin: [f32; 2]) -> [[f32; 2]; 3] {
+fn calculate_corners(cell_offset: Point2f) -> [[f32; 2]; 3] {
+    let g2 = *G2 as f32;
     let mut corner = [[0., 0.]; 3];
 
-    corner[0][0] = p[0] - cell_origin[0];
-    corner[0][1] = p[1] - cell_origin[1];
+    corner[0][0] = cell_offset[0];
+    corner[0][1] = cell_offset[1];
 
     let offset = get_second_corner_offset(corner[0]);
-    corner[1][0] = corner[0][0] - offset[0] as f32 + *G2;
-    corner[1][1] = corner[0][1] - offset[1] as f32 + *G2;
+    corner[1][0] = corner[0][0] - offset[0] as f32 + g2;
+    corner[1][1] = corner[0][1] - offset[1] as f32 + g2;
 
-    corner[2][0] = corner[0][0] - 1. + 2. * *G2;
-    corner[2][1] = corner[0][1] - 1. + 2. * *G2;
+    corner[2][0] = corner[0][0] - 1. + 2. * g2;
+    corner[2][1] = corner[0][1] - 1. + 2. * g2;
     corner
 }
 
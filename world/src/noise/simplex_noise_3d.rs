@@ -0,0 +1,155 @@
+use rand::prelude::{SliceRandom, StdRng};
+use std::iter;
+
+use super::Noise3D;
+use core::{Point3d, Point3f, Seed};
+
+/*
+    3D noise calculation based on code by
+    Stefan Gustavson & Peter Eastman
+    itn.liu.se/~stegu/simplexnoise/SimplexNoise.java
+*/
+
+const F3: f64 = 1. / 3.;
+const G3: f64 = 1. / 6.;
+
+const GRADIENTS: [[i32; 3]; 12] = [
+    [1, 1, 0],
+    [-1, 1, 0],
+    [1, -1, 0],
+    [-1, -1, 0],
+    [1, 0, 1],
+    [-1, 0, 1],
+    [1, 0, -1],
+    [-1, 0, -1],
+    [0, 1, 1],
+    [0, -1, 1],
+    [0, 1, -1],
+    [0, -1, -1],
+];
+
+#[derive(Clone)]
+pub struct SimplexNoise3D {
+    permutation_table: Vec<u8>,
+}
+
+impl SimplexNoise3D {
+    pub fn from_seed(seed: Seed) -> Self {
+        let mut rng: StdRng = seed.into();
+        let mut permutation: Vec<u8> = (0u8..255).chain(iter::once(255u8)).collect();
+        permutation.shuffle(&mut rng);
+        let perm_clone = permutation.clone();
+        permutation.extend(perm_clone);
+        Self {
+            permutation_table: permutation,
+        }
+    }
+
+    fn get_gradient_index(&self, cell: [i64; 3]) -> usize {
+        let table = &self.permutation_table;
+        let i = (cell[0] & 0xFF) as usize;
+        let j = (cell[1] & 0xFF) as usize;
+        let k = (cell[2] & 0xFF) as usize;
+        table[i + table[j + table[k] as usize] as usize] as usize % 12
+    }
+}
+
+impl Noise3D for SimplexNoise3D {
+    fn get_noise(&self, p: Point3f) -> f32 {
+        self.get_noise_precise(Point3d::from(p))
+    }
+
+    // The cell is located in double precision, only the offset within the cell is single precision
+    fn get_noise_precise(&self, p: Point3d) -> f32 {
+        let skew = (p[0] + p[1] + p[2]) * F3;
+        let cell = [
+            f64::floor(p[0] + skew) as i64,
+            f64::floor(p[1] + skew) as i64,
+            f64::floor(p[2] + skew) as i64,
+        ];
+        let unskew = (cell[0] + cell[1] + cell[2]) as f64 * G3;
+        let corner = [
+            (p[0] - (cell[0] as f64 - unskew)) as f32,
+            (p[1] - (cell[1] as f64 - unskew)) as f32,
+            (p[2] - (cell[2] as f64 - unskew)) as f32,
+        ];
+        let g3 = G3 as f32;
+
+        let (second, third) = get_corner_offsets(corner);
+        let offsets = [[0, 0, 0], second, third, [1, 1, 1]];
+
+        let mut contrib_sum = 0.;
+        for (i, offset) in offsets.iter().enumerate() {
+            let local = [
+                corner[0] - offset[0] as f32 + i as f32 * g3,
+                corner[1] - offset[1] as f32 + i as f32 * g3,
+                corner[2] - offset[2] as f32 + i as f32 * g3,
+            ];
+            let grad_index = self.get_gradient_index([
+                cell[0] + offset[0] as i64,
+                cell[1] + offset[1] as i64,
+                cell[2] + offset[2] as i64,
+            ]);
+            contrib_sum += calculate_corner_contribution(grad_index, local);
+        }
+        f32::max(-1., f32::min(1., 32. * contrib_sum))
+    }
+
+    fn get_range(&self) -> [f32; 2] {
+        [-1., 1.]
+    }
+}
+
+// Offsets of the second and third simplex corner, depending on the traversed simplex
+fn get_corner_offsets(c: [f32; 3]) -> ([i32; 3], [i32; 3]) {
+    if c[0] >= c[1] {
+        if c[1] >= c[2] {
+            ([1, 0, 0], [1, 1, 0])
+        } else if c[0] >= c[2] {
+            ([1, 0, 0], [1, 0, 1])
+        } else {
+            ([0, 0, 1], [1, 0, 1])
+        }
+    } else if c[1] < c[2] {
+        ([0, 0, 1], [0, 1, 1])
+    } else if c[0] < c[2] {
+        ([0, 1, 0], [0, 1, 1])
+    } else {
+        ([0, 1, 0], [1, 1, 0])
+    }
+}
+
+fn calculate_corner_contribution(grad_index: usize, offset: [f32; 3]) -> f32 {
+    let t = 0.6 - offset[0].powf(2.) - offset[1].powf(2.) - offset[2].powf(2.);
+    if t < 0. {
+        0.
+    } else {
+        let grad = GRADIENTS[grad_index];
+        t.powf(4.)
+            * (grad[0] as f32 * offset[0] + grad[1] as f32 * offset[1] + grad[2] as f32 * offset[2])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_noise_in_range() {
+        let noise = SimplexNoise3D::from_seed(Seed::from_string("CAVES"));
+        for i in 0..1000 {
+            let p = Point3f::new(i as f32 * 0.37, i as f32 * -0.11, i as f32 * 0.53);
+            let n = noise.get_noise(p);
+            assert!(n >= -1. && n <= 1.);
+        }
+    }
+
+    #[test]
+    fn test_noise_deterministic() {
+        let a = SimplexNoise3D::from_seed(Seed::from_string("CAVES"));
+        let b = SimplexNoise3D::from_seed(Seed::from_string("CAVES"));
+        let p = Point3f::new(12.3, -4.5, 6.7);
+        assert_eq!(a.get_noise(p), b.get_noise(p));
+    }
+}
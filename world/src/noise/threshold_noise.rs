@@ -1,5 +1,5 @@
 use crate::noise::Noise;
-use core::Point2f;
+use core::{Point2d, Point2f};
 
 pub struct ThresholdNoise {
     noise: Box<dyn Noise>,
@@ -23,7 +23,11 @@ impl ThresholdNoise {
 
 impl Noise for ThresholdNoise {
     fn get_noise(&self, point: Point2f) -> f32 {
-        let n = self.noise.get_noise(point);
+        self.get_noise_precise(Point2d::from(point))
+    }
+
+    fn get_noise_precise(&self, point: Point2d) -> f32 {
+        let n = self.noise.get_noise_precise(point);
         match self.threshold {
             Threshold::Below(max) if n <= max => n,
             Threshold::Above(min) if n >= min => n,
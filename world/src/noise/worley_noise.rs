@@ -4,7 +4,6 @@ use rand::Rng;
 use std::f32;
 
 use super::Noise;
-use crate::chunk::CHUNK_SIZE;
 use core::{Point2f, Point2i, Seed};
 
 pub struct WorleyNoise {
@@ -27,12 +26,16 @@ impl Noise for WorleyNoise {
 }
 
 impl WorleyNoise {
-    pub fn from_seed(seed: Seed) -> Self {
+    /// Grid size is the distance between the feature point cells in world units,
+    /// like the chunk size of the world layout
+    pub fn from_seed(seed: Seed, grid_size: i32) -> Self {
+        debug_assert!(grid_size > 0);
         Self {
             seed: seed,
-            grid_size: CHUNK_SIZE,
+            grid_size: grid_size,
         }
     }
+
     fn get_min_distance(&self, p: Point2f) -> f32 {
         const GRID_OFFSETS: [[i32; 2]; 9] = [
             [-1, -1],
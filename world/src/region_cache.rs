@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use core::Point2i;
+
+struct CachedRegion<T> {
+    value: Arc<T>,
+    last_used: u64,
+}
+
+struct Regions<T> {
+    region_map: BTreeMap<Point2i, CachedRegion<T>>,
+    use_count: u64,
+}
+
+/// Values created per region on first use, the least recently used regions are dropped
+/// once the capacity is exceeded
+pub struct RegionCache<T> {
+    capacity: usize,
+    regions: Mutex<Regions<T>>,
+}
+
+impl<T> RegionCache<T> {
+    pub fn new(capacity: usize) -> Self {
+        debug_assert!(capacity > 0);
+        Self {
+            capacity: capacity,
+            regions: Mutex::new(Regions {
+                region_map: BTreeMap::new(),
+                use_count: 0,
+            }),
+        }
+    }
+
+    /// The value is created outside of the lock, so concurrent callers may both create it
+    pub fn get_or_create<F: FnOnce() -> T>(&self, region_pos: Point2i, create_fn: F) -> Arc<T> {
+        let cached = self.regions.lock().ok().and_then(|mut regions| {
+            regions.use_count += 1;
+            let use_count = regions.use_count;
+            regions.region_map.get_mut(&region_pos).map(|cached| {
+                cached.last_used = use_count;
+                cached.value.clone()
+            })
+        });
+        if let Some(value) = cached {
+            return value;
+        }
+        let value = Arc::new(create_fn());
+        if let Ok(mut regions) = self.regions.lock() {
+            let last_used = regions.use_count;
+            regions.region_map.insert(
+                region_pos,
+                CachedRegion {
+                    value: value.clone(),
+                    last_used: last_used,
+                },
+            );
+            while regions.region_map.len() > self.capacity {
+                let oldest = regions
+                    .region_map
+                    .iter()
+                    .min_by_key(|(_, cached)| cached.last_used)
+                    .map(|(pos, _)| *pos);
+                if let Some(pos) = oldest {
+                    regions.region_map.remove(&pos);
+                }
+            }
+        }
+        value
+    }
+
+    pub fn clear(&mut self) {
+        if let Ok(regions) = self.regions.get_mut() {
+            regions.region_map.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_least_recently_used_dropped() {
+        let cache = RegionCache::new(2);
+        cache.get_or_create(Point2i::new(0, 0), || 0);
+        cache.get_or_create(Point2i::new(1, 0), || 1);
+        // region 0 is used again, so region 1 is the least recently used
+        assert_eq!(0, *cache.get_or_create(Point2i::new(0, 0), || 10));
+        cache.get_or_create(Point2i::new(2, 0), || 2);
+        assert_eq!(0, *cache.get_or_create(Point2i::new(0, 0), || 10));
+        assert_eq!(11, *cache.get_or_create(Point2i::new(1, 0), || 11));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut cache = RegionCache::new(4);
+        cache.get_or_create(Point2i::new(0, 0), || 0);
+        cache.clear();
+        assert_eq!(1, *cache.get_or_create(Point2i::new(0, 0), || 1));
+    }
+}
@@ -0,0 +1,5 @@
+mod road;
+mod road_planner;
+
+pub use self::road::Road;
+pub use self::road_planner::RoadPlanner;
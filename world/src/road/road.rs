@@ -0,0 +1,271 @@
+use crate::{AnalysisMap, HeightMap};
+use core::{Point2f, Point2i, Point3f};
+
+// Distance between samples of the centre line, in world units
+const SPLINE_STEP: f32 = 1.;
+// Samples on each side averaged for the road height, evens out the grade along the road
+const GRADE_RADIUS: usize = 12;
+// Width beside the road surface across which the terrain is blended back to its own height
+const SHOULDER_WIDTH: f32 = 6.;
+
+/// Centre line of a road with smoothed heights, sampled from a Catmull-Rom spline
+pub struct Road {
+    width: f32,
+    point_list: Vec<Point3f>,
+    min: Point2f,
+    max: Point2f,
+}
+
+impl Road {
+    /// Heights along the road follow the terrain given by the height function,
+    /// but never go below the minimum height, so water is crossed on a causeway
+    pub fn from_path<F: Fn(Point2f) -> f32>(
+        path: &[Point2f],
+        width: f32,
+        height_fn: F,
+        min_height: f32,
+    ) -> Self {
+        debug_assert!(path.len() >= 2);
+        let centre_line = sample_spline(path);
+        let heights: Vec<f32> = centre_line
+            .iter()
+            .map(|p| f32::max(height_fn(*p), min_height))
+            .collect();
+
+        let mut point_list = Vec::with_capacity(centre_line.len());
+        for (i, p) in centre_line.iter().enumerate() {
+            let window = &heights
+                [i.saturating_sub(GRADE_RADIUS)..usize::min(heights.len(), i + GRADE_RADIUS + 1)];
+            let height = window.iter().sum::<f32>() / window.len() as f32;
+            point_list.push(p.extend(height));
+        }
+
+        let reach = width / 2. + SHOULDER_WIDTH;
+        let mut min = Point2f::from_scalar(f32::INFINITY);
+        let mut max = Point2f::from_scalar(f32::NEG_INFINITY);
+        for p in centre_line.iter() {
+            min = Point2f::new(f32::min(min[0], p[0]), f32::min(min[1], p[1]));
+            max = Point2f::new(f32::max(max[0], p[0]), f32::max(max[1], p[1]));
+        }
+        Self {
+            width: width,
+            point_list: point_list,
+            min: min - Point2f::from_scalar(reach),
+            max: max + Point2f::from_scalar(reach),
+        }
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn get_points(&self) -> &[Point3f] {
+        self.point_list.as_slice()
+    }
+
+    pub fn get_length(&self) -> f32 {
+        self.point_list
+            .windows(2)
+            .map(|w| (w[1].as_xy() - w[0].as_xy()).length())
+            .sum()
+    }
+
+    /// Whether the road or its shoulders touch the given rectangle
+    pub fn intersects(&self, min: Point2f, max: Point2f) -> bool {
+        self.min[0] <= max[0]
+            && self.min[1] <= max[1]
+            && self.max[0] >= min[0]
+            && self.max[1] >= min[1]
+    }
+
+    /// Road height, terrain blend weight and road surface coverage at the given position,
+    /// none if the position is beyond the shoulders of the road
+    pub fn get_cross_section(&self, pos: Point2f) -> Option<(f32, f32, f32)> {
+        if !self.intersects(pos, pos) {
+            return None;
+        }
+        let segments: Vec<usize> = (0..self.point_list.len() - 1).collect();
+        self.get_cross_section_on(pos, &segments)
+    }
+
+    /// Blends the heights around the road towards the road height and marks the road surface
+    /// in the mask. Only depends on world positions, so neighbouring heightmaps agree on their
+    /// shared edges. Normals of the heightmap are not updated.
+    pub fn apply_to(&self, heightmap: &mut HeightMap, mask: &mut AnalysisMap) {
+        debug_assert!(heightmap.get_size() == mask.get_size());
+        let size = heightmap.get_size();
+        let scale_factor = heightmap.get_scale_factor();
+        let origin = heightmap.get_origin();
+        let extent = origin + Point2f::from_scalar((size - 1) as f32 * scale_factor);
+        if !self.intersects(origin, extent) {
+            return;
+        }
+
+        let reach = self.width / 2. + SHOULDER_WIDTH;
+        let segments: Vec<usize> = self
+            .point_list
+            .windows(2)
+            .enumerate()
+            .filter(|(_, w)| {
+                let seg_min = Point2f::new(f32::min(w[0][0], w[1][0]), f32::min(w[0][1], w[1][1]));
+                let seg_max = Point2f::new(f32::max(w[0][0], w[1][0]), f32::max(w[0][1], w[1][1]));
+                seg_min[0] - reach <= extent[0]
+                    && seg_min[1] - reach <= extent[1]
+                    && seg_max[0] + reach >= origin[0]
+                    && seg_max[1] + reach >= origin[1]
+            })
+            .map(|(i, _)| i)
+            .collect();
+        if segments.is_empty() {
+            return;
+        }
+
+        for y in 0..size {
+            for x in 0..size {
+                let grid_pos = Point2i::new(x, y);
+                let pos = origin + Point2f::from(grid_pos) * scale_factor;
+                if let Some((road_height, weight, coverage)) =
+                    self.get_cross_section_on(pos, &segments)
+                {
+                    let height = heightmap.get(grid_pos);
+                    heightmap.set(grid_pos, height + (road_height - height) * weight);
+                    mask.set(grid_pos, f32::max(mask.get(grid_pos), coverage));
+                }
+            }
+        }
+    }
+
+    fn get_cross_section_on(&self, pos: Point2f, segments: &[usize]) -> Option<(f32, f32, f32)> {
+        let mut closest: Option<(f32, f32)> = None;
+        for i in segments.iter() {
+            let a = self.point_list[*i];
+            let b = self.point_list[*i + 1];
+            let ab = b.as_xy() - a.as_xy();
+            let length_sq = ab.dot(&ab);
+            let t = if length_sq > 0. {
+                ((pos - a.as_xy()).dot(&ab) / length_sq).max(0.).min(1.)
+            } else {
+                0.
+            };
+            let distance = (pos - (a.as_xy() + ab * t)).length();
+            match closest {
+                Some((d, _)) if d <= distance => {}
+                _ => closest = Some((distance, a[2] + (b[2] - a[2]) * t)),
+            }
+        }
+
+        let half_width = self.width / 2.;
+        match closest {
+            Some((distance, height)) if distance < half_width + SHOULDER_WIDTH => {
+                let weight = if distance <= half_width {
+                    1.
+                } else {
+                    let s = 1. - (distance - half_width) / SHOULDER_WIDTH;
+                    s * s * (3. - 2. * s)
+                };
+                let coverage = (half_width + 0.5 - distance).max(0.).min(1.);
+                Some((height, weight, coverage))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn sample_spline(path: &[Point2f]) -> Vec<Point2f> {
+    let mut samples = Vec::new();
+    let last = path.len() - 1;
+    for i in 0..last {
+        let p0 = path[i.saturating_sub(1)];
+        let p1 = path[i];
+        let p2 = path[i + 1];
+        let p3 = path[usize::min(i + 2, last)];
+        let steps = usize::max(1, ((p2 - p1).length() / SPLINE_STEP).ceil() as usize);
+        for step in 0..steps {
+            let t = step as f32 / steps as f32;
+            samples.push(catmull_rom(p0, p1, p2, p3, t));
+        }
+    }
+    samples.push(path[last]);
+    samples
+}
+
+fn catmull_rom(p0: Point2f, p1: Point2f, p2: Point2f, p3: Point2f, t: f32) -> Point2f {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.
+        + (p2 - p0) * t
+        + (p0 * 2. - p1 * 5. + p2 * 4. - p3) * t2
+        + (p1 * 3. - p0 - p2 * 3. + p3) * t3)
+        * 0.5
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::Noise;
+
+    struct SlopeNoise;
+
+    impl Noise for SlopeNoise {
+        fn get_noise(&self, point: Point2f) -> f32 {
+            point[1] * 0.5
+        }
+        fn get_range(&self) -> [f32; 2] {
+            [f32::NEG_INFINITY, f32::INFINITY]
+        }
+        fn get_cycle(&self) -> Point2f {
+            Point2f::from_scalar(f32::INFINITY)
+        }
+    }
+
+    fn create_straight_road() -> Road {
+        let path = [Point2f::new(-20., 8.), Point2f::new(60., 8.)];
+        Road::from_path(&path, 4., |_| 10., 0.)
+    }
+
+    #[test]
+    fn test_spline_passes_control_points() {
+        let path = [
+            Point2f::new(0., 0.),
+            Point2f::new(8., 0.),
+            Point2f::new(16., 8.),
+        ];
+        let samples = sample_spline(&path);
+        assert!(samples.contains(&path[1]));
+        assert_eq!(path[2], samples[samples.len() - 1]);
+    }
+
+    #[test]
+    fn test_road_flattens_and_masks() {
+        let road = create_straight_road();
+        let mut heightmap = HeightMap::from_noise(Point2f::from_scalar(0.), 17, 1., &SlopeNoise);
+        let mut mask = AnalysisMap::new(17, 1.);
+        road.apply_to(&mut heightmap, &mut mask);
+        assert_eq!(10., heightmap.get(Point2i::new(4, 8)));
+        assert_eq!(1., mask.get(Point2i::new(4, 8)));
+        assert_eq!(0., mask.get(Point2i::new(4, 0)));
+        assert_eq!(0., heightmap.get(Point2i::new(4, 0)));
+    }
+
+    #[test]
+    fn test_road_matches_on_shared_edges() {
+        let road = create_straight_road();
+        let mut left = HeightMap::from_noise(Point2f::from_scalar(0.), 17, 1., &SlopeNoise);
+        let mut right = HeightMap::from_noise(Point2f::new(16., 0.), 17, 1., &SlopeNoise);
+        let mut mask = AnalysisMap::new(17, 1.);
+        road.apply_to(&mut left, &mut mask);
+        road.apply_to(&mut right, &mut mask);
+        for y in 0..17 {
+            assert_eq!(left.get(Point2i::new(16, y)), right.get(Point2i::new(0, y)));
+        }
+    }
+
+    #[test]
+    fn test_road_over_water_raised() {
+        let path = [Point2f::new(0., 0.), Point2f::new(40., 0.)];
+        let road = Road::from_path(&path, 4., |_| -5., 0.);
+        assert!(road.get_points().iter().all(|p| p[2] == 0.));
+        assert!(road.get_length() > 39.);
+    }
+}
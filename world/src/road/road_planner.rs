@@ -0,0 +1,306 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap};
+
+use core::{Point2f, Point2i};
+
+/*
+    A* search on a coarse grid of terrain heights. Search states are
+    grid nodes together with the direction they were entered from, so
+    that changes of direction can be penalised and the resulting paths
+    stay smooth enough to be followed by a spline.
+*/
+
+const DIRECTIONS: [[i32; 2]; 8] = [
+    [1, 0],
+    [1, 1],
+    [0, 1],
+    [-1, 1],
+    [-1, 0],
+    [-1, -1],
+    [0, -1],
+    [1, -1],
+];
+// Direction of the start node, which has no predecessor
+const NO_DIRECTION: usize = DIRECTIONS.len();
+// Turns sharper than this many 45° steps are not allowed
+const MAX_TURN: usize = 2;
+// Nodes around the bounding box of start and end which are searched as well
+const MIN_SEARCH_MARGIN: i32 = 16;
+
+pub struct RoadPlanner {
+    grid_spacing: f32,
+    sea_level: f32,
+    slope_weight: f32,
+    water_penalty: f32,
+    turn_weight: f32,
+}
+
+impl RoadPlanner {
+    pub fn new(grid_spacing: f32, sea_level: f32) -> Self {
+        debug_assert!(grid_spacing > 0.);
+        Self {
+            grid_spacing: grid_spacing,
+            sea_level: sea_level,
+            slope_weight: 100.,
+            water_penalty: 20.,
+            turn_weight: 0.5,
+        }
+    }
+
+    /// Cost per distance is multiplied by 1 + weight * grade²
+    pub fn with_slope_weight(mut self, slope_weight: f32) -> Self {
+        self.slope_weight = slope_weight;
+        self
+    }
+
+    /// Additional cost per distance below the sea level
+    pub fn with_water_penalty(mut self, water_penalty: f32) -> Self {
+        self.water_penalty = water_penalty;
+        self
+    }
+
+    /// Cost of a 45° turn, in grid spacings. Sharper turns cost quadratically more.
+    pub fn with_turn_weight(mut self, turn_weight: f32) -> Self {
+        self.turn_weight = turn_weight;
+        self
+    }
+
+    pub fn get_grid_spacing(&self) -> f32 {
+        self.grid_spacing
+    }
+
+    /// Cheapest path from start to end, as world positions of the visited grid nodes.
+    /// The first and last position are the exact start and end positions.
+    /// None if the end can't be reached within the search area.
+    pub fn find_path<F: Fn(Point2f) -> f32>(
+        &self,
+        height_fn: F,
+        from: Point2f,
+        to: Point2f,
+    ) -> Option<Vec<Point2f>> {
+        let start = self.get_node(from);
+        let goal = self.get_node(to);
+        let margin = i32::max(MIN_SEARCH_MARGIN, (goal - start).length() as i32 / 2);
+        let min = Point2i::new(
+            i32::min(start[0], goal[0]) - margin,
+            i32::min(start[1], goal[1]) - margin,
+        );
+        let max = Point2i::new(
+            i32::max(start[0], goal[0]) + margin,
+            i32::max(start[1], goal[1]) + margin,
+        );
+
+        let mut heights: BTreeMap<Point2i, f32> = BTreeMap::new();
+        let mut get_height = |node: Point2i| -> f32 {
+            *heights
+                .entry(node)
+                .or_insert_with(|| height_fn(Point2f::from(node) * self.grid_spacing))
+        };
+
+        let mut costs: BTreeMap<(Point2i, usize), f32> = BTreeMap::new();
+        let mut parents: BTreeMap<(Point2i, usize), (Point2i, usize)> = BTreeMap::new();
+        let mut queue = BinaryHeap::new();
+        costs.insert((start, NO_DIRECTION), 0.);
+        queue.push(SearchNode {
+            estimate: self.get_estimate(start, goal),
+            cost: 0.,
+            node: start,
+            direction: NO_DIRECTION,
+        });
+
+        while let Some(current) = queue.pop() {
+            let state = (current.node, current.direction);
+            if current.node == goal {
+                return Some(self.build_path(&parents, state, from, to));
+            }
+            match costs.get(&state) {
+                Some(cost) if *cost < current.cost => continue,
+                _ => {}
+            }
+            let height = get_height(current.node);
+            for (direction, offset) in DIRECTIONS.iter().enumerate() {
+                let next = current.node + Point2i::new(offset[0], offset[1]);
+                if next[0] < min[0] || next[1] < min[1] || next[0] > max[0] || next[1] > max[1] {
+                    continue;
+                }
+                let step_cost = match self.get_step_cost(
+                    height,
+                    get_height(next),
+                    current.direction,
+                    direction,
+                ) {
+                    Some(c) => c,
+                    None => continue,
+                };
+                let cost = current.cost + step_cost;
+                let next_state = (next, direction);
+                match costs.get(&next_state) {
+                    Some(known) if *known <= cost => {}
+                    _ => {
+                        costs.insert(next_state, cost);
+                        parents.insert(next_state, state);
+                        queue.push(SearchNode {
+                            estimate: cost + self.get_estimate(next, goal),
+                            cost: cost,
+                            node: next,
+                            direction: direction,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn get_node(&self, pos: Point2f) -> Point2i {
+        let grid_pos = pos / self.grid_spacing;
+        Point2i::new(grid_pos[0].round() as i32, grid_pos[1].round() as i32)
+    }
+
+    // Straight line distance never overestimates, as every step costs at least its length
+    fn get_estimate(&self, node: Point2i, goal: Point2i) -> f32 {
+        (goal - node).length() * self.grid_spacing
+    }
+
+    fn get_step_cost(
+        &self,
+        from_height: f32,
+        to_height: f32,
+        from_direction: usize,
+        direction: usize,
+    ) -> Option<f32> {
+        let turn = if from_direction == NO_DIRECTION {
+            0
+        } else {
+            let diff = (from_direction as i32 - direction as i32).abs() as usize;
+            usize::min(diff, DIRECTIONS.len() - diff)
+        };
+        if turn > MAX_TURN {
+            return None;
+        }
+        let offset = DIRECTIONS[direction];
+        let length = Point2i::new(offset[0], offset[1]).length() * self.grid_spacing;
+        let grade = (to_height - from_height) / length;
+        let mut cost = length * (1. + self.slope_weight * grade * grade);
+        if to_height < self.sea_level {
+            cost += length * self.water_penalty;
+        }
+        cost += self.turn_weight * (turn * turn) as f32 * self.grid_spacing;
+        Some(cost)
+    }
+
+    fn build_path(
+        &self,
+        parents: &BTreeMap<(Point2i, usize), (Point2i, usize)>,
+        end_state: (Point2i, usize),
+        from: Point2f,
+        to: Point2f,
+    ) -> Vec<Point2f> {
+        let mut nodes = vec![end_state.0];
+        let mut state = end_state;
+        while let Some(parent) = parents.get(&state) {
+            nodes.push(parent.0);
+            state = *parent;
+        }
+        nodes.reverse();
+
+        let mut path: Vec<Point2f> = nodes
+            .into_iter()
+            .map(|node| Point2f::from(node) * self.grid_spacing)
+            .collect();
+        path[0] = from;
+        if path.len() == 1 {
+            path.push(to);
+        } else {
+            let last = path.len() - 1;
+            path[last] = to;
+        }
+        path
+    }
+}
+
+// Ordered so that the binary heap yields the lowest estimate first
+struct SearchNode {
+    estimate: f32,
+    cost: f32,
+    node: Point2i,
+    direction: usize,
+}
+
+impl Ord for SearchNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.node.cmp(&self.node))
+            .then_with(|| other.direction.cmp(&self.direction))
+    }
+}
+
+impl PartialOrd for SearchNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SearchNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SearchNode {}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_flat_path_is_straight() {
+        let planner = RoadPlanner::new(4., -100.);
+        let path = planner
+            .find_path(|_| 0., Point2f::new(0., 0.), Point2f::new(40., 0.))
+            .unwrap();
+        assert_eq!(11, path.len());
+        assert!(path.iter().all(|p| p[1] == 0.));
+    }
+
+    #[test]
+    fn test_path_avoids_hill() {
+        let hill = |p: Point2f| f32::max(0., 40. - (p - Point2f::new(40., 0.)).length()) * 2.;
+        let planner = RoadPlanner::new(4., -100.);
+        let path = planner
+            .find_path(hill, Point2f::new(0., 0.), Point2f::new(80., 0.))
+            .unwrap();
+        let max_height = path.iter().fold(0f32, |acc, p| f32::max(acc, hill(*p)));
+        assert!(max_height < 40.);
+    }
+
+    #[test]
+    fn test_path_avoids_water() {
+        let lake = |p: Point2f| {
+            if (p - Point2f::new(40., 0.)).length() < 20. {
+                -5.
+            } else {
+                0.
+            }
+        };
+        let planner = RoadPlanner::new(4., 0.);
+        let path = planner
+            .find_path(lake, Point2f::new(0., 0.), Point2f::new(80., 0.))
+            .unwrap();
+        assert!(path.iter().all(|p| lake(*p) >= 0.));
+    }
+
+    #[test]
+    fn test_path_keeps_exact_endpoints() {
+        let planner = RoadPlanner::new(8., 0.);
+        let from = Point2f::new(1.5, -2.5);
+        let to = Point2f::new(3., 1.);
+        let path = planner.find_path(|_| 0., from, to).unwrap();
+        assert_eq!(from, path[0]);
+        assert_eq!(to, path[path.len() - 1]);
+    }
+}
@@ -0,0 +1,278 @@
+use core::graphics::mesh::{Triangle, Vertex};
+use core::{Point2i, Point3f};
+
+use crate::HeightMap;
+
+/*
+    Right-triangulated irregular network, based on the approach by
+    Evans, Kirkpatrick & Townsend and the martini implementation by
+    Vladimir Agafonkin (github.com/mapbox/martini)
+*/
+
+pub struct Rtin {
+    grid_size: i32,
+    scale_factor: f32,
+    heights: Vec<f32>,
+    normals: Vec<Point3f>,
+    errors: Vec<f32>,
+}
+
+impl Rtin {
+    /// Heightmap size must be 2^k + 1
+    pub fn new(heightmap: &HeightMap) -> Option<Self> {
+        let grid_size = heightmap.get_size();
+        let tile_size = grid_size - 1;
+        if tile_size < 1 || tile_size & (tile_size - 1) != 0 {
+            return None;
+        }
+        let mut normals = Vec::with_capacity((grid_size * grid_size) as usize);
+        for y in 0..grid_size {
+            for x in 0..grid_size {
+                normals.push(heightmap.get_normal(Point2i::new(x, y)));
+            }
+        }
+        let mut rtin = Self {
+            grid_size: grid_size,
+            scale_factor: heightmap.get_scale_factor(),
+            heights: heightmap.get_list().to_vec(),
+            normals: normals,
+            errors: Vec::new(),
+        };
+        rtin.update_errors();
+        Some(rtin)
+    }
+
+    pub fn get_max_error(&self) -> f32 {
+        self.errors.iter().fold(0., |acc, e| f32::max(acc, *e))
+    }
+
+    pub fn triangulate(&self, max_error: f32, skirt_depth: f32) -> Vec<Triangle> {
+        let max = self.grid_size - 1;
+        let mut triangles = Vec::new();
+        self.process_triangle([0, 0], [max, max], [max, 0], max_error, &mut triangles);
+        self.process_triangle([max, max], [0, 0], [0, max], max_error, &mut triangles);
+
+        if skirt_depth > 0. {
+            let mut skirts = Vec::new();
+            for triangle in triangles.iter() {
+                self.add_skirts(triangle, skirt_depth, &mut skirts);
+            }
+            triangles.append(&mut skirts);
+        }
+        triangles
+    }
+
+    fn update_errors(&mut self) {
+        let tile_size = self.grid_size - 1;
+        let triangle_count = tile_size * tile_size * 2 - 2;
+        let parent_count = triangle_count - tile_size * tile_size;
+
+        self.errors.clear();
+        self.errors
+            .resize((self.grid_size * self.grid_size) as usize, 0.);
+
+        for i in (0..triangle_count).rev() {
+            let (a, b) = self.get_triangle_coords(i + 2);
+            let m = [(a[0] + b[0]) >> 1, (a[1] + b[1]) >> 1];
+            let c = [m[0] + m[1] - a[1], m[1] + a[0] - m[0]];
+
+            let interpolated = (self.get_height(a) + self.get_height(b)) / 2.;
+            let middle_index = self.get_index(m);
+            let middle_error = (interpolated - self.heights[middle_index]).abs();
+            let mut error = f32::max(self.errors[middle_index], middle_error);
+
+            if i < parent_count {
+                let left_child = [(a[0] + c[0]) >> 1, (a[1] + c[1]) >> 1];
+                let right_child = [(b[0] + c[0]) >> 1, (b[1] + c[1]) >> 1];
+                error = f32::max(error, self.errors[self.get_index(left_child)]);
+                error = f32::max(error, self.errors[self.get_index(right_child)]);
+            }
+            self.errors[middle_index] = error;
+        }
+    }
+
+    // Walks the implicit binary triangle tree down to the triangle with the given id
+    fn get_triangle_coords(&self, mut id: i32) -> ([i32; 2], [i32; 2]) {
+        let tile_size = self.grid_size - 1;
+        let (mut a, mut b, mut c) = if id & 1 != 0 {
+            ([0, 0], [tile_size, tile_size], [tile_size, 0])
+        } else {
+            ([tile_size, tile_size], [0, 0], [0, tile_size])
+        };
+        id >>= 1;
+        while id > 1 {
+            let m = [(a[0] + b[0]) >> 1, (a[1] + b[1]) >> 1];
+            if id & 1 != 0 {
+                b = a;
+                a = c;
+            } else {
+                a = b;
+                b = c;
+            }
+            c = m;
+            id >>= 1;
+        }
+        (a, b)
+    }
+
+    fn process_triangle(
+        &self,
+        a: [i32; 2],
+        b: [i32; 2],
+        c: [i32; 2],
+        max_error: f32,
+        triangles: &mut Vec<Triangle>,
+    ) {
+        let m = [(a[0] + b[0]) >> 1, (a[1] + b[1]) >> 1];
+        let splittable = (a[0] - c[0]).abs() + (a[1] - c[1]).abs() > 1;
+        if splittable && self.errors[self.get_index(m)] > max_error {
+            self.process_triangle(c, a, m, max_error, triangles);
+            self.process_triangle(b, c, m, max_error, triangles);
+        } else {
+            let mut triangle = Triangle::new([
+                self.create_vertex(a),
+                self.create_vertex(b),
+                self.create_vertex(c),
+            ]);
+            triangle.force_ccw();
+            triangle.update_triangle_normal();
+            triangles.push(triangle);
+        }
+    }
+
+    fn add_skirts(&self, triangle: &Triangle, depth: f32, skirts: &mut Vec<Triangle>) {
+        let max = ((self.grid_size - 1) as f32) * self.scale_factor;
+        let vertices = triangle.as_vertices();
+        for i in 0..3 {
+            let p = vertices[i].get_pos();
+            let q = vertices[(i + 1) % 3].get_pos();
+            let outward = match (p.as_xy(), q.as_xy()) {
+                (p, q) if p[0] == 0. && q[0] == 0. => Point3f::new(-1., 0., 0.),
+                (p, q) if p[0] == max && q[0] == max => Point3f::new(1., 0., 0.),
+                (p, q) if p[1] == 0. && q[1] == 0. => Point3f::new(0., -1., 0.),
+                (p, q) if p[1] == max && q[1] == max => Point3f::new(0., 1., 0.),
+                _ => continue,
+            };
+            let top = [vertices[i], vertices[(i + 1) % 3]];
+            let mut bottom = top;
+            for v in bottom.iter_mut() {
+                v.set_pos(v.get_pos() - Point3f::new(0., 0., depth));
+            }
+            for corners in [[top[0], bottom[0], top[1]], [top[1], bottom[0], bottom[1]]].iter() {
+                let mut skirt = Triangle::new(*corners);
+                if skirt.get_normal().dot(&outward) < 0. {
+                    let mut swapped = *corners;
+                    swapped.swap(0, 1);
+                    skirt = Triangle::new(swapped);
+                }
+                skirts.push(skirt);
+            }
+        }
+    }
+
+    fn create_vertex(&self, grid_pos: [i32; 2]) -> Vertex {
+        let index = self.get_index(grid_pos);
+        let mut vertex = Vertex::default();
+        vertex.set_pos(Point3f::new(
+            grid_pos[0] as f32 * self.scale_factor,
+            grid_pos[1] as f32 * self.scale_factor,
+            self.heights[index],
+        ));
+        vertex.set_normal(self.normals[index]);
+        vertex
+    }
+
+    fn get_height(&self, grid_pos: [i32; 2]) -> f32 {
+        self.heights[self.get_index(grid_pos)]
+    }
+
+    fn get_index(&self, grid_pos: [i32; 2]) -> usize {
+        (grid_pos[0] + grid_pos[1] * self.grid_size) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const RANDOM_SEED: u64 = 9001;
+
+    fn create_random_heightmap(size: i32) -> HeightMap {
+        let mut rng = StdRng::seed_from_u64(RANDOM_SEED);
+        let mut hm = HeightMap::new(size, 1.);
+        for y in 0..size {
+            for x in 0..size {
+                hm.set(Point2i::new(x, y), rng.gen_range(-10.0..10.0));
+            }
+        }
+        hm
+    }
+
+    fn interpolate_triangle(triangle: &Triangle, p: [f32; 2]) -> Option<f32> {
+        let v: Vec<Point3f> = triangle.as_vertices().iter().map(|v| v.get_pos()).collect();
+        let det =
+            (v[1][1] - v[2][1]) * (v[0][0] - v[2][0]) + (v[2][0] - v[1][0]) * (v[0][1] - v[2][1]);
+        let l1 =
+            ((v[1][1] - v[2][1]) * (p[0] - v[2][0]) + (v[2][0] - v[1][0]) * (p[1] - v[2][1])) / det;
+        let l2 =
+            ((v[2][1] - v[0][1]) * (p[0] - v[2][0]) + (v[0][0] - v[2][0]) * (p[1] - v[2][1])) / det;
+        let l3 = 1. - l1 - l2;
+        if l1 >= -1e-5 && l2 >= -1e-5 && l3 >= -1e-5 {
+            Some(l1 * v[0][2] + l2 * v[1][2] + l3 * v[2][2])
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn test_rtin_invalid_size() {
+        assert!(Rtin::new(&HeightMap::new(64, 1.)).is_none());
+    }
+
+    #[test]
+    fn test_rtin_flat_map_two_triangles() {
+        let rtin = Rtin::new(&HeightMap::new(65, 1.)).unwrap();
+        assert_eq!(2, rtin.triangulate(0., 0.).len());
+    }
+
+    #[test]
+    fn test_rtin_flat_map_skirts() {
+        let rtin = Rtin::new(&HeightMap::new(65, 1.)).unwrap();
+        assert_eq!(2 + 4 * 2, rtin.triangulate(0., 1.).len());
+    }
+
+    #[test]
+    fn test_rtin_zero_error_full_resolution() {
+        let rtin = Rtin::new(&create_random_heightmap(17)).unwrap();
+        assert_eq!(2 * 16 * 16, rtin.triangulate(0., 0.).len());
+    }
+
+    #[test]
+    fn test_rtin_triangles_ccw() {
+        let rtin = Rtin::new(&create_random_heightmap(33)).unwrap();
+        assert!(rtin
+            .triangulate(1., 0.)
+            .iter()
+            .all(|t| t.get_normal()[2] > 0.));
+    }
+
+    #[test]
+    fn test_rtin_error_bound() {
+        const MAX_ERROR: f32 = 2.5;
+        let hm = create_random_heightmap(33);
+        let triangles = Rtin::new(&hm).unwrap().triangulate(MAX_ERROR, 0.);
+        for y in 0..33 {
+            for x in 0..33 {
+                let p = [x as f32, y as f32];
+                let mesh_height = triangles
+                    .iter()
+                    .find_map(|t| interpolate_triangle(t, p))
+                    .unwrap();
+                assert!((mesh_height - hm.get(Point2i::new(x, y))).abs() <= MAX_ERROR + 1e-4);
+            }
+        }
+    }
+}
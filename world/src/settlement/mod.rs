@@ -0,0 +1,3 @@
+mod settlement_site;
+
+pub use self::settlement_site::{score_site, select_sites, SettlementSite};
@@ -0,0 +1,217 @@
+use std::cmp::Ordering;
+use std::f32::consts::PI;
+
+use core::{Point2f, Point3f};
+
+// Radius around a site which should be flat enough to build on
+const FLAT_RADIUS: f32 = 12.;
+// Sites with a larger height difference within the flat radius are rejected
+const MAX_HEIGHT_RANGE: f32 = 10.;
+// Water further away than this doesn't add to the score
+const WATER_RADIUS: f32 = 64.;
+const WATER_SEARCH_STEP: f32 = 8.;
+// Preferred height band above the sea level, scores fall off linearly outside of it
+const ELEVATION_BAND: [f32; 2] = [2., 80.];
+const ELEVATION_FALLOFF: f32 = 40.;
+// Preferred temperatures in degrees celsius, scores fall off linearly outside of them
+const TEMPERATURE_BAND: [f32; 2] = [5., 25.];
+const TEMPERATURE_FALLOFF: f32 = 15.;
+// Share of the air moisture rained out per climate cell, drier sites score lower.
+// A quarter of the background rain of saturated air.
+const MIN_PRECIPITATION: f32 = 0.005;
+
+const FLATNESS_WEIGHT: f32 = 0.35;
+const WATER_WEIGHT: f32 = 0.2;
+const ELEVATION_WEIGHT: f32 = 0.15;
+// Forest density only decides about clearings, the biome is given by the climate
+const FOREST_WEIGHT: f32 = 0.1;
+const CLIMATE_WEIGHT: f32 = 0.2;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SettlementSite {
+    pos: Point3f,
+    score: f32,
+}
+
+impl SettlementSite {
+    pub fn new(pos: Point3f, score: f32) -> Self {
+        Self {
+            pos: pos,
+            score: score,
+        }
+    }
+
+    pub fn get_pos(&self) -> Point3f {
+        self.pos
+    }
+
+    /// Between 0 and 1, higher is better
+    pub fn get_score(&self) -> f32 {
+        self.score
+    }
+}
+
+/// Suitability of a position for a settlement, none if it's underwater or too steep.
+/// The forest function gives the forest density between 0 and 1, clearings are preferred.
+/// The climate function gives the temperature and the precipitation, temperate and wet
+/// climates are preferred.
+pub fn score_site<H: Fn(Point2f) -> f32, F: Fn(Point2f) -> f32, C: Fn(Point2f) -> [f32; 2]>(
+    height_fn: H,
+    forest_fn: F,
+    climate_fn: C,
+    pos: Point2f,
+    sea_level: f32,
+) -> Option<f32> {
+    let height = height_fn(pos);
+    let elevation = height - sea_level;
+    if elevation < ELEVATION_BAND[0] {
+        return None;
+    }
+
+    let height_range = get_ring(pos, FLAT_RADIUS)
+        .map(|p| (height_fn(p) - height).abs())
+        .fold(0., f32::max);
+    if height_range > MAX_HEIGHT_RANGE {
+        return None;
+    }
+    let flatness = 1. - height_range / MAX_HEIGHT_RANGE;
+
+    let mut water = 0.;
+    let mut radius = WATER_SEARCH_STEP;
+    while radius <= WATER_RADIUS {
+        if get_ring(pos, radius).any(|p| height_fn(p) < sea_level) {
+            water = 1. - (radius - WATER_SEARCH_STEP) / WATER_RADIUS;
+            break;
+        }
+        radius += WATER_SEARCH_STEP;
+    }
+
+    let band_distance = if elevation > ELEVATION_BAND[1] {
+        elevation - ELEVATION_BAND[1]
+    } else {
+        0.
+    };
+    let elevation_score = f32::max(0., 1. - band_distance / ELEVATION_FALLOFF);
+    let forest = 1. - forest_fn(pos).max(0.).min(1.);
+
+    let [temperature, precipitation] = climate_fn(pos);
+    let temperature_distance = (TEMPERATURE_BAND[0] - temperature)
+        .max(temperature - TEMPERATURE_BAND[1])
+        .max(0.);
+    let climate = f32::max(0., 1. - temperature_distance / TEMPERATURE_FALLOFF)
+        * (precipitation / MIN_PRECIPITATION).max(0.).min(1.);
+
+    Some(
+        FLATNESS_WEIGHT * flatness
+            + WATER_WEIGHT * water
+            + ELEVATION_WEIGHT * elevation_score
+            + FOREST_WEIGHT * forest
+            + CLIMATE_WEIGHT * climate,
+    )
+}
+
+/// Greedily picks the best candidates which keep the minimum distance to all picked sites
+pub fn select_sites(
+    mut candidates: Vec<SettlementSite>,
+    min_distance: f32,
+    max_count: usize,
+) -> Vec<SettlementSite> {
+    candidates.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.pos.cmp(&b.pos))
+    });
+    let mut sites: Vec<SettlementSite> = Vec::new();
+    for candidate in candidates {
+        if sites.len() >= max_count {
+            break;
+        }
+        let candidate_pos = candidate.pos.as_xy();
+        if sites
+            .iter()
+            .all(|s| (s.pos.as_xy() - candidate_pos).length() >= min_distance)
+        {
+            sites.push(candidate);
+        }
+    }
+    sites
+}
+
+fn get_ring(center: Point2f, radius: f32) -> impl Iterator<Item = Point2f> {
+    (0..8).map(move |i| {
+        let angle = i as f32 * PI / 4.;
+        center + Point2f::new(angle.cos(), angle.sin()) * radius
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn temperate(_: Point2f) -> [f32; 2] {
+        [15., 0.02]
+    }
+
+    #[test]
+    fn test_flat_land_beats_slope() {
+        let flat = score_site(|_| 10., |_| 0., temperate, Point2f::new(0., 0.), 0.).unwrap();
+        let sloped = score_site(
+            |p| 10. + p[0] * 0.5,
+            |_| 0.,
+            temperate,
+            Point2f::new(0., 0.),
+            0.,
+        )
+        .unwrap();
+        assert!(flat > sloped);
+    }
+
+    #[test]
+    fn test_underwater_and_steep_rejected() {
+        assert!(score_site(|_| -1., |_| 0., temperate, Point2f::new(0., 0.), 0.).is_none());
+        assert!(score_site(
+            |p| 10. + p[0] * 2.,
+            |_| 0.,
+            temperate,
+            Point2f::new(0., 0.),
+            0.
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_nearby_water_preferred() {
+        let shore = |p: Point2f| if p[0] > 20. { -5. } else { 10. };
+        let near = score_site(shore, |_| 0., temperate, Point2f::new(0., 0.), 0.).unwrap();
+        let far = score_site(shore, |_| 0., temperate, Point2f::new(-100., 0.), 0.).unwrap();
+        assert!(near > far);
+    }
+
+    #[test]
+    fn test_temperate_climate_preferred() {
+        let score = |climate: [f32; 2]| {
+            score_site(|_| 10., |_| 0., |_| climate, Point2f::new(0., 0.), 0.).unwrap()
+        };
+        let temperate = score([15., 0.02]);
+        assert!(temperate > score([-20., 0.02]));
+        assert!(temperate > score([40., 0.02]));
+        assert!(temperate > score([15., 0.]));
+        assert_eq!(temperate, score([20., 0.05]));
+    }
+
+    #[test]
+    fn test_selection_spacing() {
+        let candidates: Vec<SettlementSite> = (0..20)
+            .map(|i| SettlementSite::new(Point3f::new(i as f32 * 10., 0., 0.), i as f32))
+            .collect();
+        let sites = select_sites(candidates, 35., 100);
+        assert_eq!(190., sites[0].get_pos()[0]);
+        for (i, a) in sites.iter().enumerate() {
+            for b in sites.iter().skip(i + 1) {
+                assert!((a.get_pos() - b.get_pos()).length() >= 35.);
+            }
+        }
+    }
+}
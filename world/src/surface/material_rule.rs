@@ -0,0 +1,136 @@
+use serde::Deserialize;
+
+/// Conditions under which a material covers the terrain.
+/// Each range is given as [min, max, fade], outside of [min, max]
+/// the coverage fades out linearly over the fade distance.
+/// Missing ranges don't restrict the coverage.
+#[derive(Deserialize, Clone, Default, Debug)]
+pub struct MaterialRule {
+    height: Option<[f32; 3]>,
+    /// In degrees
+    slope: Option<[f32; 3]>,
+    /// Topographic wetness index, ignored where no terrain analysis is available
+    wetness: Option<[f32; 3]>,
+    /// In degrees celsius, ignored where no climate is available
+    temperature: Option<[f32; 3]>,
+    /// Share of the air moisture rained out per climate cell, ignored where no climate is available
+    precipitation: Option<[f32; 3]>,
+}
+
+impl MaterialRule {
+    pub fn with_height(mut self, range: [f32; 3]) -> Self {
+        self.height = Some(range);
+        self
+    }
+
+    pub fn with_slope(mut self, range: [f32; 3]) -> Self {
+        self.slope = Some(range);
+        self
+    }
+
+    pub fn with_wetness(mut self, range: [f32; 3]) -> Self {
+        self.wetness = Some(range);
+        self
+    }
+
+    pub fn with_temperature(mut self, range: [f32; 3]) -> Self {
+        self.temperature = Some(range);
+        self
+    }
+
+    pub fn with_precipitation(mut self, range: [f32; 3]) -> Self {
+        self.precipitation = Some(range);
+        self
+    }
+
+    pub fn uses_climate(&self) -> bool {
+        self.temperature.is_some() || self.precipitation.is_some()
+    }
+
+    /// Between 0 and 1, slope in radians, climate as temperature and precipitation
+    pub fn get_coverage(
+        &self,
+        height: f32,
+        slope: f32,
+        wetness: Option<f32>,
+        climate: Option<[f32; 2]>,
+    ) -> f32 {
+        let mut coverage = 1.;
+        if let Some(range) = self.height {
+            coverage *= get_range_weight(height, range);
+        }
+        if let Some(range) = self.slope {
+            coverage *= get_range_weight(slope.to_degrees(), range);
+        }
+        if let (Some(range), Some(wetness)) = (self.wetness, wetness) {
+            coverage *= get_range_weight(wetness, range);
+        }
+        if let Some([temperature, precipitation]) = climate {
+            if let Some(range) = self.temperature {
+                coverage *= get_range_weight(temperature, range);
+            }
+            if let Some(range) = self.precipitation {
+                coverage *= get_range_weight(precipitation, range);
+            }
+        }
+        coverage
+    }
+}
+
+fn get_range_weight(value: f32, range: [f32; 3]) -> f32 {
+    let [min, max, fade] = range;
+    let distance = if value < min {
+        min - value
+    } else if value > max {
+        value - max
+    } else {
+        return 1.;
+    };
+    if fade > 0. {
+        f32::max(0., 1. - distance / fade)
+    } else {
+        0.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_empty_rule_covers_everything() {
+        assert_eq!(
+            1.,
+            MaterialRule::default().get_coverage(-50., 1., None, None)
+        );
+    }
+
+    #[test]
+    fn test_range_fades_out() {
+        let rule = MaterialRule::default().with_height([0., 10., 4.]);
+        assert_eq!(1., rule.get_coverage(5., 0., None, None));
+        assert_eq!(0.5, rule.get_coverage(12., 0., None, None));
+        assert_eq!(0., rule.get_coverage(-4., 0., None, None));
+    }
+
+    #[test]
+    fn test_missing_wetness_ignored() {
+        let rule = MaterialRule::default().with_wetness([10., 20., 0.]);
+        assert_eq!(1., rule.get_coverage(0., 0., None, None));
+        assert_eq!(0., rule.get_coverage(0., 0., Some(5.), None));
+    }
+
+    #[test]
+    fn test_climate_ranges() {
+        let rule = MaterialRule::default()
+            .with_temperature([-50., 0., 10.])
+            .with_precipitation([0.01, 1., 0.]);
+        assert!(rule.uses_climate());
+        assert!(!MaterialRule::default().uses_climate());
+        assert_eq!(1., rule.get_coverage(0., 0., None, None));
+        assert_eq!(1., rule.get_coverage(0., 0., None, Some([-10., 0.05])));
+        assert_eq!(0.5, rule.get_coverage(0., 0., None, Some([5., 0.05])));
+        assert_eq!(0., rule.get_coverage(0., 0., None, Some([-10., 0.001])));
+    }
+}
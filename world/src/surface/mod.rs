@@ -0,0 +1,7 @@
+mod material_rule;
+mod splat_map;
+mod surface_info;
+
+pub use self::material_rule::MaterialRule;
+pub use self::splat_map::{SplatMap, SPLAT_CHANNELS};
+pub use self::surface_info::SurfaceInfo;
@@ -0,0 +1,148 @@
+use std::convert::TryInto;
+
+use super::MaterialRule;
+use crate::analysis::{AnalysisLayer, TerrainAnalysis};
+use crate::{HeightMap, HeightSampler};
+use core::{GraphicsError, Point2f, Point2i, Texture, TextureBuilder};
+
+/// Materials which can be blended per texel, one per channel of the splat texture
+pub const SPLAT_CHANNELS: usize = 4;
+
+/// Material weights per heightmap grid point, summing up to one
+#[derive(Clone)]
+pub struct SplatMap {
+    size: i32,
+    weight_list: Vec<[f32; SPLAT_CHANNELS]>,
+    climate_list: Option<Vec<[f32; 2]>>,
+}
+
+impl SplatMap {
+    /// The first rule is the base material, every following material
+    /// is layered on top of the previous ones by its coverage.
+    /// The climate is given as temperature and precipitation per grid point.
+    pub fn from_heightmap(
+        heightmap: &HeightMap,
+        analysis: Option<&TerrainAnalysis>,
+        climate: Option<&[[f32; 2]]>,
+        rules: &[MaterialRule],
+    ) -> Self {
+        debug_assert!(rules.len() <= SPLAT_CHANNELS);
+        debug_assert!(climate.map_or(true, |c| c.len() == heightmap.get_list().len()));
+        let size = heightmap.get_size();
+        let scale_factor = heightmap.get_scale_factor();
+        let wetness = analysis.map(|a| a.get_layer(AnalysisLayer::Wetness));
+        let mut weight_list = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let grid_pos = Point2i::new(x, y);
+                let height = heightmap.get(grid_pos);
+                let slope = heightmap.sample_slope(Point2f::from(grid_pos) * scale_factor);
+                let wetness = wetness.map(|w| w.get(grid_pos));
+                let climate = climate.map(|c| c[(x + y * size) as usize]);
+
+                let mut weights = [0.; SPLAT_CHANNELS];
+                weights[0] = 1.;
+                for (i, rule) in rules.iter().enumerate().skip(1) {
+                    let coverage = rule.get_coverage(height, slope, wetness, climate);
+                    for w in weights[..i].iter_mut() {
+                        *w *= 1. - coverage;
+                    }
+                    weights[i] = coverage;
+                }
+                weight_list.push(weights);
+            }
+        }
+        Self {
+            size: size,
+            weight_list: weight_list,
+            climate_list: climate.map(|c| c.to_vec()),
+        }
+    }
+
+    /// Climate the map was built with, kept for rebuilding the map after edits
+    pub fn get_climate(&self) -> Option<&[[f32; 2]]> {
+        self.climate_list.as_deref()
+    }
+
+    pub fn get_size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn get_weights(&self, pos: Point2i) -> [f32; SPLAT_CHANNELS] {
+        debug_assert!(pos[0] >= 0 && pos[1] >= 0 && pos[0] < self.size && pos[1] < self.size);
+        self.weight_list[(pos[0] + pos[1] * self.size) as usize]
+    }
+
+    /// Index of the material with the highest weight
+    pub fn get_dominant(&self, pos: Point2i) -> usize {
+        let weights = self.get_weights(pos);
+        (1..SPLAT_CHANNELS).fold(
+            0,
+            |best, i| if weights[i] > weights[best] { i } else { best },
+        )
+    }
+
+    pub fn write_texture(&self, texture: &Texture) -> Result<(), GraphicsError> {
+        let packed: Vec<f32> = self.weight_list.iter().flatten().copied().collect();
+        texture.write_data(packed.as_slice())?;
+        Ok(())
+    }
+}
+
+impl TryInto<Texture> for &SplatMap {
+    type Error = GraphicsError;
+    fn try_into(self) -> Result<Texture, Self::Error> {
+        let texture = TextureBuilder::new_2d(Point2i::from_scalar(self.size))
+            .format_rgba32f()
+            .finish()?;
+        self.write_texture(&texture)?;
+        Ok(texture)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn create_ramp_heightmap() -> HeightMap {
+        let mut hm = HeightMap::new(17, 1.);
+        for y in 0..17 {
+            for x in 0..17 {
+                hm.set(Point2i::new(x, y), x as f32 * 2.);
+            }
+        }
+        hm
+    }
+
+    #[test]
+    fn test_weights_sum_to_one() {
+        let rules = [
+            MaterialRule::default(),
+            MaterialRule::default().with_height([0., 10., 5.]),
+            MaterialRule::default().with_height([20., 100., 10.]),
+        ];
+        let splat = SplatMap::from_heightmap(&create_ramp_heightmap(), None, None, &rules);
+        for x in 0..17 {
+            let sum: f32 = splat.get_weights(Point2i::new(x, 8)).iter().sum();
+            assert!((sum - 1.).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_later_rules_on_top() {
+        let rules = [
+            MaterialRule::default(),
+            MaterialRule::default().with_height([-100., 10., 0.]),
+            MaterialRule::default().with_slope([30., 90., 0.]),
+        ];
+        let splat = SplatMap::from_heightmap(&create_ramp_heightmap(), None, None, &rules);
+        // the ramp is steeper than 60°, so rock covers everything
+        assert_eq!(2, splat.get_dominant(Point2i::new(2, 8)));
+        assert_eq!(2, splat.get_dominant(Point2i::new(14, 8)));
+        let flat_rules = [rules[0].clone(), rules[1].clone()];
+        let splat = SplatMap::from_heightmap(&create_ramp_heightmap(), None, None, &flat_rules);
+        assert_eq!(1, splat.get_dominant(Point2i::new(2, 8)));
+        assert_eq!(0, splat.get_dominant(Point2i::new(14, 8)));
+    }
+}
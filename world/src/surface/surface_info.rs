@@ -0,0 +1,107 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use super::{MaterialRule, SPLAT_CHANNELS};
+use crate::chunk::ChunkError;
+use core::file::{read_file, read_image_rgba};
+use core::{GraphicsError, Point2i, Point3i, Texture, TextureBuilder};
+
+#[derive(Deserialize)]
+struct SurfaceFile {
+    texture_size: i32,
+    path: String,
+    /// Atlas column, atlas row and material order
+    surface_coordinates: BTreeMap<String, [i32; 3]>,
+    #[serde(default)]
+    surface_rules: BTreeMap<String, MaterialRule>,
+}
+
+struct SurfaceMaterial {
+    name: String,
+    atlas_pos: Point2i,
+    rule: MaterialRule,
+}
+
+/// Surface materials with their atlas tiles and splat rules
+pub struct SurfaceInfo {
+    texture_size: i32,
+    atlas_path: String,
+    material_list: Vec<SurfaceMaterial>,
+}
+
+impl SurfaceInfo {
+    pub fn from_yaml(file_path: &str) -> Result<Self, ChunkError> {
+        let content = read_file(file_path)?;
+        let parsed_file: SurfaceFile = serde_yaml::from_str(&content)?;
+        let mut rules = parsed_file.surface_rules;
+
+        let mut coordinates: Vec<(String, [i32; 3])> =
+            parsed_file.surface_coordinates.into_iter().collect();
+        coordinates.sort_by_key(|(_, c)| c[2]);
+        if coordinates.len() > SPLAT_CHANNELS {
+            warn!(
+                "Only {} surface materials supported, ignoring {}",
+                SPLAT_CHANNELS,
+                coordinates.len() - SPLAT_CHANNELS
+            );
+            coordinates.truncate(SPLAT_CHANNELS);
+        }
+
+        let material_list: Vec<SurfaceMaterial> = coordinates
+            .into_iter()
+            .map(|(name, c)| {
+                let rule = rules.remove(&name).unwrap_or_default();
+                SurfaceMaterial {
+                    name: name,
+                    atlas_pos: Point2i::new(c[0], c[1]),
+                    rule: rule,
+                }
+            })
+            .collect();
+        for name in rules.keys() {
+            warn!("Surface rule for unknown material '{}'", name);
+        }
+        info!(
+            "Loaded {} surface materials from '{}'",
+            material_list.len(),
+            file_path
+        );
+
+        Ok(Self {
+            texture_size: parsed_file.texture_size,
+            atlas_path: parsed_file.path,
+            material_list: material_list,
+        })
+    }
+
+    pub fn get_material_count(&self) -> usize {
+        self.material_list.len()
+    }
+
+    pub fn get_material_name(&self, index: usize) -> Option<&str> {
+        self.material_list.get(index).map(|m| m.name.as_str())
+    }
+
+    /// In material order, the first material being the base
+    pub fn get_rules(&self) -> Vec<MaterialRule> {
+        self.material_list.iter().map(|m| m.rule.clone()).collect()
+    }
+
+    /// One layer per material, in material order
+    pub fn create_texture_array(&self) -> Result<Texture, GraphicsError> {
+        let img = read_image_rgba(&self.atlas_path)?;
+        let layers = self.material_list.len().max(1) as i32;
+        let mut texture = TextureBuilder::new_2d_array(Point3i::new(
+            self.texture_size,
+            self.texture_size,
+            layers,
+        ))
+        .use_mipmaps()
+        .format_rgba8()
+        .finish()?;
+        for (layer, material) in self.material_list.iter().enumerate() {
+            texture.load_array_layer(layer as i32, material.atlas_pos * self.texture_size, &img)?;
+        }
+        Ok(texture)
+    }
+}
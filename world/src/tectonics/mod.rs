@@ -0,0 +1,7 @@
+mod plate;
+mod plate_map;
+mod plate_noise;
+
+pub use self::plate::{Plate, PlateKind};
+pub use self::plate_map::{Boundary, BoundaryKind, PlateMap};
+pub use self::plate_noise::PlateNoise;
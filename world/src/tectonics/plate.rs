@@ -0,0 +1,51 @@
+use core::{Point2d, Point2f, Point2i};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlateKind {
+    Oceanic,
+    Continental,
+}
+
+/// Voronoi cell of the plate map, moving as a whole
+#[derive(Clone, Copy, Debug)]
+pub struct Plate {
+    cell: Point2i,
+    center: Point2d,
+    kind: PlateKind,
+    motion: Point2f,
+}
+
+impl Plate {
+    pub fn new(cell: Point2i, center: Point2d, kind: PlateKind, motion: Point2f) -> Self {
+        Self {
+            cell: cell,
+            center: center,
+            kind: kind,
+            motion: motion,
+        }
+    }
+
+    /// Cell of the plate map grid the plate center lies in, identifies the plate
+    pub fn get_cell(&self) -> Point2i {
+        self.cell
+    }
+
+    pub fn get_center(&self) -> Point2d {
+        self.center
+    }
+
+    pub fn get_kind(&self) -> PlateKind {
+        self.kind
+    }
+
+    /// Direction and speed of the plate, the speed is at most 1
+    pub fn get_motion(&self) -> Point2f {
+        self.motion
+    }
+}
+
+impl PartialEq for Plate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cell == other.cell
+    }
+}
@@ -0,0 +1,373 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::f32::consts::PI;
+
+use super::{Plate, PlateKind};
+use core::{Point2d, Point2f, Point2i, Seed};
+
+// Extent of the grid cells containing one plate center each, in world units
+const DEFAULT_PLATE_SIZE: f32 = 4096.;
+// Plate centers are jittered within the middle of their cell only, so the two nearest plates
+// of any position closer to a boundary than PLATE_SIZE / 8 are within the 3x3 neighbouring cells
+const CENTER_JITTER: [f32; 2] = [0.3, 0.7];
+const CONTINENTAL_RATIO: f32 = 0.4;
+
+const CONTINENTAL_ELEVATION: f32 = 20.;
+const OCEANIC_ELEVATION: f32 = -60.;
+// Distance from the boundary over which the base elevations of both plates are blended
+const SHELF_WIDTH: f32 = 384.;
+
+// Distance from the boundary affected by the plate movement, at most an eighth of the plate size
+const BOUNDARY_WIDTH: f32 = 512.;
+const COLLISION_HEIGHT: f32 = 160.;
+const ARC_HEIGHT: f32 = 100.;
+const TRENCH_DEPTH: f32 = 80.;
+const RIFT_DEPTH: f32 = 30.;
+const RIDGE_HEIGHT: f32 = 25.;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BoundaryKind {
+    /// Plates move towards each other, raising mountains or subducting into a trench
+    Convergent,
+    /// Plates move apart, opening a rift or a mid-ocean ridge
+    Divergent,
+    /// Plates slide past each other
+    Transform,
+}
+
+/// Boundary between a plate and one of its neighbours, as seen from a position on the plate
+#[derive(Clone, Copy, Debug)]
+pub struct Boundary {
+    plate: Plate,
+    neighbour: Plate,
+    distance: f32,
+    convergence: f32,
+}
+
+impl Boundary {
+    pub fn get_plate(&self) -> Plate {
+        self.plate
+    }
+
+    pub fn get_neighbour(&self) -> Plate {
+        self.neighbour
+    }
+
+    /// Distance of the position to the boundary in world units
+    pub fn get_distance(&self) -> f32 {
+        self.distance
+    }
+
+    /// Relative speed of the plates towards each other, negative if they move apart
+    pub fn get_convergence(&self) -> f32 {
+        self.convergence
+    }
+
+    pub fn get_kind(&self) -> BoundaryKind {
+        let normal = self.get_normal();
+        let relative_motion = self.plate.get_motion() - self.neighbour.get_motion();
+        let shear = (relative_motion - normal * self.convergence).length();
+        if self.convergence.abs() < shear {
+            BoundaryKind::Transform
+        } else if self.convergence > 0. {
+            BoundaryKind::Convergent
+        } else {
+            BoundaryKind::Divergent
+        }
+    }
+
+    // Unit vector from the plate towards its neighbour
+    fn get_normal(&self) -> Point2f {
+        (self.neighbour.get_center() - self.plate.get_center())
+            .as_f32()
+            .as_normalized()
+    }
+
+    // Elevation change by the plate movement, the same on both sides at the boundary
+    fn get_uplift(&self) -> f32 {
+        let t = self.distance / BOUNDARY_WIDTH;
+        if t >= 1. {
+            return 0.;
+        }
+        let strength = self.convergence.abs().min(1.);
+        // highest at the boundary
+        let peak = (1. - t).powi(2);
+        // highest halfway into the boundary zone, zero at the boundary
+        let offset = (PI * t).sin();
+        let kinds = (self.plate.get_kind(), self.neighbour.get_kind());
+        let amount = match self.get_kind() {
+            BoundaryKind::Transform => 0.,
+            BoundaryKind::Divergent => match kinds {
+                (PlateKind::Continental, PlateKind::Continental) => -RIFT_DEPTH * peak,
+                _ => RIDGE_HEIGHT * peak,
+            },
+            BoundaryKind::Convergent => match kinds {
+                (PlateKind::Continental, PlateKind::Continental) => COLLISION_HEIGHT * peak,
+                (PlateKind::Oceanic, PlateKind::Continental) => -TRENCH_DEPTH * offset,
+                (PlateKind::Continental, PlateKind::Oceanic) => ARC_HEIGHT * offset,
+                (PlateKind::Oceanic, PlateKind::Oceanic) => {
+                    // the plate approaching faster subducts, the other one forms an island arc
+                    let normal = self.get_normal();
+                    let approach = self.plate.get_motion().dot(&normal);
+                    let neighbour_approach = -self.neighbour.get_motion().dot(&normal);
+                    if approach > neighbour_approach {
+                        -TRENCH_DEPTH * offset
+                    } else {
+                        ARC_HEIGHT * offset
+                    }
+                }
+            },
+        };
+        amount * strength
+    }
+}
+
+/// Tectonic plates as Voronoi cells of jittered points on a coarse grid.
+/// Only depends on the seed, plates are generated on demand.
+#[derive(Clone)]
+pub struct PlateMap {
+    seed: Seed,
+    plate_size: f32,
+}
+
+impl PlateMap {
+    pub fn from_seed(seed: Seed) -> Self {
+        Self {
+            seed: seed,
+            plate_size: DEFAULT_PLATE_SIZE,
+        }
+    }
+
+    /// The plate size has to be at least eight times the boundary width
+    pub fn with_plate_size(mut self, plate_size: f32) -> Self {
+        debug_assert!(plate_size >= 8. * BOUNDARY_WIDTH);
+        self.plate_size = plate_size;
+        self
+    }
+
+    pub fn get_plate_size(&self) -> f32 {
+        self.plate_size
+    }
+
+    pub fn get_plate(&self, cell: Point2i) -> Plate {
+        let mut rng: StdRng = self.seed.mix_with_point(cell).into();
+        let jitter = Point2d::new(
+            rng.gen_range(CENTER_JITTER[0]..CENTER_JITTER[1]) as f64,
+            rng.gen_range(CENTER_JITTER[0]..CENTER_JITTER[1]) as f64,
+        );
+        let center = (Point2d::from(cell) + jitter) * self.plate_size as f64;
+        let kind = if rng.gen_range(0.0..1.0) < CONTINENTAL_RATIO {
+            PlateKind::Continental
+        } else {
+            PlateKind::Oceanic
+        };
+        let angle: f32 = rng.gen_range(0.0..2. * PI);
+        let speed: f32 = rng.gen_range(0.0..1.0);
+        let motion = Point2f::new(angle.cos(), angle.sin()) * speed;
+        Plate::new(cell, center, kind, motion)
+    }
+
+    /// Plate containing the position
+    pub fn get_plate_at(&self, absolute_pos: Point2d) -> Plate {
+        self.get_boundary(absolute_pos).get_plate()
+    }
+
+    /// Nearest boundary of the plate containing the position
+    pub fn get_boundary(&self, absolute_pos: Point2d) -> Boundary {
+        let candidates = self.get_candidates(absolute_pos);
+        let plate = candidates[0].1;
+        candidates[1..]
+            .iter()
+            .map(|(_, neighbour)| create_boundary(plate, *neighbour, absolute_pos))
+            .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap())
+            .unwrap()
+    }
+
+    /// Base elevations of the plates blended towards their boundaries, plus mountains, rifts
+    /// and trenches formed by the plate movement. Near a junction of several plates the
+    /// uplift of every boundary in reach is blended, so the elevation stays continuous.
+    pub fn get_elevation(&self, absolute_pos: Point2d) -> f32 {
+        let candidates = self.get_candidates(absolute_pos);
+        let nearest = candidates[0].0;
+
+        // plates weighted by how much further their center is than the nearest one,
+        // halfway between two centers both weigh the same
+        let mut base = 0.;
+        let mut base_weight = 0.;
+        for (distance, plate) in candidates.iter() {
+            let weight = (1. - (distance - nearest) / (2. * SHELF_WIDTH))
+                .max(0.)
+                .powi(2);
+            base += weight * get_base_elevation(plate.get_kind());
+            base_weight += weight;
+        }
+
+        // boundaries between the nearest plate and its neighbours have full weight,
+        // the ones between neighbours fade out with the distance to the nearest plate
+        let mut raise: f32 = 0.;
+        let mut lower: f32 = 0.;
+        for (i, (distance, plate)) in candidates.iter().enumerate() {
+            let weight = 1. - (distance - nearest) / BOUNDARY_WIDTH;
+            if weight <= 0. {
+                break;
+            }
+            for (_, neighbour) in candidates[i + 1..].iter() {
+                let uplift =
+                    weight * create_boundary(*plate, *neighbour, absolute_pos).get_uplift();
+                raise = raise.max(uplift);
+                lower = lower.min(uplift);
+            }
+        }
+        base / base_weight + raise + lower
+    }
+
+    pub fn get_elevation_range(&self) -> [f32; 2] {
+        [
+            OCEANIC_ELEVATION - TRENCH_DEPTH,
+            CONTINENTAL_ELEVATION + COLLISION_HEIGHT,
+        ]
+    }
+
+    // Plates of the neighbouring cells by the distance to their center, nearest first
+    fn get_candidates(&self, absolute_pos: Point2d) -> Vec<(f32, Plate)> {
+        let size = self.plate_size as f64;
+        let cell = Point2i::new(
+            (absolute_pos[0] / size).floor() as i32,
+            (absolute_pos[1] / size).floor() as i32,
+        );
+        let mut candidates: Vec<(f32, Plate)> = Vec::with_capacity(9);
+        for y in -1..2 {
+            for x in -1..2 {
+                let plate = self.get_plate(cell + Point2i::new(x, y));
+                let distance = (plate.get_center() - absolute_pos).length() as f32;
+                candidates.push((distance, plate));
+            }
+        }
+        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        candidates
+    }
+}
+
+// Boundary seen from the plate, the position lies on its side of the bisector
+fn create_boundary(plate: Plate, neighbour: Plate, absolute_pos: Point2d) -> Boundary {
+    let offset = neighbour.get_center() - plate.get_center();
+    let center_distance = offset.length();
+    // distance to the bisector of both centers
+    let distance = ((neighbour.get_center() - absolute_pos).length().powi(2)
+        - (plate.get_center() - absolute_pos).length().powi(2))
+        / (2. * center_distance);
+    let normal = offset.as_f32().as_normalized();
+    let convergence = (plate.get_motion() - neighbour.get_motion()).dot(&normal);
+    Boundary {
+        plate: plate,
+        neighbour: neighbour,
+        distance: distance as f32,
+        convergence: convergence,
+    }
+}
+
+fn get_base_elevation(kind: PlateKind) -> f32 {
+    match kind {
+        PlateKind::Continental => CONTINENTAL_ELEVATION,
+        PlateKind::Oceanic => OCEANIC_ELEVATION,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plate_center_at_base_elevation() {
+        let plates = PlateMap::from_seed(Seed::from_string("PLATES"));
+        for x in -3..3 {
+            let plate = plates.get_plate(Point2i::new(x, 1));
+            assert_eq!(plate, plates.get_plate_at(plate.get_center()));
+            assert_eq!(
+                get_base_elevation(plate.get_kind()),
+                plates.get_elevation(plate.get_center())
+            );
+        }
+    }
+
+    #[test]
+    fn test_elevation_continuous_across_boundary() {
+        let plates = PlateMap::from_seed(Seed::from_string("PLATES"));
+        for x in -3..3 {
+            let a = plates.get_plate(Point2i::new(x, 0)).get_center();
+            let b = plates.get_plate(Point2i::new(x + 1, 0)).get_center();
+            // bisect the segment between both centers for the point where the plate changes
+            let mut lower = 0.;
+            let mut upper = 1.;
+            let start = plates.get_plate_at(a);
+            for _ in 0..40 {
+                let t = (lower + upper) / 2.;
+                if plates.get_plate_at(a + (b - a) * t) == start {
+                    lower = t;
+                } else {
+                    upper = t;
+                }
+            }
+            let before = plates.get_elevation(a + (b - a) * lower);
+            let after = plates.get_elevation(a + (b - a) * upper);
+            assert!((before - after).abs() < 1e-2, "{} != {}", before, after);
+        }
+    }
+
+    // Center of the circle through the three points
+    fn get_circumcenter(a: Point2d, b: Point2d, c: Point2d) -> Point2d {
+        let (b, c) = (b - a, c - a);
+        let d = 2. * (b[0] * c[1] - b[1] * c[0]);
+        let (b_sq, c_sq) = (b[0] * b[0] + b[1] * b[1], c[0] * c[0] + c[1] * c[1]);
+        a + Point2d::new(
+            (c[1] * b_sq - b[1] * c_sq) / d,
+            (b[0] * c_sq - c[0] * b_sq) / d,
+        )
+    }
+
+    #[test]
+    fn test_elevation_continuous_through_triple_junction() {
+        let plates = PlateMap::from_seed(Seed::from_string("PLATES"));
+        let mut junctions = 0;
+        for x in -3..3 {
+            let cells = [
+                Point2i::new(x, 0),
+                Point2i::new(x + 1, 0),
+                Point2i::new(x, 1),
+            ];
+            let centers: Vec<Point2d> = cells
+                .iter()
+                .map(|c| plates.get_plate(*c).get_center())
+                .collect();
+            let junction = get_circumcenter(centers[0], centers[1], centers[2]);
+            // the three plates have to be the nearest ones at the circumcenter
+            let candidates = plates.get_candidates(junction);
+            if !candidates[..3]
+                .iter()
+                .all(|(_, plate)| cells.contains(&plate.get_cell()))
+            {
+                continue;
+            }
+            junctions += 1;
+            for direction in [Point2d::new(1., 0.), Point2d::new(0.6, 0.8)].iter() {
+                let step = 0.25;
+                let mut previous: Option<f32> = None;
+                for i in -4000..4000 {
+                    let pos = junction + *direction * (i as f64 * step);
+                    let elevation = plates.get_elevation(pos);
+                    if let Some(previous) = previous {
+                        assert!(
+                            (elevation - previous).abs() < 1.,
+                            "jump from {} to {} at {:?}",
+                            previous,
+                            elevation,
+                            pos
+                        );
+                    }
+                    previous = Some(elevation);
+                }
+            }
+        }
+        assert!(junctions > 0);
+    }
+}
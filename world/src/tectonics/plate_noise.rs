@@ -0,0 +1,41 @@
+use super::PlateMap;
+use crate::noise::Noise;
+use core::{Point2d, Point2f};
+
+/// Detail noise on top of the elevation of the tectonic plates
+pub struct PlateNoise {
+    plates: PlateMap,
+    detail_noise: Box<dyn Noise>,
+}
+
+impl PlateNoise {
+    pub fn new(plates: PlateMap, detail_noise: Box<dyn Noise>) -> Self {
+        Self {
+            plates: plates,
+            detail_noise: detail_noise,
+        }
+    }
+}
+
+impl Noise for PlateNoise {
+    fn get_noise(&self, point: Point2f) -> f32 {
+        self.get_noise_precise(Point2d::from(point))
+    }
+
+    fn get_noise_precise(&self, point: Point2d) -> f32 {
+        self.plates.get_elevation(point) + self.detail_noise.get_noise_precise(point)
+    }
+
+    fn get_range(&self) -> [f32; 2] {
+        let plate_range = self.plates.get_elevation_range();
+        let detail_range = self.detail_noise.get_range();
+        [
+            plate_range[0] + detail_range[0],
+            plate_range[1] + detail_range[1],
+        ]
+    }
+
+    fn get_cycle(&self) -> Point2f {
+        Point2f::from_scalar(f32::INFINITY)
+    }
+}
@@ -0,0 +1,190 @@
+use core::graphics::mesh::Triangle;
+use core::{Point3f, Point3i};
+
+use super::polygonize;
+
+/// Regular grid of density samples, one world unit apart along z and
+/// the grid spacing apart on the xy-plane.
+/// Positive densities are solid, the surface lies at density zero.
+/// The x and y axis are relative to the chunk origin, z is absolute.
+#[derive(Clone)]
+pub struct DensityField {
+    size: i32,
+    z_origin: i32,
+    layers: i32,
+    spacing: f32,
+    value_list: Vec<f32>,
+}
+
+impl DensityField {
+    pub fn new(size: i32, z_origin: i32, layers: i32) -> Self {
+        debug_assert!(size > 1 && layers > 1);
+        let mut value_list = Vec::new();
+        value_list.resize((size * size * layers) as usize, -1.);
+        Self {
+            size: size,
+            z_origin: z_origin,
+            layers: layers,
+            spacing: 1.,
+            value_list: value_list,
+        }
+    }
+
+    pub fn with_spacing(mut self, spacing: f32) -> Self {
+        debug_assert!(spacing > 0.);
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn get_spacing(&self) -> f32 {
+        self.spacing
+    }
+
+    pub fn get_size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn get_layers(&self) -> i32 {
+        self.layers
+    }
+
+    pub fn get_z_origin(&self) -> i32 {
+        self.z_origin
+    }
+
+    pub fn get_z_range(&self) -> [f32; 2] {
+        [
+            self.z_origin as f32,
+            (self.z_origin + self.layers - 1) as f32,
+        ]
+    }
+
+    pub fn set(&mut self, grid_pos: Point3i, density: f32) {
+        let index = self.get_index(grid_pos);
+        self.value_list[index] = density;
+    }
+
+    /// Positions are clamped on the xy-plane, everything below the field is solid
+    /// and everything above is empty
+    pub fn get(&self, grid_pos: Point3i) -> f32 {
+        if grid_pos[2] < 0 {
+            1.
+        } else if grid_pos[2] >= self.layers {
+            -1.
+        } else {
+            let clamped = Point3i::new(
+                i32::max(0, i32::min(self.size - 1, grid_pos[0])),
+                i32::max(0, i32::min(self.size - 1, grid_pos[1])),
+                grid_pos[2],
+            );
+            self.value_list[self.get_index(clamped)]
+        }
+    }
+
+    /// Trilinear interpolation, x and y are in world units relative to the chunk origin,
+    /// z is absolute
+    pub fn sample(&self, pos: Point3f) -> f32 {
+        let grid = Point3f::new(
+            pos[0] / self.spacing,
+            pos[1] / self.spacing,
+            pos[2] - self.z_origin as f32,
+        );
+        let anchor = Point3i::new(
+            grid[0].floor() as i32,
+            grid[1].floor() as i32,
+            grid[2].floor() as i32,
+        );
+        let t = [
+            grid[0] - anchor[0] as f32,
+            grid[1] - anchor[1] as f32,
+            grid[2] - anchor[2] as f32,
+        ];
+        let mut sum = 0.;
+        for corner in 0..8 {
+            let offset = get_corner_offset(corner);
+            let mut weight = 1.;
+            for axis in 0..3 {
+                weight *= if offset[axis] == 1 {
+                    t[axis]
+                } else {
+                    1. - t[axis]
+                };
+            }
+            if weight > 0. {
+                sum += weight * self.get(anchor + offset);
+            }
+        }
+        sum
+    }
+
+    /// Central differences in world units, pointing towards increasing density
+    pub fn get_gradient(&self, grid_pos: Point3i) -> Point3f {
+        let mut gradient = Point3f::from_scalar(0.);
+        for axis in 0..3 {
+            let mut offset = Point3i::from_scalar(0);
+            offset[axis] = 1;
+            let distance = if axis < 2 { 2. * self.spacing } else { 2. };
+            gradient[axis] = (self.get(grid_pos + offset) - self.get(grid_pos - offset)) / distance;
+        }
+        gradient
+    }
+
+    pub fn triangulate(&self) -> Vec<Triangle> {
+        polygonize(self)
+    }
+
+    fn get_index(&self, grid_pos: Point3i) -> usize {
+        debug_assert!(grid_pos[0] >= 0 && grid_pos[0] < self.size);
+        debug_assert!(grid_pos[1] >= 0 && grid_pos[1] < self.size);
+        debug_assert!(grid_pos[2] >= 0 && grid_pos[2] < self.layers);
+        (grid_pos[0] + grid_pos[1] * self.size + grid_pos[2] * self.size * self.size) as usize
+    }
+}
+
+// Corner i of a unit cube lies at (i & 1, (i >> 1) & 1, (i >> 2) & 1)
+pub fn get_corner_offset(corner: usize) -> Point3i {
+    Point3i::new(
+        (corner & 1) as i32,
+        ((corner >> 1) & 1) as i32,
+        ((corner >> 2) & 1) as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn create_plane_field(height: f32) -> DensityField {
+        let mut field = DensityField::new(5, -4, 9);
+        for z in 0..9 {
+            for y in 0..5 {
+                for x in 0..5 {
+                    field.set(Point3i::new(x, y, z), height - (z - 4) as f32);
+                }
+            }
+        }
+        field
+    }
+
+    #[test]
+    fn test_sample_interpolates() {
+        let field = create_plane_field(0.5);
+        assert!((field.sample(Point3f::new(1.3, 2.7, 0.5))).abs() < 1e-5);
+        assert!((field.sample(Point3f::new(1.3, 2.7, -1.)) - 1.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_outside_layers() {
+        let field = create_plane_field(0.5);
+        assert!(field.get(Point3i::new(0, 0, -1)) > 0.);
+        assert!(field.get(Point3i::new(0, 0, 9)) < 0.);
+    }
+
+    #[test]
+    fn test_gradient_points_down() {
+        let field = create_plane_field(0.5);
+        let gradient = field.get_gradient(Point3i::new(2, 2, 4));
+        assert!((gradient[2] + 1.).abs() < 1e-5);
+    }
+}
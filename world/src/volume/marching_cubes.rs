@@ -0,0 +1,177 @@
+use core::graphics::mesh::{Triangle, Vertex};
+use core::{Point3f, Point3i};
+
+use super::density_field::get_corner_offset;
+use super::DensityField;
+
+/*
+    Marching cubes with every cube decomposed into six tetrahedra
+    around its main diagonal. The decomposition is the same for all cubes,
+    so neighbouring cubes share their face diagonals and the surface is
+    free of cracks, without the ambiguous cases of the classic lookup tables.
+*/
+
+const TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 3, 7],
+    [0, 2, 3, 7],
+    [0, 2, 6, 7],
+    [0, 4, 6, 7],
+    [0, 4, 5, 7],
+    [0, 1, 5, 7],
+];
+
+#[derive(Clone, Copy)]
+struct Sample {
+    pos: Point3i,
+    density: f32,
+}
+
+pub fn polygonize(field: &DensityField) -> Vec<Triangle> {
+    let mut triangles = Vec::new();
+    for z in 0..field.get_layers() - 1 {
+        for y in 0..field.get_size() - 1 {
+            for x in 0..field.get_size() - 1 {
+                let base = Point3i::new(x, y, z);
+                let mut corners = [Sample {
+                    pos: base,
+                    density: 0.,
+                }; 8];
+                for (i, corner) in corners.iter_mut().enumerate() {
+                    corner.pos = base + get_corner_offset(i);
+                    corner.density = field.get(corner.pos);
+                }
+                let solid_count = corners.iter().filter(|c| c.density > 0.).count();
+                if solid_count == 0 || solid_count == 8 {
+                    continue;
+                }
+                for tetrahedron in TETRAHEDRA.iter() {
+                    let samples = [
+                        corners[tetrahedron[0]],
+                        corners[tetrahedron[1]],
+                        corners[tetrahedron[2]],
+                        corners[tetrahedron[3]],
+                    ];
+                    polygonize_tetrahedron(field, &samples, &mut triangles);
+                }
+            }
+        }
+    }
+    triangles
+}
+
+fn polygonize_tetrahedron(
+    field: &DensityField,
+    samples: &[Sample; 4],
+    triangles: &mut Vec<Triangle>,
+) {
+    let (solid, empty): (Vec<Sample>, Vec<Sample>) = samples.iter().partition(|s| s.density > 0.);
+    let edges: Vec<[Sample; 2]> = match (solid.len(), empty.len()) {
+        (1, 3) => empty.iter().map(|e| [solid[0], *e]).collect(),
+        (3, 1) => solid.iter().map(|s| [*s, empty[0]]).collect(),
+        (2, 2) => vec![
+            [solid[0], empty[0]],
+            [solid[0], empty[1]],
+            [solid[1], empty[1]],
+            [solid[1], empty[0]],
+        ],
+        _ => return,
+    };
+    let vertices: Vec<Vertex> = edges
+        .iter()
+        .map(|[a, b]| create_vertex(field, a, b))
+        .collect();
+    add_triangle([vertices[0], vertices[1], vertices[2]], triangles);
+    if vertices.len() == 4 {
+        add_triangle([vertices[0], vertices[2], vertices[3]], triangles);
+    }
+}
+
+// Orients the triangle so that its normal agrees with the outward surface normals
+fn add_triangle(vertices: [Vertex; 3], triangles: &mut Vec<Triangle>) {
+    let outward = vertices
+        .iter()
+        .fold(Point3f::from_scalar(0.), |acc, v| acc + v.get_normal());
+    let mut triangle = Triangle::new(vertices);
+    if triangle.get_normal().dot(&outward) < 0. {
+        let mut swapped = vertices;
+        swapped.swap(0, 1);
+        triangle = Triangle::new(swapped);
+    }
+    triangles.push(triangle);
+}
+
+fn create_vertex(field: &DensityField, solid: &Sample, empty: &Sample) -> Vertex {
+    let t = solid.density / (solid.density - empty.density);
+    let a = Point3f::from(solid.pos);
+    let b = Point3f::from(empty.pos);
+    let gradient_a = field.get_gradient(solid.pos);
+    let gradient_b = field.get_gradient(empty.pos);
+    let gradient = gradient_a + (gradient_b - gradient_a) * t;
+
+    let mut vertex = Vertex::default();
+    let pos = a + (b - a) * t;
+    vertex.set_pos(Point3f::new(
+        pos[0] * field.get_spacing(),
+        pos[1] * field.get_spacing(),
+        pos[2] + field.get_z_origin() as f32,
+    ));
+    if gradient.length() > 0. {
+        vertex.set_normal((gradient * -1.).as_normalized());
+    } else {
+        vertex.set_normal(Point3f::new(0., 0., 1.));
+    }
+    vertex
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn create_sphere_field() -> DensityField {
+        let mut field = DensityField::new(11, 0, 11);
+        for z in 0..11 {
+            for y in 0..11 {
+                for x in 0..11 {
+                    let d = Point3f::new(x as f32 - 5., y as f32 - 5., z as f32 - 5.).length();
+                    field.set(Point3i::new(x, y, z), 3.5 - d);
+                }
+            }
+        }
+        field
+    }
+
+    #[test]
+    fn test_empty_field_no_triangles() {
+        assert!(DensityField::new(4, 0, 4).triangulate().is_empty());
+    }
+
+    #[test]
+    fn test_sphere_vertices_on_surface() {
+        let triangles = create_sphere_field().triangulate();
+        assert!(!triangles.is_empty());
+        for triangle in triangles.iter() {
+            for vertex in triangle.as_vertices().iter() {
+                let d = (vertex.get_pos() - Point3f::from_scalar(5.)).length();
+                assert!((d - 3.5).abs() < 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sphere_triangles_face_outward() {
+        for triangle in create_sphere_field().triangulate().iter() {
+            let center = triangle
+                .as_vertices()
+                .iter()
+                .fold(Point3f::from_scalar(0.), |acc, v| acc + v.get_pos())
+                / 3.;
+            assert!(
+                triangle
+                    .get_normal()
+                    .dot(&(center - Point3f::from_scalar(5.)))
+                    > 0.
+            );
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod density_field;
+mod marching_cubes;
+
+pub use self::density_field::DensityField;
+pub use self::marching_cubes::polygonize;
@@ -1,20 +1,16 @@
 use std::path::Path;
 use std::rc::Rc;
 
-use core::graphics::{
-    GraphicsError, Mesh, Model, ShaderProgram, ShaderProgramBuilder, Texture, TextureBuilder,
-};
+use core::graphics::{GraphicsError, ShaderProgram, ShaderProgramBuilder, Texture, TextureBuilder};
 use core::light::SceneLights;
-use core::traits::{RenderInfo, Renderable, Scalable, Translatable, Updatable};
+use core::traits::{RenderInfo, Updatable};
 use core::{Config, CoreError, FileError, Point2i, Point3f, UpdateError};
 
+// Shared shader and textures of all water surfaces, the surface meshes are owned by the chunks
 pub struct Water {
     shader: Rc<ShaderProgram>,
     normal_map: Texture,
     dudv_map: Texture,
-    model: Model,
-    mesh: Mesh,
-    water_level: f32,
     dudv_offset: f32,
     dudv_offset_per_second: f32,
 }
@@ -69,11 +65,6 @@ impl Water {
             return Err(GraphicsError::from(e).into());
         }
 
-        let mesh_path = Path::new(config.get_str("water_surface_mesh")?)
-            .to_str()
-            .ok_or(FileError::InvalidPath("water_surface_mesh".to_owned()))?
-            .to_owned();
-
         let normal_map_path = Path::new(config.get_str("water_normal_map")?)
             .to_str()
             .ok_or(FileError::InvalidPath("water_normal_map".to_owned()))?
@@ -94,22 +85,10 @@ impl Water {
             .finish()?;
         dudv_map.fill_with_image(&dudv_map_path)?;
 
-        let mut model = Model::default();
-        model.set_scale(Point3f::new(1024., 1024., 1.));
-        model.set_translation(Point3f::new(0., 0., 80.));
-
-        let mesh = match Mesh::from_obj(&mesh_path) {
-            Ok(m) => m,
-            Err(e) => return Err(CoreError::from(GraphicsError::from(e))),
-        };
-
         Ok(Self {
             shader: Rc::new(shader),
             normal_map: normal_map,
             dudv_map: dudv_map,
-            model: model,
-            mesh: mesh,
-            water_level: 0.,
             dudv_offset: 0.,
             dudv_offset_per_second: 5e-3,
         })
@@ -132,40 +111,28 @@ impl Water {
     pub fn get_shader(&self) -> &ShaderProgram {
         &self.shader
     }
-}
 
-impl Updatable for Water {
-    fn tick(&mut self, time_passed: u32) -> Result<(), UpdateError> {
-        self.dudv_offset += self.dudv_offset_per_second * (time_passed as f32 / 1000.);
-        Ok(())
-    }
-}
-
-impl Renderable for Water {
-    fn render<'a>(&self, info: &'a mut RenderInfo) -> Result<(), GraphicsError> {
+    /// Renders the given surfaces with the water shader and textures active
+    pub fn render_surfaces<F>(&self, info: &mut RenderInfo, render: F) -> Result<(), GraphicsError>
+    where
+        F: FnOnce(&mut RenderInfo) -> Result<(), GraphicsError>,
+    {
         info.push_shader(self.shader.clone());
         self.normal_map.activate(0);
         self.dudv_map.activate(1);
 
-        let mvp = info.get_camera().create_mvp_matrix(&self.model);
-        let shader = info.get_active_shader()?;
-        shader.set_resource_mat4("mvp", &mvp)?;
-        shader.set_resource_mat4("model", self.model.get_matrix_ref())?;
-        self.mesh.render(info)?;
+        let result = render(info);
 
         self.dudv_map.deactivate();
         self.normal_map.deactivate();
         info.pop_shader();
-        Ok(())
+        result
     }
 }
 
-impl Translatable for Water {
-    fn set_translation(&mut self, mut new_translation: Point3f) {
-        new_translation[2] = self.water_level;
-        self.model.set_translation(new_translation);
-    }
-    fn get_translation(&self) -> Point3f {
-        self.model.get_translation()
+impl Updatable for Water {
+    fn tick(&mut self, time_passed: u32) -> Result<(), UpdateError> {
+        self.dudv_offset += self.dudv_offset_per_second * (time_passed as f32 / 1000.);
+        Ok(())
     }
 }
@@ -1,13 +1,16 @@
 use rand::rngs::StdRng;
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::architect::Architect;
-use crate::chunk::{ChunkManager, CHUNK_SIZE};
-use crate::{Water, WorldError};
+use crate::chunk::{BuildMetrics, ChunkListener, ChunkManager, WorldLayout, WorldPos};
+use crate::{AnalysisLayer, Brush, HeightSampler, SettlementSite, Water, WorldError};
 use core::graphics::GraphicsError;
 use core::light::{Light, SceneLights};
 use core::traits::{RenderInfo, Renderable, Rotatable, Scalable, Translatable, Updatable};
-use core::{Config, ObjectManager, Player, Point3f, Seed, Skybox, Sun, Timer, UpdateError};
+use core::{
+    Config, ObjectManager, Player, Point2f, Point3f, Seed, Skybox, Sun, Timer, UpdateError,
+};
 
 pub struct World {
     skybox: Skybox,
@@ -20,7 +23,10 @@ pub struct World {
     scene_lights: SceneLights,
     monkey_id: u32,
     center: Point3f,
+    view_direction: Point3f,
     gravity: f32,
+    rebase_distance: f32,
+    settlements: Vec<SettlementSite>,
 }
 
 impl World {
@@ -31,14 +37,35 @@ impl World {
 
         info!("Day length is {}s", day_length);
         info!("Gravity is {}", gravity);
+        let rebase_distance = config.get_float_or_default("rebase_distance", 2048.);
 
         let seed = Seed::from_string("SEAS");
         info!("World seed = {}", seed);
         let mut rng: StdRng = seed.into();
 
         let mut object_manager = ObjectManager::from_yaml(&object_prototypes_path)?;
-        let architect = Arc::new(Architect::from_seed(Seed::from_rng(&mut rng)));
-        let chunk_manager = ChunkManager::new(architect, config)?;
+        let sea_level = config.get_float_or_default("sea_level", 0.);
+        info!("Sea level is {}", sea_level);
+        let layout = WorldLayout::from_config(config);
+        info!(
+            "Chunk size is {} with a resolution of {}",
+            layout.get_chunk_size(),
+            layout.get_resolution()
+        );
+        let mut architect = Architect::from_seed(Seed::from_rng(&mut rng))
+            .with_sea_level(sea_level)
+            .with_layout(layout);
+        let spawn_region = architect.get_settlement_region(Point2f::from_scalar(0.));
+        let settlements = architect.get_settlement_sites(spawn_region);
+        info!(
+            "Found {} settlement sites around the spawn",
+            settlements.len()
+        );
+        if config.get_bool_or_default("settlement_roads", true) {
+            connect_settlements(&mut architect, &settlements);
+        }
+        add_roads(&mut architect, config);
+        let chunk_manager = ChunkManager::new(Arc::new(architect), config)?;
 
         let monkey_id = object_manager.create_object("monkey", true)?;
         object_manager.mod_object(monkey_id, |o| {
@@ -47,7 +74,9 @@ impl World {
         });
 
         let mut skybox = Skybox::new(config)?;
-        skybox.scale((config.get_int_or_default("active_radius", 50) * CHUNK_SIZE * 2) as f32);
+        skybox.scale(
+            config.get_int_or_default("active_radius", 50) as f32 * layout.get_chunk_size() * 2.,
+        );
 
         let world = World {
             skybox: skybox,
@@ -60,7 +89,10 @@ impl World {
             scene_lights: create_default_scene_lights(),
             monkey_id: monkey_id,
             center: Point3f::new(0., 0., 0.),
+            view_direction: Point3f::new(1., 0., 0.),
             gravity: gravity,
+            rebase_distance: rebase_distance,
+            settlements: settlements,
         };
 
         Ok(world)
@@ -70,12 +102,9 @@ impl World {
         let player_pos = player.get_translation();
 
         let chunk_height = self.chunk_manager.get_height(player_pos);
-        let player_pos_xy = player_pos.as_xy();
+        let gradient = self.chunk_manager.sample_gradient(player_pos.as_xy());
         let forward_xy = player.get_direction().as_xy().as_normalized();
-        let forward_height = self
-            .chunk_manager
-            .get_height((player_pos_xy + forward_xy).extend(0.));
-        let forward_z = forward_height - chunk_height;
+        let forward_z = gradient.dot(&forward_xy);
         player.update_forward(forward_xy.extend(forward_z).as_normalized());
 
         let height_diff = player.get_z() - chunk_height;
@@ -98,6 +127,121 @@ impl World {
         self.center = pos;
     }
 
+    /// Chunks in view direction are loaded first
+    pub fn set_view_direction(&mut self, direction: Point3f) {
+        self.view_direction = direction;
+    }
+
+    /// Moves the render origin to the chunk containing the center, once the center is further
+    /// away from the origin than the rebase distance. Objects and lights of the world are moved
+    /// along, returns the offset by which all other positions kept relative to the render origin,
+    /// like the player and the camera, have to be moved.
+    pub fn rebase(&mut self, center: Point3f) -> Option<Point3f> {
+        if center.as_xy().length() <= self.rebase_distance {
+            return None;
+        }
+        let layout = self.get_layout();
+        let shift = layout.get_chunk_pos(center);
+        let offset = layout.get_world_pos(shift, None).extend(0.) * -1.;
+        let origin = self.chunk_manager.get_origin() + shift;
+        self.chunk_manager.set_origin(origin);
+        self.center = center + offset;
+        self.object_manager.translate_all(offset);
+        self.scene_lights.translate_all(offset);
+        info!("Moved render origin to chunk {}", origin);
+        Some(offset)
+    }
+
+    /// Position in the world of a position relative to the render origin
+    pub fn get_world_pos(&self, render_pos: Point3f) -> WorldPos {
+        WorldPos::new(
+            &self.get_layout(),
+            self.chunk_manager.get_origin(),
+            render_pos,
+        )
+    }
+
+    pub fn get_render_pos(&self, world_pos: &WorldPos) -> Point3f {
+        let layout = self.get_layout();
+        let origin = WorldPos::new(
+            &layout,
+            self.chunk_manager.get_origin(),
+            Point3f::from_scalar(0.),
+        );
+        origin.get_offset_to(&layout, world_pos)
+    }
+
+    /// First intersection of the ray with the terrain surface within the given distance
+    pub fn raycast(
+        &self,
+        origin: Point3f,
+        direction: Point3f,
+        max_distance: f32,
+    ) -> Option<Point3f> {
+        self.chunk_manager.raycast(origin, direction, max_distance)
+    }
+
+    pub fn apply_brush(
+        &mut self,
+        brush: &Brush,
+        world_pos: Point2f,
+        intensity: f32,
+    ) -> Result<(), WorldError> {
+        self.chunk_manager
+            .apply_brush(brush, world_pos, intensity)
+            .map_err(WorldError::from)
+    }
+
+    /// The listener is called when chunks are generated, loaded, unloaded or modified
+    pub fn subscribe_chunk_events(&mut self, listener: ChunkListener) -> u32 {
+        self.chunk_manager.subscribe(listener)
+    }
+
+    pub fn unsubscribe_chunk_events(&mut self, id: u32) -> bool {
+        self.chunk_manager.unsubscribe(id)
+    }
+
+    pub fn get_build_metrics(&self) -> Result<BuildMetrics, WorldError> {
+        Ok(self.chunk_manager.get_build_metrics()?)
+    }
+
+    /// CSV for a `.csv` extension, JSON otherwise
+    pub fn export_build_metrics(&self, path: &Path) -> Result<(), WorldError> {
+        Ok(self.chunk_manager.export_build_metrics(path)?)
+    }
+
+    /// Water surface level at the given world position, none if there is no water
+    pub fn get_water_level(&self, world_pos: Point2f) -> Option<f32> {
+        self.chunk_manager.get_water_level(world_pos)
+    }
+
+    pub fn get_layout(&self) -> WorldLayout {
+        self.chunk_manager.get_layout()
+    }
+
+    /// Sites in the region around the spawn, known before any chunk is loaded.
+    /// Positions are absolute, not relative to the render origin.
+    pub fn get_settlements(&self) -> &[SettlementSite] {
+        self.settlements.as_slice()
+    }
+
+    /// Dominant surface material, as configured in the surface info file
+    pub fn get_surface_material(&self, world_pos: Point2f) -> Option<&str> {
+        self.chunk_manager.get_surface_material(world_pos)
+    }
+
+    /// Between zero off-road and one on the road surface
+    pub fn get_road_coverage(&self, world_pos: Point2f) -> f32 {
+        self.chunk_manager.get_road_coverage(world_pos)
+    }
+
+    /// Analysis layer value at the given world position, zero where no analysis is available
+    pub fn sample_analysis_layer(&self, layer: AnalysisLayer, world_pos: Point2f) -> f32 {
+        self.chunk_manager
+            .get_layer_sampler(layer)
+            .sample_bilinear(world_pos)
+    }
+
     fn update_shader_resources(&mut self) -> Result<(), GraphicsError> {
         match self.scene_lights.get_light_mut("sun") {
             Some(sun_light) => {
@@ -145,7 +289,9 @@ impl Renderable for World {
         self.surface_texture.deactivate();*/
         // self.object_manager.render(info)?;
         self.chunk_manager.render(info)?;
-        self.water_surface.render(info)?;
+        let chunk_manager = &self.chunk_manager;
+        self.water_surface
+            .render_surfaces(info, |info| chunk_manager.render_water(info))?;
         self.skybox.render(info)?;
         Ok(())
     }
@@ -157,7 +303,7 @@ impl Updatable for World {
         self.decoration_timer.tick(time_passed)?;
         if self.chunk_update_timer.fires() {
             self.chunk_manager
-                .request(self.center)
+                .request(self.center, self.view_direction)
                 .map_err(|e| UpdateError::Internal(e.to_string()))?;
         }
 
@@ -166,7 +312,6 @@ impl Updatable for World {
         self.skybox.set_translation(self.center);
         self.sun.set_rotation_center(self.center);
         self.sun.tick(time_passed)?;
-        self.water_surface.set_translation(self.center);
         self.water_surface.tick(time_passed)?;
 
         let sun_pos = self.sun.calculate_position();
@@ -182,6 +327,56 @@ impl Updatable for World {
     }
 }
 
+// Connects the settlements along a minimum spanning tree of their distances
+fn connect_settlements(architect: &mut Architect, settlements: &[SettlementSite]) {
+    let positions: Vec<Point2f> = settlements.iter().map(|s| s.get_pos().as_xy()).collect();
+    let mut connected = vec![false; positions.len()];
+    if let Some(first) = connected.first_mut() {
+        *first = true;
+    }
+    for _ in 1..positions.len() {
+        let mut closest: Option<(usize, usize, f32)> = None;
+        for (from, from_pos) in positions.iter().enumerate().filter(|(i, _)| connected[*i]) {
+            for (to, to_pos) in positions.iter().enumerate().filter(|(i, _)| !connected[*i]) {
+                let distance = (*to_pos - *from_pos).length();
+                match closest {
+                    Some((_, _, d)) if d <= distance => {}
+                    _ => closest = Some((from, to, distance)),
+                }
+            }
+        }
+        if let Some((from, to, _)) = closest {
+            connected[to] = true;
+            match architect.plan_road(positions[from], positions[to]) {
+                Some(road) => architect.add_road(road),
+                None => warn!(
+                    "Could not connect settlements at {} and {}",
+                    positions[from], positions[to]
+                ),
+            }
+        }
+    }
+}
+
+// Connects consecutive road waypoints from the config
+fn add_roads(architect: &mut Architect, config: &Config) {
+    let waypoints = config.get_point_list("road_waypoints").unwrap_or_default();
+    for pair in waypoints.windows(2) {
+        match architect.plan_road(pair[0], pair[1]) {
+            Some(road) => {
+                info!(
+                    "Planned road from {} to {}, length = {:.0}",
+                    pair[0],
+                    pair[1],
+                    road.get_length()
+                );
+                architect.add_road(road);
+            }
+            None => warn!("Could not find a road from {} to {}", pair[0], pair[1]),
+        }
+    }
+}
+
 fn create_default_scene_lights() -> SceneLights {
     let mut scene_lights = SceneLights::default();
 
@@ -1,9 +1,13 @@
+use std::path::PathBuf;
+
 use core::{
-    Camera, Config, Float, Input, Player, Point3f, RenderInfo, Renderable, Rotatable, State,
-    StateError, Translatable, Updatable,
+    Camera, Config, Float, Input, Player, Point3f, RenderInfo, Renderable, Rotatable, Seed, State,
+    StateError, Translatable, Updatable, UpdateError,
 };
 
-use crate::{World, CHUNK_SIZE};
+use crate::{Brush, BrushKind, NoiseBuilder, World};
+
+const BRUSH_RANGE: f32 = 256.;
 
 pub struct WorldState {
     camera: Camera,
@@ -11,6 +15,10 @@ pub struct WorldState {
     world: World,
     mouse_sensitivity: f32,
     fly_mode: bool,
+    brushes: Vec<Brush>,
+    active_brush: usize,
+    sculpting: bool,
+    build_metrics_file: PathBuf,
 }
 
 impl WorldState {
@@ -19,7 +27,11 @@ impl WorldState {
         let player = Player::default();
         let world = World::new(&config).map_err(|e| StateError::Setup(e.to_string()))?;
 
-        camera.set_far((CHUNK_SIZE * config.get_int_or_default("active_radius", 50)) as f32 * 8.);
+        camera.set_far(
+            world.get_layout().get_chunk_size()
+                * config.get_int_or_default("active_radius", 50) as f32
+                * 8.,
+        );
 
         Ok(WorldState {
             camera: camera,
@@ -27,6 +39,12 @@ impl WorldState {
             world: world,
             mouse_sensitivity: config.get_float_or_default("mouse_sensitivity", 0.3),
             fly_mode: false,
+            brushes: create_brushes(config),
+            active_brush: 0,
+            sculpting: false,
+            build_metrics_file: PathBuf::from(
+                config.get_str_or_default("build_metrics_file", "build_metrics.json"),
+            ),
         })
     }
 }
@@ -46,6 +64,9 @@ impl State for WorldState {
             self.update_player(input)?;
         }
         self.update_camera();
+        self.update_brush_selection(input);
+        self.update_metrics_export(input);
+        self.update_sculpting(input)?;
         self.update_world(input)?;
         Ok(())
     }
@@ -62,8 +83,55 @@ impl WorldState {
         self.player.align_camera(&mut self.camera);
     }
 
+    fn update_brush_selection(&mut self, input: &mut Input) {
+        for i in 0..self.brushes.len() {
+            let key = (i + 1).to_string();
+            if input.key_pressed(&key) > 0 {
+                self.active_brush = i;
+                info!("Selected brush: {}", self.brushes[i].get_kind());
+                input.clear_key(&key);
+            }
+        }
+    }
+
+    fn update_metrics_export(&mut self, input: &mut Input) {
+        if input.key_pressed("F4") > 0 {
+            if let Err(e) = self.world.export_build_metrics(&self.build_metrics_file) {
+                error!("Could not export chunk build metrics: {}", e);
+            }
+            input.clear_key("F4");
+        }
+    }
+
+    // Applies the active brush where the view ray hits the terrain, while the left mouse button is held
+    fn update_sculpting(&mut self, input: &Input) -> Result<(), StateError> {
+        if input.key_pressed("MOUSE_LEFT") == 0 {
+            self.sculpting = false;
+            return Ok(());
+        }
+        let origin = self.camera.get_translation();
+        let direction = self.camera.get_direction();
+        if let Some(hit) = self.world.raycast(origin, direction, BRUSH_RANGE) {
+            let brush = &mut self.brushes[self.active_brush];
+            if !self.sculpting {
+                brush.set_flatten_height(hit[2]);
+                self.sculpting = true;
+            }
+            let intensity = input.get_time_passed() as f32 / 1000.;
+            self.world
+                .apply_brush(brush, hit.as_xy(), intensity)
+                .map_err(|e| UpdateError::Internal(e.to_string()))?;
+        }
+        Ok(())
+    }
+
     fn update_world(&mut self, input: &mut Input) -> Result<(), StateError> {
+        if let Some(offset) = self.world.rebase(self.player.get_translation()) {
+            self.player.mod_translation(offset);
+            self.update_camera();
+        }
         self.world.set_center(self.player.get_translation());
+        self.world.set_view_direction(self.camera.get_direction());
         self.world.tick(input.get_time_passed())?;
         Ok(())
     }
@@ -142,3 +210,21 @@ impl WorldState {
         }
     }
 }
+
+// Selected by the number keys, in this order
+fn create_brushes(config: &Config) -> Vec<Brush> {
+    let radius = config.get_float_or_default("brush_radius", 8.);
+    let strength = config.get_float_or_default("brush_strength", 4.);
+    let stamp_noise = NoiseBuilder::new()
+        .seed(Seed::from_entropy())
+        .octaves(2)
+        .scale(0.1)
+        .finish();
+    vec![
+        Brush::new(BrushKind::Raise, radius).with_strength(strength),
+        Brush::new(BrushKind::Lower, radius).with_strength(strength),
+        Brush::new(BrushKind::Flatten(0.), radius).with_strength(strength / 2.),
+        Brush::new(BrushKind::Smooth, radius).with_strength(strength / 2.),
+        Brush::new(BrushKind::NoiseStamp(stamp_noise), radius).with_strength(strength),
+    ]
+}